@@ -1,6 +1,10 @@
 pub mod sdm;
 pub use sdm::distributions;
 
+// The model macros refer to `sdm_engine::...`, so the tests can use them too
+#[cfg(test)]
+extern crate self as sdm_engine;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -9,4 +13,1804 @@ mod tests {
     fn declare_sdm() -> Result<(), String> {
         Ok(())
     }
+
+    fn sample_moments(distrib: &dyn distributions::Distrib, n: usize) -> (f64, f64) {
+        let samples: Vec<f64> = (0..n).map(|_| distrib.gen()).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+
+        (mean, variance)
+    }
+
+    #[test]
+    fn continuous_distributions_match_theoretical_moments() -> Result<(), String> {
+        let distribs: Vec<(&str, Box<dyn distributions::Distrib>)> = vec![
+            ("Triangular", Box::new(distributions::Triangular::new(1.0, 3.0, 8.0).unwrap())),
+            ("Lognormal", Box::new(distributions::Lognormal::new(1.0, 0.4).unwrap())),
+            ("Weibull", Box::new(distributions::Weibull::new(1.5, 4.0).unwrap())),
+            ("Gamma", Box::new(distributions::Gamma::new(0.7, 2.0).unwrap())),
+            ("Erlang", Box::new(distributions::Erlang::new(3, 2.0).unwrap())),
+            ("Beta", Box::new(distributions::Beta::new_scaled(2.0, 5.0, 10.0, 20.0).unwrap())),
+            ("Pareto", Box::new(distributions::Pareto::new(2.0, 6.0).unwrap())),
+            ("JohnsonSU", Box::new(distributions::JohnsonSU::new(0.5, 2.0, 10.0, 3.0).unwrap())),
+        ];
+
+        let n = 200_000;
+        for (name, distrib) in distribs.iter() {
+            let (mean, variance) = sample_moments(distrib.as_ref(), n);
+            let expected_mean = distrib.mean().unwrap();
+            let expected_variance = distrib.variance().unwrap();
+
+            if (mean - expected_mean).abs() > 5.0 * (expected_variance / n as f64).sqrt() {
+                return Err(format!("{}: sample mean {} != {}", name, mean, expected_mean));
+            }
+
+            if (variance - expected_variance).abs() > 0.05 * expected_variance {
+                return Err(format!("{}: sample variance {} != {}", name, variance, expected_variance));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn discrete_distributions_match_theoretical_moments() -> Result<(), String> {
+        use sdm::discrete::*;
+
+        let distribs: Vec<(&str, Box<dyn DiscreteDistrib>)> = vec![
+            ("Bernoulli", Box::new(Bernoulli::new(0.3).unwrap())),
+            ("DiscreteUniform", Box::new(DiscreteUniform::new(1, 4).unwrap())),
+            ("DiscreteUniform (extreme bounds)", Box::new(DiscreteUniform::new(i64::MIN, i64::MAX).unwrap())),
+            ("Poisson", Box::new(Poisson::new(4.5).unwrap())),
+            ("Poisson (rejection)", Box::new(Poisson::new(12.0).unwrap())),
+            ("Poisson (large mean)", Box::new(Poisson::new(120.0).unwrap())),
+            ("Poisson (huge mean)", Box::new(Poisson::new(1e7).unwrap())),
+            ("Binomial", Box::new(Binomial::new(40, 0.7).unwrap())),
+            ("Geometric", Box::new(Geometric::new(0.25).unwrap())),
+            ("NegativeBinomial", Box::new(NegativeBinomial::new(3, 0.4).unwrap())),
+            ("DiscreteTable", Box::new(DiscreteTable::new(&[(1, 0.2), (2, 0.5), (6, 0.3)]).unwrap())),
+        ];
+
+        let invalid = [
+            Geometric::new(f64::NAN).is_err(),
+            NegativeBinomial::new(3, f64::NAN).is_err(),
+            DiscreteTable::new(&[(1, f64::NAN), (2, 1.0)]).is_err(),
+            DiscreteTable::new(&[(1, f64::INFINITY)]).is_err(),
+        ];
+        if invalid.contains(&false) {
+            return Err(format!("NaN probabilities were accepted: {:?}", invalid));
+        }
+
+        let n = 100_000;
+        for (name, distrib) in distribs.iter() {
+            let samples: Vec<f64> = (0..n).map(|_| distrib.gen() as f64).collect();
+            let mean = samples.iter().sum::<f64>() / n as f64;
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            let expected_mean = distrib.mean().unwrap();
+            let expected_variance = distrib.variance().unwrap();
+
+            if (mean - expected_mean).abs() > 5.0 * (expected_variance / n as f64).sqrt() {
+                return Err(format!("{}: sample mean {} != {}", name, mean, expected_mean));
+            }
+
+            if (variance - expected_variance).abs() > 0.05 * expected_variance {
+                return Err(format!("{}: sample variance {} != {}", name, variance, expected_variance));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn trace_replay_reads_csv_column_in_order() -> Result<(), String> {
+        use sdm::distributions::Distrib;
+
+        let path = std::env::temp_dir().join(format!("sdm_trace_{}.csv", std::process::id()));
+        let contents = "order,\"cook, time\"\n1,12.5\n\"2, \"\"rush\"\"\",9.0\n3,\"15.25\"\n";
+        std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+
+        let column = "cook, time";
+        let trace = sdm::TraceReplay::from_csv(&path, column).map_err(|e| e.to_string())?;
+        let looping = sdm::TraceReplay::from_csv(&path, column).map_err(|e| e.to_string())?.with_wrap_around();
+        let empirical = sdm::EmpiricalContinuous::from_csv(&path, column).map_err(|e| e.to_string())?;
+        let missing = sdm::TraceReplay::from_csv(&path, "serve_time");
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+
+        let replayed: Vec<Option<f64>> = (0..4).map(|_| trace.next_value()).collect();
+        if replayed != vec![Some(12.5), Some(9.0), Some(15.25), None] || !trace.is_exhausted() {
+            return Err(format!("Unexpected trace {:?}", replayed));
+        }
+
+        let looped: Vec<f64> = (0..4).map(|_| looping.gen()).collect();
+        if looped != vec![12.5, 9.0, 15.25, 12.5] {
+            return Err(format!("Unexpected wrapped-around trace {:?}", looped));
+        }
+
+        let non_finite = [vec![1.0, f64::NAN], vec![f64::INFINITY]];
+        if non_finite.into_iter().any(|values| sdm::TraceReplay::new(values).is_ok())
+            || sdm::EmpiricalDiscrete::new(&[1.0, f64::NAN]).is_ok()
+        {
+            return Err("Non-finite observations were accepted".to_string());
+        }
+
+        if (0..1000).map(|_| empirical.gen()).any(|x| !(9.0..=15.25).contains(&x)) {
+            return Err("Empirical sample outside of observed range".to_string());
+        }
+
+        if missing.is_ok() {
+            return Err("Missing column was accepted".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn combinators_respect_bounds_and_moments() -> Result<(), String> {
+        use sdm::distributions::Distrib;
+
+        let truncated_gaussian = sdm::Truncated::new(sdm::Gaussian::new(1.0, 4.0), 0.0, f64::INFINITY).unwrap();
+        let truncated_exponential = sdm::Truncated::new(sdm::Exponential::new(10.0), 2.0, 5.0).unwrap();
+        if (0..10_000).any(|_| truncated_gaussian.gen() < 0.0) {
+            return Err("Truncated gaussian generated a negative value".to_string());
+        }
+
+        if (0..10_000).map(|_| truncated_exponential.gen()).any(|x| !(2.0..=5.0).contains(&x)) {
+            return Err("Truncated exponential generated a value outside its bounds".to_string());
+        }
+
+        // Without a CDF, samples by rejection when the interval is likely enough
+        struct SamplesOnly(sdm::Gaussian);
+        impl Distrib for SamplesOnly {
+            fn gen(&self) -> f64 {
+                self.0.gen()
+            }
+        }
+
+        let rejecting = sdm::Truncated::new(SamplesOnly(sdm::Gaussian::new(0.0, 1.0)), 0.0, f64::INFINITY).unwrap();
+        if (0..10_000).any(|_| rejecting.gen() < 0.0) {
+            return Err("Rejection sampling generated a negative value".to_string());
+        }
+
+        if sdm::Truncated::new(SamplesOnly(sdm::Gaussian::new(0.0, 1.0)), 6.0, f64::INFINITY).is_ok() {
+            return Err("An interval too unlikely to reject into was accepted".to_string());
+        }
+
+        let mixture = sdm::Mixture::new(vec![
+            (1.0, Box::new(sdm::Constant(2.0))),
+            (3.0, Box::new(sdm::Affine::new(sdm::Uniform::new(0.0, 1.0), 4.0, 10.0).unwrap())),
+        ])
+        .unwrap();
+        let expected = 0.25 * 2.0 + 0.75 * 12.0;
+        if (mixture.mean().unwrap() - expected).abs() > 1e-5 {
+            return Err(format!("Mixture mean {} != {}", mixture.mean().unwrap(), expected));
+        }
+
+        let (mean, _) = sample_moments(&mixture, 100_000);
+        if (mean - expected).abs() > 0.1 {
+            return Err(format!("Mixture sample mean {} != {}", mean, expected));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fitting_recovers_parameters_and_ranks_candidates() -> Result<(), String> {
+        use sdm::distributions::Distrib;
+        use sdm::fitting::{fit, fit_all, Criterion, Family};
+
+        sdm::distributions::set_seed(3);
+
+        let source = sdm::Gamma::new(3.0, 2.0).unwrap();
+        let samples: Vec<f64> = (0..5_000).map(|_| source.gen()).collect();
+
+        let gamma = fit(&samples, Family::Gamma).map_err(|e| e.to_string())?;
+        let shape = gamma.parameters[0].1;
+        if (shape - 3.0).abs() > 0.3 {
+            return Err(format!("Fitted gamma shape {} != 3", shape));
+        }
+
+        if gamma.kolmogorov_smirnov_p_value < 0.001 {
+            return Err(format!("True family rejected: {}", gamma));
+        }
+
+        // Bounds and percentiles are noisy estimates, so check the fitted moments rather than parameters
+        let candidates: Vec<(Family, Box<dyn Distrib>)> = vec![
+            (Family::Beta, Box::new(sdm::Beta::new_scaled(2.0, 5.0, 10.0, 20.0).unwrap())),
+            (Family::Erlang, Box::new(sdm::Erlang::new(4, 2.0).unwrap())),
+            (Family::Pareto, Box::new(sdm::Pareto::new(2.0, 5.0).unwrap())),
+            (Family::JohnsonSU, Box::new(sdm::JohnsonSU::new(0.5, 2.0, 10.0, 3.0).unwrap())),
+        ];
+        for (family, source) in candidates {
+            let samples: Vec<f64> = (0..5_000).map(|_| source.gen()).collect();
+            let fitted = fit(&samples, family).map_err(|e| e.to_string())?;
+            let (mean, variance) = (source.mean().unwrap(), source.variance().unwrap());
+            let fitted_mean = fitted.distribution.mean().unwrap_or(f64::NAN);
+            if (fitted_mean - mean).abs() > 0.1 * variance.sqrt() || fitted.kolmogorov_smirnov_p_value < 0.001 {
+                return Err(format!("{} does not fit {}", fitted, family));
+            }
+        }
+
+        let ranked = fit_all(&samples, Criterion::AndersonDarling).map_err(|e| e.to_string())?;
+        let rank = |family| ranked.iter().position(|fit| fit.family == family).unwrap();
+        if rank(Family::Gamma) > rank(Family::Uniform) || rank(Family::Gamma) > rank(Family::Exponential) {
+            return Err(format!("Unexpected ranking: {:?}", ranked.iter().map(|f| f.family).collect::<Vec<_>>()));
+        }
+
+        let mut corrupted = samples.clone();
+        corrupted[10] = f64::NAN;
+        let best = sdm::fitting::best_fit(&corrupted, Criterion::ChiSquare);
+        if fit(&corrupted, Family::Gaussian).is_ok() || best.is_ok() {
+            return Err("Non-finite samples were fitted".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn continuous_distributions_reject_invalid_parameters() -> Result<(), String> {
+        let invalid = [
+            distributions::Triangular::new(5.0, 1.0, 8.0).is_err(),
+            distributions::Lognormal::new(1.0, 0.0).is_err(),
+            distributions::Weibull::new(-1.0, 1.0).is_err(),
+            distributions::Gamma::new(1.0, 0.0).is_err(),
+            distributions::Erlang::new(0, 1.0).is_err(),
+            distributions::Beta::new_scaled(1.0, 1.0, 5.0, 5.0).is_err(),
+            distributions::Pareto::new(0.0, 2.0).is_err(),
+            distributions::JohnsonSU::new(0.0, 0.0, 0.0, 1.0).is_err(),
+            distributions::Triangular::new(f64::NAN, 1.0, 8.0).is_err(),
+            distributions::Lognormal::new(1.0, f64::NAN).is_err(),
+            distributions::Lognormal::from_mean_std(f64::INFINITY, 1.0).is_err(),
+            distributions::Weibull::new(f64::NAN, 1.0).is_err(),
+            distributions::Gamma::new(1.0, f64::NAN).is_err(),
+            distributions::Erlang::new(2, f64::NAN).is_err(),
+            distributions::Beta::new_scaled(f64::NAN, 1.0, 0.0, 1.0).is_err(),
+            distributions::Beta::new_scaled(1.0, 1.0, 0.0, f64::NAN).is_err(),
+            distributions::Pareto::new(1.0, f64::NAN).is_err(),
+            distributions::JohnsonSU::new(f64::NAN, 1.0, 0.0, 1.0).is_err(),
+        ];
+
+        if invalid.iter().all(|rejected| *rejected) {
+            Ok(())
+        } else {
+            Err("Invalid parameters were accepted".to_string())
+        }
+    }
+
+    struct RecordTime {
+        executed: std::rc::Rc<std::cell::RefCell<Vec<f64>>>,
+    }
+
+    impl sdm::Event for RecordTime {
+        fn name(&self) -> &str {
+            "Record time"
+        }
+
+        fn execute(&mut self) {
+            self.executed.borrow_mut().push(sdm::Scheduler::time());
+        }
+    }
+
+    #[test]
+    fn long_horizons_keep_event_ordering_exact() -> Result<(), String> {
+        use rand::seq::SliceRandom;
+
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        let executed = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+
+        // One millisecond apart, one simulated week in, where `f32` only resolves 1/16 s
+        let week = 7.0 * 24.0 * 3600.0;
+        let expected: Vec<f64> = (0..500).map(|k| week + k as f64 * 0.001).collect();
+        let mut shuffled = expected.clone();
+        shuffled.shuffle(&mut rand::thread_rng());
+
+        for time in shuffled {
+            let event = RecordTime {
+                executed: executed.clone(),
+            };
+            scheduler.schedule_at(Box::new(event), time);
+        }
+
+        while !scheduler.simulate_one_step() {}
+        sdm::Scheduler::destroy();
+
+        let executed = executed.borrow();
+        if *executed != expected {
+            return Err("Events were not executed at their exact scheduled times".to_string());
+        }
+
+        if executed.windows(2).any(|w| w[0] >= w[1]) {
+            return Err("Events were not executed in strictly increasing time order".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn calendar_maps_times_to_dates_and_opening_hours() -> Result<(), String> {
+        use sdm::time::*;
+
+        if Duration::hours(1.5).as_unit(TimeUnit::Minutes) != 90.0 {
+            return Err("1.5 h is not 90 min".to_string());
+        }
+
+        // Leap day, one day before Saturday 2024-03-02
+        let start = DateTime::new(2024, 2, 29, 22, 30, 0.0).map_err(|e| e.to_string())?;
+        let calendar = Calendar::new(start, TimeUnit::Minutes);
+        let next_day = calendar.date_time(24.0 * 60.0 + 45.0);
+        if next_day.to_string() != "2024-03-01 23:15:00" || next_day.weekday() != Weekday::Friday {
+            return Err(format!("Unexpected date {} ({:?})", next_day, next_day.weekday()));
+        }
+
+        if DateTime::new(2023, 2, 29, 0, 0, 0.0).is_ok() {
+            return Err("2023-02-29 was accepted".to_string());
+        }
+
+        let opening_hours =
+            WeeklySchedule::new(Duration::hours(11.0), Duration::hours(23.0), &[Weekday::Monday])
+                .map_err(|e| e.to_string())?;
+
+        // Saturday 23:15 reopens on Sunday 11:00, Sunday 23:15 skips Monday
+        let saturday = DateTime::new(2024, 3, 2, 23, 15, 0.0).map_err(|e| e.to_string())?;
+        let sunday = saturday + Duration::days(1.0);
+        let openings = [
+            opening_hours.next_opening(saturday).to_string(),
+            opening_hours.next_opening(sunday).to_string(),
+        ];
+        if openings != ["2024-03-03 11:00:00", "2024-03-05 11:00:00"] {
+            return Err(format!("Unexpected openings {:?}", openings));
+        }
+
+        let reopening = opening_hours.next_opening_at(&calendar, calendar.time(saturday));
+        if !opening_hours.is_open_at(&calendar, reopening) || opening_hours.is_open_at(&calendar, 45.0) {
+            return Err("Opening hours do not follow the calendar".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn quantiles_invert_cdfs_and_pdfs_match_cdf_slopes() -> Result<(), String> {
+        use sdm::*;
+
+        let distribs: Vec<(&str, Box<dyn Distrib>)> = vec![
+            ("Gaussian", Box::new(Gaussian::new(10.0, 3.0))),
+            ("Exponential", Box::new(Exponential::new(2.0))),
+            ("Triangular", Box::new(Triangular::new(1.0, 3.0, 8.0).unwrap())),
+            ("Lognormal", Box::new(Lognormal::new(1.0, 0.4).unwrap())),
+            ("Weibull", Box::new(Weibull::new(1.5, 4.0).unwrap())),
+            ("Gamma", Box::new(Gamma::new(0.7, 2.0).unwrap())),
+            ("Erlang", Box::new(Erlang::new(3, 2.0).unwrap())),
+            ("Beta", Box::new(Beta::new_scaled(2.0, 5.0, 10.0, 20.0).unwrap())),
+            ("Pareto", Box::new(Pareto::new(2.0, 6.0).unwrap())),
+            ("JohnsonSU", Box::new(JohnsonSU::new(0.5, 2.0, 10.0, 3.0).unwrap())),
+            ("EmpiricalContinuous", Box::new(EmpiricalContinuous::new(&[1.0, 2.0, 2.5, 4.0, 7.0, 8.0]).unwrap())),
+            ("Maximum", Box::new(Maximum::new(Exponential::new(1.0), Uniform::new(0.0, 2.0)))),
+            (
+                "Mixture",
+                Box::new(
+                    Mixture::new(vec![
+                        (1.0, Box::new(Gaussian::new(0.0, 1.0))),
+                        (3.0, Box::new(Gaussian::new(5.0, 2.0))),
+                    ])
+                    .unwrap(),
+                ),
+            ),
+        ];
+
+        for (name, distrib) in distribs.iter() {
+            for p in [0.01, 0.1, 0.5, 0.9, 0.99] {
+                let x = distrib.quantile(p).ok_or(format!("{} has no quantile", name))?;
+                let cdf = distrib.cdf(x).unwrap();
+                if (cdf - p).abs() > 1e-6 {
+                    return Err(format!("{}: cdf(quantile({})) = {}", name, p, cdf));
+                }
+
+                let h = 1e-3 * x.abs().max(0.1);
+                let slope = (distrib.cdf(x + h).unwrap() - distrib.cdf(x - h).unwrap()) / (2.0 * h);
+                let pdf = distrib.pdf(x).ok_or(format!("{} has no pdf", name))?;
+                if (pdf - slope).abs() > 1e-2 * pdf {
+                    return Err(format!("{}: pdf({}) = {} but the cdf slope is {}", name, x, pdf, slope));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn sobol_and_latin_hypercube_stratify_inputs() -> Result<(), String> {
+        use sdm::qmc::*;
+        use sdm::{
+            Bernoulli, Binomial, DiscreteDistrib, DiscreteTable, DiscreteUniform, Distrib, Exponential, Geometric,
+            NegativeBinomial, Poisson,
+        };
+
+        let first: Vec<Vec<f64>> = Sobol::new(2).unwrap().take(4).collect();
+        if first != [[0.0, 0.0], [0.5, 0.5], [0.75, 0.25], [0.25, 0.75]] {
+            return Err(format!("Unexpected Sobol points {:?}", first));
+        }
+
+        let n = 100;
+        let design = latin_hypercube(n, 3);
+        for dimension in 0..3 {
+            let mut strata: Vec<usize> = design.iter().map(|point| (point[dimension] * n as f64) as usize).collect();
+            strata.sort_unstable();
+            if strata != (0..n).collect::<Vec<_>>() {
+                return Err(format!("Dimension {} is not stratified", dimension));
+            }
+        }
+
+        // Quasi-random estimates of the mean of an exponential converge much faster than n^-1/2
+        let exponential = Exponential::new(2.0);
+        let points: Vec<Vec<f64>> = Sobol::new_shifted(1).unwrap().take(4096).collect();
+        let samples = inverse_transform(&[&exponential], &points).map_err(|e| e.to_string())?;
+        let sobol_mean = samples.iter().map(|point| point[0]).sum::<f64>() / samples.len() as f64;
+
+        let uniforms = latin_hypercube(4096, 1).into_iter().map(|point| point[0]).collect();
+        let stratified = QuasiRandom::new(Exponential::new(2.0), uniforms).map_err(|e| e.to_string())?;
+        let lhs_mean = (0..4096).map(|_| stratified.gen()).sum::<f64>() / 4096.0;
+
+        for (name, mean) in [("Sobol", sobol_mean), ("Latin hypercube", lhs_mean)] {
+            if (mean - 2.0).abs() > 0.01 {
+                return Err(format!("{} estimate of the mean {} != 2", name, mean));
+            }
+        }
+
+        if !stratified.is_exhausted() || stratified.next_value().is_some() {
+            return Err("QuasiRandom should report exhaustion instead of wrapping around".into());
+        }
+
+        // Discrete quantiles are the smallest values whose CDF reaches p
+        let discretes: Vec<(&str, Box<dyn DiscreteDistrib>)> = vec![
+            ("Bernoulli", Box::new(Bernoulli::new(0.3).unwrap())),
+            ("DiscreteUniform", Box::new(DiscreteUniform::new(-3, 7).unwrap())),
+            ("Poisson", Box::new(Poisson::new(3.5).unwrap())),
+            ("Poisson (large)", Box::new(Poisson::new(250.0).unwrap())),
+            ("Binomial", Box::new(Binomial::new(20, 0.3).unwrap())),
+            ("Geometric", Box::new(Geometric::new(0.2).unwrap())),
+            ("NegativeBinomial", Box::new(NegativeBinomial::new(3, 0.4).unwrap())),
+            ("DiscreteTable", Box::new(DiscreteTable::new(&[(5, 0.5), (-1, 0.2), (2, 0.3)]).unwrap())),
+        ];
+
+        for (name, distrib) in discretes {
+            for p in [0.01, 0.25, 0.5, 0.75, 0.99] {
+                let x = distrib.quantile(p).unwrap();
+                if distrib.cdf(x).unwrap() < p - 1e-9 || distrib.cdf(x - 1).unwrap() >= p {
+                    return Err(format!("{} quantile({}) = {} does not invert the CDF", name, p, x));
+                }
+            }
+
+            let uniforms = latin_hypercube(4096, 1).into_iter().map(|point| point[0]).collect();
+            let mean = distrib.mean().unwrap();
+            let stratified = QuasiRandomDiscrete::new(distrib, uniforms).map_err(|e| e.to_string())?;
+            let lhs_mean = (0..4096).map(|_| stratified.gen() as f64).sum::<f64>() / 4096.0;
+            if (lhs_mean - mean).abs() > 0.01 * f64::max(1.0, mean.abs()) {
+                return Err(format!("{} Latin hypercube mean {} != {}", name, lhs_mean, mean));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sample_correlation(pairs: &[(f64, f64)]) -> f64 {
+        let n = pairs.len() as f64;
+        let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+        let covariance = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+        let variance_x = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+        let variance_y = pairs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum::<f64>();
+
+        covariance / (variance_x * variance_y).sqrt()
+    }
+
+    #[test]
+    fn correlated_inputs_reach_target_correlations() -> Result<(), String> {
+        use sdm::*;
+
+        let arta = Autocorrelated::new(Exponential::new(2.0), 0.5).map_err(|e| e.to_string())?;
+        let series: Vec<f64> = (0..100_000).map(|_| arta.gen()).collect();
+        let lag1: Vec<(f64, f64)> = series.windows(2).map(|w| (w[0], w[1])).collect();
+        let mean = series.iter().sum::<f64>() / series.len() as f64;
+
+        if (sample_correlation(&lag1) - 0.5).abs() > 0.03 || (mean - 2.0).abs() > 0.1 {
+            return Err(format!("ARTA lag-1 correlation {}, mean {}", sample_correlation(&lag1), mean));
+        }
+
+        let target = vec![vec![1.0, 0.6, -0.3], vec![0.6, 1.0, 0.2], vec![-0.3, 0.2, 1.0]];
+        let norta = Norta::new(
+            vec![
+                Box::new(Exponential::new(2.0)),
+                Box::new(Uniform::new(0.0, 10.0)),
+                Box::new(Lognormal::new(1.0, 0.5).unwrap()),
+            ],
+            &target,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let vectors: Vec<Vec<f64>> = (0..50_000).map(|_| norta.gen()).collect();
+        for i in 0..3 {
+            for j in 0..i {
+                let pairs: Vec<(f64, f64)> = vectors.iter().map(|v| (v[i], v[j])).collect();
+                let correlation = sample_correlation(&pairs);
+                if (correlation - target[i][j]).abs() > 0.03 {
+                    return Err(format!("NORTA correlation ({}, {}) is {}", i, j, correlation));
+                }
+            }
+        }
+
+        // Two exponentials cannot be more negatively correlated than 1 - pi^2 / 6
+        if Autocorrelated::new(Exponential::new(1.0), -0.9).is_ok() {
+            return Err("Infeasible correlation was accepted".to_string());
+        }
+
+        // Correlations are undefined without a finite variance, reported or from the tails
+        let heavy_tailed: Vec<(&str, Box<dyn Distrib>)> = vec![
+            ("Pareto", Box::new(Pareto::new(1.0, 1.5).unwrap())),
+            ("Pareto", Box::new(Pareto::new(1.0, 2.0).unwrap())),
+            ("Maximum", Box::new(Maximum::new(Pareto::new(1.0, 1.5).unwrap(), Exponential::new(1.0)))),
+        ];
+        for (name, marginal) in heavy_tailed {
+            if Autocorrelated::new(marginal, 0.3).is_ok() {
+                return Err(format!("{} with an infinite variance was accepted", name));
+            }
+        }
+
+        let light_tailed: Vec<Box<dyn Distrib>> = vec![
+            Box::new(Pareto::new(1.0, 3.0).unwrap()),
+            Box::new(Maximum::new(Exponential::new(1.0), Uniform::new(0.0, 2.0))),
+        ];
+        for marginal in light_tailed {
+            Autocorrelated::new(marginal, 0.3).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn warm_up_helpers_average_replications_and_reset_tallies() -> Result<(), String> {
+        use sdm::statistics::{Statistic, Tally};
+
+        let replications = vec![vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0], vec![2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0]];
+        let welch = sdm::analysis::welch_moving_average(&replications, 2).map_err(|e| e.to_string())?;
+        if welch != vec![1.0, 3.0, 5.0, 7.0] {
+            return Err(format!("Welch moving averages are {:?}", welch));
+        }
+
+        if sdm::analysis::welch_moving_average(&replications, 6).is_ok() {
+            return Err("A window longer than the replications was accepted".to_string());
+        }
+
+        let tally = Tally::new("Waiting time");
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            tally.record(value);
+        }
+        if tally.mean() != 2.5 || (tally.variance() - 5.0 / 3.0).abs() > 1e-12 || tally.max() != 4.0 {
+            return Err(format!("Tally mean {}, variance {}", tally.mean(), tally.variance()));
+        }
+
+        tally.reset();
+        if tally.mean() != 0.0 || tally.variance() != 0.0 || tally.min() != 0.0 || tally.max() != 0.0 {
+            return Err(format!("Empty tally mean {}, variance {}", tally.mean(), tally.variance()));
+        }
+
+        tally.record(10.0);
+        if tally.count() != 1 || tally.mean() != 10.0 || tally.min() != 10.0 || tally.variance() != 0.0 {
+            return Err("Tally kept observations from before the reset".to_string());
+        }
+
+        // Averages of sets and resources are 0 right after a reset, rather than 0 / 0
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        let queue = scheduler.manage_entity_set(Parts::new("Queue", sdm::EntitySetMode::FIFO));
+        let seats = scheduler.manage_resource(Seats::new("Seats", 1));
+        scheduler.set_time(5.0);
+        scheduler.reset_statistics();
+        let averages = [
+            queue.average_size(),
+            queue.average_time_in_set(),
+            seats.allocation_rate(),
+            seats.average_allocation(),
+        ];
+        sdm::Scheduler::destroy();
+
+        if averages != [0.0; 4] {
+            return Err(format!("Averages right after a reset {:?}", averages));
+        }
+
+        Ok(())
+    }
+
+    struct RecordService {
+        tally: std::rc::Rc<sdm::Tally>,
+    }
+
+    impl sdm::Event for RecordService {
+        fn name(&self) -> &str {
+            "Record service"
+        }
+
+        fn execute(&mut self) {
+            use sdm::Distrib;
+
+            self.tally.record(sdm::Exponential::new(2.0).gen());
+            let next = RecordService {
+                tally: self.tally.clone(),
+            };
+            sdm::Scheduler::instance().unwrap().schedule_in(Box::new(next), 1.0);
+        }
+    }
+
+    #[test]
+    fn replications_are_seeded_and_reach_target_precision() -> Result<(), String> {
+        use sdm::experiment::{Experiment, Summary};
+
+        let experiment = Experiment::new(100.0, |scheduler| {
+            let tally = scheduler.manage_statistic(sdm::Tally::new("Service"));
+            scheduler.schedule_now(Box::new(RecordService { tally }));
+            Ok(())
+        })
+        .with_seed(42);
+
+        let first = experiment.run_replication(3).map_err(|e| e.to_string())?;
+        if first != experiment.run_replication(3).map_err(|e| e.to_string())? {
+            return Err("The same seed gave different replications".to_string());
+        }
+        if first == experiment.run_replication(4).map_err(|e| e.to_string())? {
+            return Err("Different seeds gave identical replications".to_string());
+        }
+
+        let results = experiment.run(10).map_err(|e| e.to_string())?;
+        let service = results.summary("Service").ok_or("Missing custom statistic")?;
+        if service.replications != 10 || service.low() > 2.0 || service.high() < 2.0 {
+            return Err(format!("Interval does not cover the true mean: {}", service));
+        }
+
+        let precise = experiment
+            .run_until_precision(&["Service"], 0.02, 5, 200)
+            .map_err(|e| e.to_string())?;
+        let service = precise.summary("Service").unwrap();
+        if precise.replications() <= 5 || service.relative_precision() > 0.02 {
+            return Err(format!("Precision was not reached: {}", service));
+        }
+
+        // Every replication runs on its own thread-local scheduler and seed
+        let parallel = experiment.with_threads(4).run_parallel(10).map_err(|e| e.to_string())?;
+        if parallel.values("Service") != results.values("Service") {
+            return Err("Parallel replications differ from sequential ones".to_string());
+        }
+
+        // Processes draw their random numbers in activation order on every thread
+        let processes = Experiment::new(50.0, |scheduler| {
+            let tally = scheduler.manage_statistic(sdm::Tally::new("Work"));
+            for name in ["Cut", "Drill", "Weld", "Paint"] {
+                scheduler.start_process_now(Box::new(Work::new(name, sdm::Exponential::new(1.0), tally.clone())));
+            }
+            let service = scheduler.manage_statistic(sdm::Tally::new("Service"));
+            scheduler.schedule_now(Box::new(RecordService { tally: service }));
+            Ok(())
+        })
+        .with_seed(11);
+
+        let sequential = processes.run(6).map_err(|e| e.to_string())?;
+        let parallel = processes.with_threads(3).run_parallel(6).map_err(|e| e.to_string())?;
+        for name in ["Work", "Service"] {
+            if parallel.values(name) != sequential.values(name) {
+                return Err(format!("Parallel replications of a process model differ on {}", name));
+            }
+        }
+
+        // Statistics missing from some replications are padded with NaN, also by `run_more`
+        let built = std::sync::atomic::AtomicUsize::new(0);
+        let sparse = Experiment::new(10.0, |scheduler| {
+            let replication = built.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            if replication % 2 == 1 {
+                scheduler.manage_statistic(sdm::Tally::new("Odd")).record(1.0);
+            }
+            if replication == 3 {
+                scheduler.manage_statistic(sdm::Tally::new("Late")).record(1.0);
+            }
+            Ok(())
+        })
+        .with_threads(1);
+
+        let mut padded = sparse.run_parallel(2).map_err(|e| e.to_string())?;
+        sparse.run_more(&mut padded, 2).map_err(|e| e.to_string())?;
+        let pattern = |name: &str| -> Vec<bool> {
+            padded.values(name).unwrap_or(&[]).iter().map(|value| value.is_nan()).collect()
+        };
+        if pattern("Odd") != [true, false, true, false] || pattern("Late") != [true, true, true, false] {
+            return Err(format!("Missing statistics padded as {:?} and {:?}", pattern("Odd"), pattern("Late")));
+        }
+        let odd = padded.summary("Odd").unwrap();
+        if odd.replications != 2 || odd.mean != 1.0 {
+            return Err(format!("Missing values were summarized: {}", odd));
+        }
+
+        // A statistic that is always 0 is precise from the start
+        let idle = Experiment::new(10.0, |scheduler| {
+            scheduler.manage_statistic(sdm::Tally::new("Idle")).record(0.0);
+            Ok(())
+        });
+        let idle = idle.run_until_precision(&["Idle"], 0.02, 5, 50).map_err(|e| e.to_string())?;
+        if idle.replications() != 5 {
+            return Err(format!("{} replications of a statistic that is always 0", idle.replications()));
+        }
+
+        // t(0.975, 9) = 2.2622
+        let summary = Summary::new("Known", &(1..=10).map(|i| i as f64).collect::<Vec<_>>(), 0.95);
+        if (summary.half_width - 2.2622 * summary.std_dev / 10f64.sqrt()).abs() > 1e-3 {
+            return Err(format!("Half-width is {}", summary.half_width));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_means_decorrelate_a_single_long_run() -> Result<(), String> {
+        use sdm::analysis::{lag1_autocorrelation, BatchMeans};
+        use sdm::*;
+
+        // AR(1) waiting times around 5, strongly correlated like a congested queue
+        distributions::set_seed(7);
+        if Tally::new("Waiting time").observations().is_some() {
+            return Err("Tallies should only keep observations on request".to_string());
+        }
+
+        let tally = Tally::new("Waiting time").with_observations();
+        let mut deviation = 0.0;
+        for _ in 0..100_000 {
+            deviation = 0.99 * deviation + Gaussian::marsaglia_polar_gen();
+            tally.record(5.0 + deviation);
+        }
+
+        let observations = tally.observations().ok_or("Observations were not kept")?;
+        if lag1_autocorrelation(&observations) < 0.85 {
+            return Err("Observations should be strongly correlated".to_string());
+        }
+
+        let fixed = BatchMeans::new("Waiting time", &observations, 20, 0.95).map_err(|e| e.to_string())?;
+        if fixed.batch_size != 5000 || fixed.means.len() != 20 {
+            return Err(format!("{} batches of {}", fixed.means.len(), fixed.batch_size));
+        }
+
+        let automatic = BatchMeans::automatic("Waiting time", &observations, 0.95).map_err(|e| e.to_string())?;
+        let summary = &automatic.summary;
+        let covered = summary.low() <= 5.0 && 5.0 <= summary.high();
+        if automatic.batch_size <= 390 || automatic.lag1_autocorrelation >= 0.1 || !covered {
+            return Err(format!(
+                "Batches of {} with lag-1 autocorrelation {}: {}",
+                automatic.batch_size, automatic.lag1_autocorrelation, summary
+            ));
+        }
+
+        if BatchMeans::automatic("Waiting time", &observations[..50], 0.95).is_ok() {
+            return Err("A run too short for independent batches was accepted".to_string());
+        }
+
+        // A constant output is trivially uncorrelated
+        let constant = BatchMeans::automatic("Constant", &[3.0; 1000], 0.95).map_err(|e| e.to_string())?;
+        if constant.lag1_autocorrelation != 0.0 || constant.summary.mean != 3.0 {
+            return Err(format!("Constant batches have lag-1 autocorrelation {}", constant.lag1_autocorrelation));
+        }
+
+        Ok(())
+    }
+
+    struct RecordExponential {
+        tally: std::rc::Rc<sdm::Tally>,
+        mean: f64,
+    }
+
+    impl sdm::Event for RecordExponential {
+        fn name(&self) -> &str {
+            "Record exponential"
+        }
+
+        fn execute(&mut self) {
+            use sdm::Distrib;
+
+            self.tally.record(sdm::Exponential::new(self.mean).gen());
+            let next = RecordExponential {
+                tally: self.tally.clone(),
+                mean: self.mean,
+            };
+            sdm::Scheduler::instance().unwrap().schedule_in(Box::new(next), 1.0);
+        }
+    }
+
+    #[test]
+    fn scenarios_compare_with_common_random_numbers() -> Result<(), String> {
+        use sdm::*;
+
+        let scenarios = Scenarios::new(50.0, |scheduler, parameters| {
+            let tally = scheduler.manage_statistic(Tally::new("Service"));
+            let mean = parameters.get("Mean")?;
+            scheduler.schedule_now(Box::new(RecordExponential { tally, mean }));
+            Ok(())
+        })
+        .with_seed(11)
+        .with_scenario("Fast", Parameters::new().with("Mean", 1.9))
+        .with_scenario("Base", Parameters::new().with("Mean", 2.0))
+        .with_scenario("Slow", Parameters::new().with("Mean", 3.0));
+
+        let comparison = scenarios.run(10).map_err(|e| e.to_string())?;
+
+        // Same random numbers, so the difference is exactly 5% of the base observations
+        let difference = comparison.paired_t("Service", "Base", "Fast").map_err(|e| e.to_string())?;
+        let base = comparison.results("Base").unwrap().summary("Service").unwrap();
+        if (difference.mean - 0.05 * base.mean).abs() > 1e-9 || difference.low() <= 0.0 {
+            return Err(format!("Paired difference {} for base {}", difference, base));
+        }
+
+        let pairwise = comparison.pairwise("Service").map_err(|e| e.to_string())?;
+        let against = comparison.against("Service", "Base").map_err(|e| e.to_string())?;
+        if pairwise.len() != 3 || against.len() != 2 || (pairwise[0].confidence - (1.0 - 0.05 / 3.0)).abs() > 1e-12 {
+            return Err("Bonferroni comparisons are inconsistent".to_string());
+        }
+
+        let selection = scenarios
+            .select_best("Service", Goal::Minimize, 0.05, 5, 100)
+            .map_err(|e| e.to_string())?;
+        if selection.best != "Fast" || selection.survivors.len() != 1 || selection.eliminated.len() != 2 {
+            return Err(format!("Selected {:?}", selection));
+        }
+
+        let unknown = comparison.paired_t("Service", "Base", "Missing");
+        if unknown.is_ok() || comparison.against("Service", "Missing").is_ok() {
+            return Err("Unknown scenario was accepted".to_string());
+        }
+
+        // Only some replications report "Rare", so it cannot be paired
+        let sparse = Scenarios::new(10.0, |scheduler, parameters| {
+            if Uniform::gen(0.0, 1.0) < 0.5 {
+                scheduler.manage_statistic(Tally::new("Rare")).record(parameters.get("Mean")?);
+            }
+            Ok(())
+        })
+        .with_scenario("Base", Parameters::new().with("Mean", 2.0))
+        .with_scenario("Slow", Parameters::new().with("Mean", 3.0));
+        let sparse = sparse.run(10).map_err(|e| e.to_string())?;
+        let rare = sparse.results("Base").and_then(|results| results.values("Rare")).unwrap_or(&[]);
+        if !rare.iter().any(|value| value.is_nan()) || rare.iter().all(|value| value.is_nan()) {
+            return Err(format!("\"Rare\" should only be reported by some replications, got {:?}", rare));
+        }
+        if sparse.paired_t("Rare", "Slow", "Base").is_ok() {
+            return Err("A statistic missing from some replications was paired".to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn designs_cover_factor_levels_and_tabulate_responses() -> Result<(), String> {
+        use sdm::*;
+
+        let factors = vec![
+            Factor::integer("Cooks", 1, 3).unwrap(),
+            Factor::new("Tables", &[10.0, 20.0]).unwrap(),
+        ];
+        let full = Design::FullFactorial.points(&factors).map_err(|e| e.to_string())?;
+        let combinations: Vec<(f64, f64)> = full
+            .iter()
+            .map(|point| (point.get("Cooks").unwrap(), point.get("Tables").unwrap()))
+            .collect();
+        if combinations.len() != 6 || !combinations.contains(&(2.0, 20.0)) || !combinations.contains(&(3.0, 10.0)) {
+            return Err(format!("Full factorial points {:?}", combinations));
+        }
+
+        // 2^(3-1) design, C confounded with the AB interaction
+        let two_level = vec![
+            Factor::continuous("A", 0.0, 1.0).unwrap(),
+            Factor::continuous("B", 0.0, 1.0).unwrap(),
+            Factor::continuous("C", 0.0, 1.0).unwrap(),
+        ];
+        let fractional = Design::fractional_factorial(&["C = AB"])
+            .and_then(|design| design.points(&two_level))
+            .map_err(|e| e.to_string())?;
+        let confounded = fractional.iter().all(|point| {
+            let (a, b, c) = (point.get("A").unwrap(), point.get("B").unwrap(), point.get("C").unwrap());
+            (c == 1.0) == (a == b)
+        });
+        if fractional.len() != 4 || !confounded {
+            return Err(format!("Fractional factorial points {:?}", fractional));
+        }
+
+        let hypercube = Design::LatinHypercube(5).points(&two_level).map_err(|e| e.to_string())?;
+        let mut strata: Vec<usize> = hypercube.iter().map(|point| (point.get("B").unwrap() * 5.0) as usize).collect();
+        strata.sort_unstable();
+        if strata != vec![0, 1, 2, 3, 4] {
+            return Err(format!("Latin hypercube strata {:?}", strata));
+        }
+
+        let table = Sweep::new(20.0, |scheduler, parameters| {
+            let tally = scheduler.manage_statistic(Tally::new("Service"));
+            let mean = parameters.get("Mean")?;
+            scheduler.schedule_now(Box::new(RecordExponential { tally, mean }));
+            Ok(())
+        })
+        .with_factor(Factor::new("Mean", &[2.0, 4.0]).unwrap())
+        .run(&Design::FullFactorial, 3)
+        .map_err(|e| e.to_string())?;
+
+        let csv = table.to_csv();
+        let rows: Vec<Vec<f64>> = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').map(|field| field.parse().unwrap()).collect())
+            .collect();
+        if !csv.starts_with("Point,Replication,Mean,Service\n") || rows.len() != 6 {
+            return Err(format!("Results table:\n{}", csv));
+        }
+
+        // Common random numbers: doubling the mean doubles every observation
+        for replication in 0..3 {
+            if (rows[replication + 3][3] - 2.0 * rows[replication][3]).abs() > 1e-9 {
+                return Err(format!("Replication {} did not reuse its random numbers", replication));
+            }
+        }
+
+        // Responses missing from a replication are empty cells, not "NaN"
+        let sparse = Sweep::new(10.0, |scheduler, parameters| {
+            if Uniform::gen(0.0, 1.0) < 0.5 {
+                scheduler.manage_statistic(Tally::new("Rare")).record(parameters.get("Mean")?);
+            }
+            Ok(())
+        })
+        .with_factor(Factor::new("Mean", &[2.0]).unwrap())
+        .run(&Design::FullFactorial, 10)
+        .map_err(|e| e.to_string())?
+        .to_csv();
+        let cells: Vec<&str> = sparse.lines().skip(1).filter_map(|line| line.split(',').nth(3)).collect();
+        if sparse.contains("NaN") || !cells.contains(&"") || !cells.contains(&"2") {
+            return Err(format!("Sparse results table:\n{}", sparse));
+        }
+
+        let invalid = [
+            Factor::new("Tables", &[10.0, f64::NAN]).is_ok(),
+            Factor::new("Tables", &[10.0, 20.0, 10.0]).is_ok(),
+            Factor::continuous("Speed", f64::NAN, 1.0).is_ok(),
+            Design::fractional_factorial(&["D = AB", "D = BC"]).is_ok(),
+            Design::fractional_factorial(&["D ="]).is_ok(),
+            Design::FractionalFactorial(vec![(2, vec![])]).points(&two_level).is_ok(),
+            Design::LatinHypercube(0).points(&two_level).is_ok(),
+        ];
+        if invalid.iter().any(|accepted| *accepted) {
+            return Err(format!("Invalid factors or generators accepted: {:?}", invalid));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn annealing_finds_the_cheapest_feasible_configuration() -> Result<(), String> {
+        use sdm::*;
+
+        // A larger mean is cheaper, as long as the average service stays within 5.5
+        let result = Optimizer::new(50.0, |scheduler, parameters| {
+            let tally = scheduler.manage_statistic(Tally::new("Service"));
+            let mean = parameters.get("Mean")?;
+            scheduler.schedule_now(Box::new(RecordExponential { tally, mean }));
+            Ok(())
+        })
+        .with_seed(3)
+        .with_factor(Factor::integer("Mean", 1, 10).unwrap())
+        .with_objective(|parameters, _| Ok(-parameters.get("Mean")?))
+        .with_constraint(|_, outcome| outcome.get("Service"), 5.5)
+        .with_penalty(100.0)
+        .simulated_annealing(&Annealing {
+            iterations: 60,
+            ..Default::default()
+        })
+        .map_err(|e| e.to_string())?;
+
+        let best = result.best.parameters.get("Mean").map_err(|e| e.to_string())?;
+        if best != 5.0 || result.trajectory.len() != 60 {
+            return Err(format!("Best candidate {} with cost {}", best, result.best.mean()));
+        }
+
+        if result.evaluations.iter().any(|evaluation| evaluation.costs.len() < 5) {
+            return Err("Every candidate should have at least the initial replications".to_string());
+        }
+
+        // Service averages the mean, so far from 5.5 feasibility does not depend on the noise
+        let misflagged = result.evaluations.iter().find(|evaluation| {
+            let mean = evaluation.parameters.get("Mean").unwrap();
+            let expected = if mean <= 4.0 { Some(true) } else if mean >= 7.0 { Some(false) } else { None };
+            expected.is_some_and(|feasible| evaluation.is_feasible() != feasible) || evaluation.violations.len() != 1
+        });
+        if !result.best.is_feasible() || misflagged.is_some() {
+            return Err(format!("Feasibility misreported for {:?}", misflagged.map(|e| e.parameters.to_string())));
+        }
+
+        // Starts missing a factor or off its levels are rejected before any simulation
+        let built = std::sync::atomic::AtomicUsize::new(0);
+        let anneal_from = |start: &[(&str, f64)]| {
+            let mut parameters = Parameters::new();
+            for (name, value) in start {
+                parameters.set(name, *value);
+            }
+
+            Optimizer::new(10.0, |_, _| {
+                built.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Ok(())
+            })
+            .with_factor(Factor::integer("Mean", 1, 10).unwrap())
+            .with_factor(Factor::continuous("Rate", 0.0, 1.0).unwrap())
+            .with_start(parameters)
+            .simulated_annealing(&Annealing::default())
+        };
+        let invalid = [
+            anneal_from(&[("Mean", 2.0)]).is_err(),
+            anneal_from(&[("Mean", 2.5), ("Rate", 0.5)]).is_err(),
+            anneal_from(&[("Mean", 2.0), ("Rate", 2.0)]).is_err(),
+        ];
+        if invalid.contains(&false) || built.load(std::sync::atomic::Ordering::Relaxed) != 0 {
+            return Err(format!("Invalid starts accepted: {:?}", invalid));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_export_statistics_and_time_series() -> Result<(), String> {
+        use sdm::*;
+
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        scheduler.set_report_interval(5.0);
+
+        let tally = scheduler.manage_statistic(Tally::new("Service \"time\""));
+        scheduler.schedule_now(Box::new(RecordExponential { tally, mean: 2.0 }));
+        scheduler.simulate_until(20.0);
+
+        let report = scheduler.report();
+        let series = scheduler.time_series();
+        Scheduler::destroy();
+
+        let times: Vec<f64> = series.reports.iter().map(|report| report.time).collect();
+        if times != vec![5.0, 10.0, 15.0, 20.0] {
+            return Err(format!("Time series reports at {:?}", times));
+        }
+
+        let statistic = &report.statistics[0];
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).map_err(|e| e.to_string())?;
+        let expected = serde_json::json!({
+            "time": 20.0,
+            "timestamp": "20.00",
+            "time_unit": "s",
+            "resources": [],
+            "entity_sets": [],
+            "matches": [],
+            "sources": [],
+            "entity_types": [],
+            "statistics": [{
+                "name": "Service \"time\"",
+                "count": statistic.count,
+                "mean": statistic.mean,
+                "min": statistic.min,
+                "max": statistic.max,
+            }],
+        });
+        let bounded = statistic.min <= statistic.mean && statistic.mean <= statistic.max;
+        if statistic.count == 0 || !bounded || json != expected {
+            return Err(format!("JSON report {}", json));
+        }
+
+        let csv = report.to_csv();
+        let expected = format!(
+            "section,name,metric,value\n\
+             statistics,\"Service \"\"time\"\"\",count,{}\n\
+             statistics,\"Service \"\"time\"\"\",mean,{}\n\
+             statistics,\"Service \"\"time\"\"\",min,{}\n\
+             statistics,\"Service \"\"time\"\"\",max,{}\n",
+            statistic.count, statistic.mean, statistic.min, statistic.max
+        );
+        if csv != expected {
+            return Err(format!("CSV report {}", csv));
+        }
+
+        let series_csv = series.to_csv();
+        let lines: Vec<&str> = series_csv.lines().collect();
+        let header = "time,timestamp,\
+                      \"statistics.Service \"\"time\"\".count\",\"statistics.Service \"\"time\"\".mean\",\
+                      \"statistics.Service \"\"time\"\".min\",\"statistics.Service \"\"time\"\".max\"";
+        if lines.len() != 5 || lines[0] != header {
+            return Err(format!("Time series CSV {}", series_csv));
+        }
+
+        // Dots in names must not be mistaken for the separators of `section.name.metric`
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        scheduler.set_report_interval(5.0);
+        scheduler.manage_statistic(Tally::new("queue.wait"));
+        scheduler.simulate_until(5.0);
+        let series = scheduler.time_series();
+        Scheduler::destroy();
+
+        let series_csv = series.to_csv();
+        if !series_csv.starts_with("time,timestamp,statistics.queue\\.wait.count,statistics.queue\\.wait.mean,") {
+            return Err(format!("Dotted time series CSV {}", series_csv));
+        }
+
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct LogObserver {
+        log: std::cell::RefCell<Vec<String>>,
+    }
+
+    impl sdm::Observer for LogObserver {
+        fn on_event_scheduled(&self, event: &dyn sdm::Event, time: f64) {
+            self.log.borrow_mut().push(format!("scheduled {} at {}", event.name(), time));
+        }
+
+        fn on_event_executed(&self, event: &dyn sdm::Event) {
+            self.log.borrow_mut().push(format!("executed {} at {}", event.name(), sdm::Scheduler::time()));
+        }
+
+        fn on_event_cancelled(&self, event: &dyn sdm::Event, time: f64) {
+            self.log.borrow_mut().push(format!("cancelled {} at {}", event.name(), time));
+        }
+
+        fn on_clock_advanced(&self, from: f64, to: f64) {
+            self.log.borrow_mut().push(format!("clock {} -> {}", from, to));
+        }
+    }
+
+    #[test]
+    fn observers_see_scheduling_execution_and_cancellation() -> Result<(), String> {
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        let observer = scheduler.add_observer(LogObserver::default());
+        let unplugged = scheduler.add_observer(LogObserver::default());
+        let executed = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+
+        for time in [1.5, 0.5] {
+            let event = RecordTime {
+                executed: executed.clone(),
+            };
+            scheduler.schedule_at(Box::new(event), time);
+        }
+        let removed = scheduler.remove_observer(&unplugged) && !scheduler.remove_observer(&unplugged);
+        let cancelled = scheduler.cancel_events(|_, time| time > 1.0);
+        while !scheduler.simulate_one_step() {}
+        sdm::Scheduler::destroy();
+
+        if !removed || unplugged.log.borrow().len() != 2 {
+            return Err(format!("Removed observer saw {:?}", unplugged.log.borrow()));
+        }
+
+        if cancelled != 1 || *executed.borrow() != vec![0.5] {
+            return Err(format!("Cancelled {} events, executed {:?}", cancelled, executed.borrow()));
+        }
+
+        let expected = vec![
+            "scheduled Record time at 1.5",
+            "scheduled Record time at 0.5",
+            "cancelled Record time at 1.5",
+            "clock 0 -> 0.5",
+            "executed Record time at 0.5",
+        ];
+        if *observer.log.borrow() != expected {
+            return Err(format!("Observed {:?}", observer.log.borrow()));
+        }
+
+        Ok(())
+    }
+
+    fn record_trace(cancel: bool) -> Result<sdm::Trace, String> {
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        let recorder = scheduler.add_observer(sdm::TraceRecorder::new());
+        let executed = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+
+        for time in [0.5, 1.5, 2.5] {
+            let event = RecordTime {
+                executed: executed.clone(),
+            };
+            scheduler.schedule_at(Box::new(event), time);
+        }
+        if cancel {
+            scheduler.cancel_events(|_, time| time == 1.5);
+        }
+        while !scheduler.simulate_one_step() {}
+        sdm::Scheduler::destroy();
+
+        Ok(recorder.trace())
+    }
+
+    #[test]
+    fn traces_round_trip_replay_and_diff() -> Result<(), String> {
+        use sdm::trace::TraceRecord;
+
+        let trace = record_trace(false)?;
+        let path = std::env::temp_dir().join(format!("sdm_events_{}.trace", std::process::id()));
+        trace.save(&path).map_err(|e| e.to_string())?;
+        let loaded = sdm::Trace::load(&path).map_err(|e| e.to_string())?;
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        if loaded != trace {
+            return Err("Trace changed when saved and loaded".to_string());
+        }
+
+        let executed = trace.filter(|entry| matches!(entry.record, TraceRecord::EventExecuted(_)));
+        let times: Vec<f64> = executed.iter().map(|entry| entry.time).collect();
+        if times != vec![0.5, 1.5, 2.5] {
+            return Err(format!("Executed at {:?}", times));
+        }
+
+        let mut replay = sdm::Replay::new(trace.clone());
+        replay.next_step();
+        let after_first = replay.state().clone();
+        if after_first.time != 0.5 || after_first.events.len() != 2 {
+            return Err(format!("After the first step: {:?}", after_first));
+        }
+        replay.next_step();
+        replay.previous_step();
+        if *replay.state() != after_first {
+            return Err(format!("Stepping back gave {:?}", replay.state()));
+        }
+        replay.seek_time(0.0);
+        if replay.state().events.len() != 3 || replay.back().is_none() || replay.position() != 2 {
+            return Err(format!("Before the first step: {:?}", replay.state()));
+        }
+
+        // Streamed to a file as recorded, including processes queued to start later
+        let path = std::env::temp_dir().join(format!("sdm_processes_{}.trace", std::process::id()));
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        let recorder = scheduler.add_observer(sdm::TraceRecorder::new());
+        let streamer = scheduler.add_observer(sdm::TraceRecorder::to_file(&path).map_err(|e| e.to_string())?);
+        let log = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        scheduler.start_process_in(Box::new(Stamp::new("Stamp", sdm::Constant(0.5), log)), 1.0);
+        let event = RecordTime {
+            executed: std::rc::Rc::new(std::cell::RefCell::new(vec![])),
+        };
+        scheduler.schedule_at(Box::new(event), 2.0);
+        while !scheduler.simulate_one_step() {}
+        sdm::Scheduler::destroy();
+
+        streamer.flush().map_err(|e| e.to_string())?;
+        let streamed = sdm::Trace::load(&path).map_err(|e| e.to_string())?;
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        let queued = streamed.filter(|entry| entry.record == TraceRecord::ProcessScheduled("Stamp".to_string(), 1.0));
+        if streamed != recorder.trace() || !streamer.trace().is_empty() || queued.len() != 1 {
+            return Err(format!("Streamed {:?}", streamed));
+        }
+
+        let mut replay = sdm::Replay::new(streamed);
+        replay.seek_time(0.0);
+        if replay.state().process_queue != vec![(1.0, "Stamp".to_string())] {
+            return Err(format!("Queued processes {:?}", replay.state().process_queue));
+        }
+
+        if record_trace(false)?.diff(&trace).is_some() {
+            return Err("Identical runs diverged".to_string());
+        }
+        match record_trace(true)?.diff(&trace) {
+            Some(divergence) if divergence.index == 3 => Ok(()),
+            divergence => Err(format!("Diverged at {:?}", divergence)),
+        }
+    }
+
+    #[test]
+    fn debugger_steps_to_breakpoints_and_watches() -> Result<(), String> {
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        let tally = scheduler.manage_statistic(sdm::Tally::new("Service"));
+        scheduler.schedule_now(Box::new(RecordExponential { tally, mean: 2.0 }));
+        let event = RecordTime {
+            executed: std::rc::Rc::new(std::cell::RefCell::new(vec![])),
+        };
+        scheduler.schedule_at(Box::new(event), 2.5);
+
+        let mut debugger = sdm::Debugger::new().map_err(|e| e.to_string())?;
+        let mut execute = |command: &str| debugger.execute(command).map_err(|e| format!("{}: {}", command, e));
+
+        let stepped = execute("step 2")?;
+        let watched = execute("watch events")?;
+        execute("break event Record time")?;
+        let at_event = execute("run")?;
+        let fel = execute("fel")?;
+        execute("delete 1")?;
+        execute("break if time >= 5")?;
+        let at_condition = execute("run")?;
+        let at_time = execute("until 7.5")?;
+        let invalid = execute("break if size Queue").is_err() && execute("jump").is_err();
+        sdm::Scheduler::destroy();
+
+        let expected = [
+            (stepped, "Stopped at 1.00".to_string()),
+            (watched, "1: events = 2".to_string()),
+            (at_event, "Breakpoint 1: event \"Record time\"\nStopped at 2.50\n1: events = 1".to_string()),
+            (fel, "- 3.00 \"Record exponential\"".to_string()),
+            (at_condition, "Breakpoint 1: if time >= 5\nStopped at 5.00\n1: events = 1".to_string()),
+            (at_time, "Stopped at 7.50\n1: events = 1".to_string()),
+        ];
+        for (printed, expected) in expected.iter() {
+            if printed != expected {
+                return Err(format!("Printed {:?} instead of {:?}", printed, expected));
+            }
+        }
+
+        if !invalid {
+            return Err("Invalid commands were accepted".to_string());
+        }
+
+        Ok(())
+    }
+
+    // Models built with the macros, which are linted here unlike in downstream crates
+    mod models {
+        #![allow(dead_code, unused_assignments, unused_mut, clippy::redundant_field_names)]
+
+        use crate::sdm::{self, Entity, EntitySetMode};
+        use crate::{EntitySetWrapper, EntityWrapper, MatchWrapper, ProcessWrapper, ResourceWrapper};
+
+        EntityWrapper! {
+            pub struct Part {
+                pub order: uuid::Uuid,
+            };
+        }
+
+        EntitySetWrapper! {
+            pub struct Parts;
+        }
+
+        ResourceWrapper! {
+            pub struct Seats;
+        }
+
+        MatchWrapper! {
+            pub struct Assemble {
+                assembled: std::rc::Rc<dyn sdm::EntitySet>,
+            };
+
+            @on_match = |matcher, entities| {
+                for entity in entities {
+                    matcher.assembled.push(entity);
+                }
+            };
+        }
+
+        MatchWrapper! {
+            pub struct Recycle;
+
+            @on_match = |matcher, entities| {
+                for ((set, _), entity) in matcher.sets.iter().zip(entities) {
+                    set.push(entity);
+                }
+            };
+        }
+
+        ProcessWrapper! {
+            pub struct Stamp {
+                log: std::rc::Rc<std::cell::RefCell<Vec<String>>>,
+            };
+
+            @on_start = |proc| {
+                proc.log.borrow_mut().push(proc.name.clone());
+            };
+        }
+
+        ProcessWrapper! {
+            pub struct Work {
+                tally: std::rc::Rc<sdm::Tally>,
+            };
+
+            @on_start = |proc| {
+                proc.tally.record(proc.duration.gen());
+            };
+        }
+    }
+
+    use models::{Assemble, Part, Parts, Recycle, Seats, Stamp, Work};
+
+    struct Arrive {
+        set: std::rc::Rc<dyn sdm::EntitySet>,
+        entity: Option<Box<dyn sdm::Entity>>,
+    }
+
+    impl sdm::Event for Arrive {
+        fn name(&self) -> &str {
+            "Arrive"
+        }
+
+        fn execute(&mut self) {
+            if let Some(entity) = self.entity.take() {
+                self.set.push(entity);
+            }
+        }
+    }
+
+    fn arrive_at(set: &std::rc::Rc<dyn sdm::EntitySet>, entity: Box<dyn sdm::Entity>, time: f64) {
+        let event = Arrive {
+            set: set.clone(),
+            entity: Some(entity),
+        };
+        sdm::Scheduler::instance().unwrap().schedule_at(Box::new(event), time);
+    }
+
+    #[test]
+    fn processes_start_in_activation_order() -> Result<(), String> {
+        use sdm::*;
+
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+
+        let log = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let names = ["Mix", "Bake", "Cool", "Pack", "Ship", "Bill", "Sweep", "Stock"];
+        for name in &names[..6] {
+            scheduler.start_process_now(Box::new(Stamp::new(name, Constant(0.5), log.clone())));
+        }
+        scheduler.start_process_at(Box::new(Stamp::new(names[6], Constant(0.5), log.clone())), 1.0);
+        scheduler.start_process_at(Box::new(Stamp::new(names[7], Constant(0.5), log.clone())), 2.0);
+
+        let executed = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        for time in [0.0, 1.5, 2.5] {
+            scheduler.schedule_at(Box::new(RecordTime { executed: executed.clone() }), time);
+        }
+        while scheduler.next_time().is_some() {
+            scheduler.simulate_one_step();
+        }
+        Scheduler::destroy();
+
+        // Every event starts the running processes, oldest activation first
+        let expected = [&names[..6], &names[..7], &names[..]].concat();
+        if *log.borrow() != expected {
+            return Err(format!("Processes started in order {:?}", log.borrow()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_pair_entities_by_key_and_measure_waits() -> Result<(), String> {
+        use sdm::*;
+
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+
+        let bodies = scheduler.manage_entity_set(Parts::new("Bodies", EntitySetMode::FIFO));
+        let wheels = scheduler.manage_entity_set(Parts::new("Wheels", EntitySetMode::FIFO));
+        let assembled = scheduler.manage_entity_set(Parts::new("Assembled", EntitySetMode::FIFO));
+        let key: matching::MatchKey = |part| part.downcast_ref::<Part>().unwrap().order;
+        let assemble = scheduler.manage_match(Assemble::new(
+            "Assemble",
+            vec![(bodies.clone(), key), (wheels.clone(), key)],
+            assembled.clone(),
+        ));
+
+        if assemble.average_wait_time() != 0.0 {
+            return Err("Average wait time without matches is not zero".to_string());
+        }
+
+        let (first, second) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        arrive_at(&bodies, Box::new(Part::new("Body", 0.0, first)), 0.0);
+        arrive_at(&bodies, Box::new(Part::new("Body", 0.0, second)), 0.0);
+        arrive_at(&wheels, Box::new(Part::new("Wheel", 2.0, second)), 2.0);
+        arrive_at(&wheels, Box::new(Part::new("Wheel", 5.0, first)), 5.0);
+        while !scheduler.simulate_one_step() {}
+
+        // Bodies waited 2 and 5, wheels were matched on arrival
+        let result = (assemble.matches(), assemble.average_wait_time(), assemble.max_wait_time());
+        let (sizes, time) = ((bodies.size(), wheels.size(), assembled.size()), Scheduler::time());
+        Scheduler::destroy();
+
+        if result != (2, 1.75, 5.0) || sizes != (0, 0, 4) || time != 5.0 {
+            return Err(format!("Unexpected matches, waits and time: {:?} {:?} {}", result, sizes, time));
+        }
+
+        // Entities pushed back by `on_match` wait for the next step instead of matching forever
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+
+        let bodies = scheduler.manage_entity_set(Parts::new("Bodies", EntitySetMode::FIFO));
+        let wheels = scheduler.manage_entity_set(Parts::new("Wheels", EntitySetMode::FIFO));
+        let recycle = scheduler.manage_match(Recycle::new(
+            "Recycle",
+            vec![(bodies.clone(), key), (wheels.clone(), key)],
+        ));
+
+        let order = uuid::Uuid::new_v4();
+        arrive_at(&bodies, Box::new(Part::new("Body", 0.0, order)), 0.0);
+        arrive_at(&wheels, Box::new(Part::new("Wheel", 0.0, order)), 0.0);
+        scheduler.simulate_one_step();
+        scheduler.simulate_one_step();
+        let matches = recycle.matches();
+        Scheduler::destroy();
+
+        if matches != 1 {
+            return Err(format!("Recycled entities were matched {} times in one step", matches));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn entity_sets_order_and_group_by_attribute() -> Result<(), String> {
+        use sdm::*;
+
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+
+        let triage = scheduler.manage_entity_set(Parts::new("Triage", EntitySetMode::ATTRIBUTE("urgency")));
+        triage.group_time_by("ward");
+        for (urgency, ward) in [(2.0, "A"), (3.0, "B"), (1.0, "A")] {
+            let mut patient = Part::new("Patient", 0.0, uuid::Uuid::nil());
+            patient.set_attribute("urgency", AttributeValue::Number(urgency));
+            patient.set_attribute("ward", ward.into());
+            triage.push(Box::new(patient));
+        }
+
+        let mut order = vec![];
+        for time in [2.0, 4.0] {
+            scheduler.set_time(time);
+            let patient = triage.pop().ok_or("Triage is empty")?;
+            order.push(patient.attribute("urgency").and_then(|urgency| urgency.as_number()));
+        }
+
+        // The patient still waiting counts up to now
+        scheduler.set_time(10.0);
+        let by_ward = triage.average_time_in_set_by("ward");
+        let (average, ungrouped) = (triage.average_time_in_set(), triage.average_time_in_set_by("urgency"));
+
+        // NaN and values of other kinds still sort in a consistent order
+        let mixed = scheduler.manage_entity_set(Parts::new("Mixed", EntitySetMode::ATTRIBUTE("urgency")));
+        let values = [
+            Some(AttributeValue::Number(f64::NAN)),
+            Some(AttributeValue::Number(3.0)),
+            Some(AttributeValue::Text("high".to_string())),
+            None,
+            Some(AttributeValue::Number(1.0)),
+            Some(AttributeValue::Time(2.0)),
+        ];
+        for value in values {
+            let mut patient = Part::new("Patient", 0.0, uuid::Uuid::nil());
+            if let Some(value) = value {
+                patient.set_attribute("urgency", value);
+            }
+            mixed.push(Box::new(patient));
+        }
+        let mut mixed_order = vec![];
+        while let Some(patient) = mixed.pop() {
+            mixed_order.push(patient.attribute("urgency").map_or("-".to_string(), |urgency| urgency.to_string()));
+        }
+        Scheduler::destroy();
+
+        if mixed_order != ["-", "1", "3", "NaN", "high", "2.00"] {
+            return Err(format!("Mixed urgencies left in order {:?}", mixed_order));
+        }
+
+        if order != vec![Some(1.0), Some(2.0)] {
+            return Err(format!("Patients left in urgency order {:?}", order));
+        }
+
+        if by_ward.get("A") != Some(&3.0) || by_ward.get("B") != Some(&10.0) || average != 16.0 / 3.0 {
+            return Err(format!("Unexpected times in set {:?} {}", by_ward, average));
+        }
+
+        if ungrouped.len() != 1 {
+            return Err(format!("Times of patients that left were kept per urgency: {:?}", ungrouped));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn histories_split_value_added_and_waiting_time() -> Result<(), String> {
+        use sdm::*;
+
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+
+        let queue = scheduler.manage_entity_set(Parts::new("Queue", EntitySetMode::FIFO));
+        let ordering = scheduler.manage_entity_set(Parts::new("Ordering", EntitySetMode::FIFO).with_value_added());
+        let waiting = scheduler.manage_entity_set(Parts::new("Waiting for food", EntitySetMode::FIFO));
+        let seats = scheduler.manage_resource(Seats::new("Seats", 1));
+
+        let mut client = Part::new("Client", 0.0, uuid::Uuid::nil());
+        client.enable_history();
+        queue.push(Box::new(client));
+
+        // Queue 0-2, order while seated 2-5, wait for food while seated 5-8, eat 8-10
+        scheduler.set_time(2.0);
+        let mut client = queue.pop().ok_or("Queue is empty")?;
+        seats.allocate_for(1, client.as_mut()).map_err(|e| e.to_string())?;
+        ordering.push(client);
+
+        scheduler.set_time(5.0);
+        waiting.push(ordering.pop().ok_or("No client ordering")?);
+
+        scheduler.set_time(8.0);
+        let mut client = waiting.pop().ok_or("No client waiting")?;
+        client.touch_event("Serve");
+
+        scheduler.set_time(10.0);
+        seats.release_for(1, client.as_mut()).map_err(|e| e.to_string())?;
+        let history = client.history().ok_or("History was not enabled")?;
+        let split = (history.value_added_time(10.0), history.waiting_time(10.0));
+        let time_in_sets = history.time_in_sets(10.0);
+        Scheduler::destroy();
+
+        if split != (5.0, 5.0) {
+            return Err(format!("Value-added and waiting times are {:?}", split));
+        }
+
+        if time_in_sets.get("Ordering") != Some(&3.0) || time_in_sets.get("Waiting for food") != Some(&3.0) {
+            return Err(format!("Unexpected times in sets {:?}", time_in_sets));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn disposals_track_work_in_process_per_type() -> Result<(), String> {
+        use sdm::*;
+
+        // Not counted, as there is no scheduler yet
+        let stray = Part::new("Stray", 0.0, uuid::Uuid::nil());
+
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+
+        let body = Part::new("Body", 0.0, uuid::Uuid::nil());
+        let wheel = Part::new("Wheel", 0.0, uuid::Uuid::nil());
+        if scheduler.disposal_statistics().get("Part").map(|stats| stats.average_system_time()) != Some(0.0) {
+            return Err("Average time in system without disposals is not zero".to_string());
+        }
+
+        scheduler.set_time(4.0);
+        scheduler.dispose(Box::new(body)).map_err(|e| e.to_string())?;
+        scheduler.set_time(6.0);
+        scheduler.dispose(Box::new(wheel)).map_err(|e| e.to_string())?;
+
+        let stray_disposed = scheduler.dispose(Box::new(stray)).is_ok();
+        let disposals = scheduler.disposal_statistics();
+        let stats = disposals.get("Part").cloned().ok_or("No statistics for parts")?;
+        let by_name = disposals.get("Body").is_some();
+        drop(disposals);
+        Scheduler::destroy();
+
+        if stray_disposed {
+            return Err("More parts were disposed of than created".to_string());
+        }
+
+        if by_name || (stats.created, stats.disposed, stats.work_in_process()) != (2, 2, 0) {
+            return Err(format!("Unexpected disposal statistics {:?}", stats));
+        }
+
+        if stats.average_system_time() != 5.0 || stats.average_work_in_process() != 0.0 {
+            return Err(format!("Unexpected averages {:?}", stats));
+        }
+
+        Ok(())
+    }
+
+    fn arrival_times(
+        source: impl Fn(std::rc::Rc<dyn sdm::EntitySet>) -> Result<sdm::Source, String>,
+    ) -> Result<Vec<f64>, String> {
+        let scheduler = sdm::Scheduler::new().map_err(|e| e.to_string())?;
+        scheduler.set_quiet(true);
+        sdm::distributions::set_seed(7);
+
+        let target = scheduler.manage_entity_set(Parts::new("Arrived", sdm::EntitySetMode::FIFO));
+        scheduler.manage_source(source(target.clone())?);
+        while scheduler.next_time().is_some() && !scheduler.simulate_one_step() {}
+
+        let mut times = vec![];
+        target.for_each(&mut |entity| times.push(entity.creation_time()));
+        sdm::Scheduler::destroy();
+
+        Ok(times)
+    }
+
+    #[test]
+    fn sources_create_batches_within_limits_and_schedules() -> Result<(), String> {
+        use sdm::*;
+
+        let part = |time| Box::new(Part::new("Part", time, uuid::Uuid::nil())) as Box<dyn Entity>;
+
+        // Batches of 2 every unit of time, cut short by the limit
+        let batches = arrival_times(|target| {
+            Ok(Source::new("Batches", Constant(1.0), target, part)
+                .with_batch_size(DiscreteUniform::new(2, 2).map_err(|e| e.to_string())?)
+                .map_err(|e| e.to_string())?
+                .with_start(1.0)
+                .with_max_arrivals(5))
+        })?;
+        if batches != vec![1.0, 1.0, 2.0, 2.0, 3.0] {
+            return Err(format!("Unexpected batch arrivals {:?}", batches));
+        }
+
+        // Batches of 0 are skipped
+        let sparse = arrival_times(|target| {
+            let batch_size = Poisson::new(0.5).map_err(|e| e.to_string())?;
+            let source = Source::new("Sparse", Constant(1.0), target, part).with_batch_size(batch_size);
+            Ok(source.map_err(|e| e.to_string())?.with_stop(99.0))
+        })?;
+        if sparse.is_empty() || sparse.len() >= 100 {
+            return Err(format!("{} entities in batches of mean 0.5", sparse.len()));
+        }
+        let negative = DiscreteUniform::new(-1, 2).map_err(|e| e.to_string())?;
+        let scheduler = Scheduler::new().map_err(|e| e.to_string())?;
+        let target = scheduler.manage_entity_set(Parts::new("Arrived", EntitySetMode::FIFO));
+        let rejected = Source::new("Negative", Constant(1.0), target, part).with_batch_size(negative).is_err();
+        Scheduler::destroy();
+        if !rejected {
+            return Err("Batch sizes that may be negative were accepted".to_string());
+        }
+
+        // Sources added later start from the current time
+        let late = arrival_times(|target| {
+            Scheduler::instance().map_err(|e| e.to_string())?.set_time(3.0);
+            Ok(Source::new("Late", Constant(2.0), target, part).with_max_arrivals(2))
+        })?;
+        if late != vec![3.0, 5.0] {
+            return Err(format!("Unexpected arrivals of a late source {:?}", late));
+        }
+
+        let stopped = arrival_times(|target| Ok(Source::new("Stopped", Constant(2.0), target, part).with_stop(5.0)))?;
+        if stopped != vec![0.0, 2.0, 4.0] {
+            return Err(format!("Unexpected arrivals before stop {:?}", stopped));
+        }
+
+        // The rate drops to 0 for good at 100, so arrivals end there
+        let piecewise = arrival_times(|target| {
+            let schedule = ArrivalSchedule::Piecewise {
+                rates: vec![(0.0, 1.0), (50.0, 0.0), (60.0, 2.0), (100.0, 0.0)],
+                period: None,
+            };
+            Source::new_scheduled("Piecewise", schedule, target, part).map_err(|e| e.to_string())
+        })?;
+        if piecewise.iter().any(|time| (50.0..60.0).contains(time) || *time >= 100.0) {
+            return Err("Arrivals happened while the rate was 0".to_string());
+        }
+        let expected = 50.0 + 2.0 * 40.0;
+        if (piecewise.len() as f64 - expected).abs() > 4.0 * expected.sqrt() {
+            return Err(format!("{} arrivals for an expected {}", piecewise.len(), expected));
+        }
+
+        // Thinning gives up once the rate stays at 0
+        let decaying = arrival_times(|target| {
+            let schedule = ArrivalSchedule::Nonhomogeneous {
+                rate: |time| if time < 10.0 { 1.0 } else { 0.0 },
+                max_rate: 1.0,
+            };
+            Source::new_scheduled("Decaying", schedule, target, part).map_err(|e| e.to_string())
+        })?;
+        if decaying.iter().any(|time| *time >= 10.0) {
+            return Err(format!("Arrivals after the rate dropped to 0: {:?}", decaying));
+        }
+
+        // Open 9:00 to 17:00, arrivals every 5 hours restart at opening
+        let opening = arrival_times(|target| {
+            let scheduler = Scheduler::instance().map_err(|e| e.to_string())?;
+            scheduler.set_time_unit(TimeUnit::Hours);
+            scheduler.set_calendar(DateTime::new(2024, 1, 2, 0, 0, 0.0).map_err(|e| e.to_string())?);
+
+            let hours =
+                WeeklySchedule::new(Duration::hours(9.0), Duration::hours(17.0), &[]).map_err(|e| e.to_string())?;
+            Ok(Source::new("Opening", Constant(5.0), target, part).with_opening_hours(hours).with_stop(40.0))
+        })?;
+        if opening != vec![9.0, 14.0, 33.0, 38.0] {
+            return Err(format!("Unexpected arrivals during opening hours {:?}", opening));
+        }
+
+        let unsorted = ArrivalSchedule::Piecewise {
+            rates: vec![(10.0, 1.0), (0.0, 2.0)],
+            period: None,
+        };
+        let nan = ArrivalSchedule::Nonhomogeneous {
+            rate: |_| 1.0,
+            max_rate: f64::NAN,
+        };
+        if unsorted.validate().is_ok() || nan.validate().is_ok() {
+            return Err("Invalid arrival schedules were accepted".to_string());
+        }
+
+        Ok(())
+    }
 }
@@ -28,29 +28,6 @@ EntitySetWrapper! {
     pub struct PreparedFoodQueue;
 }
 
-EventWrapper! {
-    pub struct Arrival {
-        n_people: u32,
-        order_queue: Rc<dyn EntitySet>,
-    };
-
-    @execute = |event| {
-        println!("{:.2} - Client arrived with size {}", Scheduler::time(), event.n_people);
-        event.order_queue.push(Box::new(
-            Client::new("Client", Scheduler::time(), event.n_people, false)
-        ));
-
-        Scheduler::instance().unwrap().schedule_in(
-            Box::new(Arrival::new(
-                "Arrival",
-                f32::round(sdm::distributions::Uniform::gen(1.0, 4.0)) as u32,
-                event.order_queue.clone()
-            )),
-            sdm::distributions::Uniform::gen(3.0, 20.0)
-        );
-    };
-}
-
 EntitySetWrapper! {
     pub struct OrderQueue;
 }
@@ -76,17 +53,12 @@ ProcessWrapper! {
     };
 
     @on_start = |proc| {
-        if !proc.order_queue.is_empty() {
-            match proc.attendants.allocate(1) {
-                Ok(()) => {
-                    println!("{:.2} - Client started ordering", Scheduler::time());
-                    proc.ordering_clients.push(proc.order_queue.pop().unwrap());
-                    proc.did_allocate.borrow_mut().push(true);
-                },
-                Err(_) => {
-                    proc.did_allocate.borrow_mut().push(false);
-                }
-            }
+        if !proc.order_queue.is_empty() && proc.attendants.n_allocated() < proc.attendants.quantity() {
+            println!("{} - Client started ordering", Scheduler::timestamp());
+            let mut client = proc.order_queue.pop().unwrap();
+            proc.attendants.allocate_for(1, client.as_mut()).unwrap();
+            proc.ordering_clients.push(client);
+            proc.did_allocate.borrow_mut().push(true);
         } else {
             proc.did_allocate.borrow_mut().push(false);
         }
@@ -94,21 +66,22 @@ ProcessWrapper! {
 
     @on_end = |proc| {
         if proc.did_allocate.borrow_mut().remove(0) {
-            println!("{:.2} - Client done ordering", Scheduler::time());
-            match proc.attendants.release(1) {
+            println!("{} - Client done ordering", Scheduler::timestamp());
+            let mut client = proc.ordering_clients.pop().unwrap();
+            match proc.attendants.release_for(1, client.as_mut()) {
                 Ok(()) => println!("Attendant deallocated"),
                 Err(_) => println!("Error")
             }
-            let client = proc.ordering_clients.pop().unwrap().downcast::<Client>().unwrap();
+            let client = client.downcast::<Client>().unwrap();
 
             proc.food_prep_queue.push(Box::new(
                 Food::new("Food", Scheduler::time(), client.id().clone(), false)
             ));
 
-            match client.n_people {
-                1 => proc.counter_queue.push(client as Box<dyn Entity>),
-                2 => proc.tables_for_2_queue.push(client as Box<dyn Entity>),
-                3 ..= 4 => proc.tables_for_4_queue.push(client as Box<dyn Entity>),
+            match client.attribute("party_size").and_then(|size| size.as_number()) {
+                Some(size) if size <= 1.0 => proc.counter_queue.push(client as Box<dyn Entity>),
+                Some(size) if size <= 2.0 => proc.tables_for_2_queue.push(client as Box<dyn Entity>),
+                Some(size) if size <= 4.0 => proc.tables_for_4_queue.push(client as Box<dyn Entity>),
                 _ => unimplemented!()
             }
         }
@@ -137,7 +110,7 @@ ProcessWrapper! {
         if !proc.food_prep_queue.is_empty() {
             match proc.cooks.allocate(1) {
                 Ok(()) => {
-                    println!("{:.2} - Food being prepared", Scheduler::time());
+                    println!("{} - Food being prepared", Scheduler::timestamp());
                     proc.food_in_preparation.push(proc.food_prep_queue.pop().unwrap());
                     proc.did_allocate.borrow_mut().push(true);
                 },
@@ -152,7 +125,7 @@ ProcessWrapper! {
 
     @on_end = |proc| {
         if proc.did_allocate.borrow_mut().remove(0) {
-            println!("{:.2} - Food prepared", Scheduler::time());
+            println!("{} - Food prepared", Scheduler::timestamp());
             match proc.cooks.release(1) {
                 Ok(()) => println!("Cook deallocated"),
                 Err(_) => println!("Error")
@@ -178,58 +151,64 @@ ProcessWrapper! {
     };
 }
 
+MatchWrapper! {
+    pub struct MatchFood {
+        clients_to_serve: Rc<dyn EntitySet>,
+    };
+
+    @on_match = |matcher, entities| {
+        let mut entities = entities.into_iter();
+        let food = entities.next().unwrap();
+        let client = entities.next().unwrap();
+        matcher.clients_to_serve.push(client);
+        if let Err(error) = Scheduler::instance().unwrap().dispose(food) {
+            println!("Error: {}", error);
+        }
+    };
+}
+
 ProcessWrapper! {
     pub struct ServeFood {
-        prepared_food_queue: Rc<dyn EntitySet>,
+        clients_to_serve: Rc<dyn EntitySet>,
         counter_seats: Rc<dyn Resource>,
         tables_for_2: Rc<dyn Resource>,
         tables_for_4: Rc<dyn Resource>,
     };
 
     @on_start = |proc| {
-        if !proc.prepared_food_queue.is_empty() {
-            println!("{:.2} - Food being served", Scheduler::time());
-            let food = proc.prepared_food_queue.pop().unwrap().downcast::<Food>().unwrap();
-
-            // Look for client
-            for client in proc.counter_seats.downcast_ref::<TableSeats>().unwrap().clients.borrow_mut().iter_mut() {
-                if client.id() == &food.client_id {
-                    client.downcast_mut::<Client>().unwrap().served = true;
-                    Scheduler::instance().unwrap().schedule_in(
-                        Box::new(Leave::new("Client leave", client.id().clone(), proc.counter_seats.clone())),
-                        sdm::distributions::Uniform::gen(10.0, 15.0)
-                    );
-                    return
-                }
-            }
-
-            for client in proc.tables_for_2.downcast_ref::<TableSeats>().unwrap().clients.borrow_mut().iter_mut() {
-                if client.id() == &food.client_id {
-                    client.downcast_mut::<Client>().unwrap().served = true;
-                    Scheduler::instance().unwrap().schedule_in(
-                        Box::new(Leave::new("Client leave", client.id().clone(), proc.tables_for_2.clone())),
-                        sdm::distributions::Uniform::gen(10.0, 15.0)
-                    );
-                    return
-                }
-            }
+        if let Some(client) = proc.clients_to_serve.pop() {
+            println!("{} - Food being served", Scheduler::timestamp());
+            let mut client = client.downcast::<Client>().unwrap();
+            client.served = true;
 
-            for client in proc.tables_for_4.downcast_ref::<TableSeats>().unwrap().clients.borrow_mut().iter_mut() {
-                if client.id() == &food.client_id {
-                    client.downcast_mut::<Client>().unwrap().served = true;
+            match proc.seat_for(&client) {
+                Ok(seat) => {
                     Scheduler::instance().unwrap().schedule_in(
-                        Box::new(Leave::new("Client leave", client.id().clone(), proc.tables_for_4.clone())),
+                        Box::new(Leave::new("Client leave", *client.id(), seat.clone())),
                         sdm::distributions::Uniform::gen(10.0, 15.0)
                     );
-                    return
-                }
+                    seat.downcast_ref::<TableSeats>().unwrap().clients.borrow_mut().push(client);
+                },
+                Err(error) => println!("Error: {}", error)
             }
         }
     };
 }
 
+impl ServeFood {
+    fn seat_for(&self, client: &Client) -> anyhow::Result<Rc<dyn Resource>> {
+        match client.n_people {
+            1 => Ok(self.counter_seats.clone()),
+            2 => Ok(self.tables_for_2.clone()),
+            3..=4 => Ok(self.tables_for_4.clone()),
+            n_people => Err(anyhow::anyhow!("No seats for a party of {}", n_people)),
+        }
+    }
+}
+
 ProcessWrapper! {
     pub struct SeatClient {
+        seated_clients: Rc<dyn EntitySet>,
         counter_queue: Rc<dyn EntitySet>,
         tables_for_2_queue: Rc<dyn EntitySet>,
         tables_for_4_queue: Rc<dyn EntitySet>,
@@ -239,49 +218,25 @@ ProcessWrapper! {
     };
 
     @on_start = |proc| {
-        if !proc.counter_queue.is_empty() {
-            match proc.counter_seats.allocate(1) {
-                Ok(()) => {
-                    println!("{:.2} - Client being seated at counter", Scheduler::time());
-                    proc.counter_seats
-                        .downcast_ref::<TableSeats>()
-                        .unwrap()
-                        .clients
-                        .borrow_mut()
-                        .push(proc.counter_queue.pop().unwrap());
-                }
-                Err(_) => {}
-            }
+        if !proc.counter_queue.is_empty() && proc.counter_seats.n_allocated() < proc.counter_seats.quantity() {
+            println!("{} - Client being seated at counter", Scheduler::timestamp());
+            let mut client = proc.counter_queue.pop().unwrap();
+            proc.counter_seats.allocate_for(1, client.as_mut()).unwrap();
+            proc.seated_clients.push(client);
         }
 
-        if !proc.tables_for_2_queue.is_empty() {
-            match proc.tables_for_2.allocate(1) {
-                Ok(()) => {
-                    println!("{:.2} - Client being seated in a table for 2", Scheduler::time());
-                    proc.tables_for_2
-                        .downcast_ref::<TableSeats>()
-                        .unwrap()
-                        .clients
-                        .borrow_mut()
-                        .push(proc.tables_for_2_queue.pop().unwrap());
-                }
-                Err(_) => {}
-            }
+        if !proc.tables_for_2_queue.is_empty() && proc.tables_for_2.n_allocated() < proc.tables_for_2.quantity() {
+            println!("{} - Client being seated in a table for 2", Scheduler::timestamp());
+            let mut client = proc.tables_for_2_queue.pop().unwrap();
+            proc.tables_for_2.allocate_for(1, client.as_mut()).unwrap();
+            proc.seated_clients.push(client);
         }
 
-        if !proc.tables_for_4_queue.is_empty() {
-            match proc.tables_for_4.allocate(1) {
-                Ok(()) => {
-                    println!("{:.2} - Client being seated in a table for 4", Scheduler::time());
-                    proc.tables_for_4
-                        .downcast_ref::<TableSeats>()
-                        .unwrap()
-                        .clients
-                        .borrow_mut()
-                        .push(proc.tables_for_4_queue.pop().unwrap());
-                }
-                Err(_) => {}
-            }
+        if !proc.tables_for_4_queue.is_empty() && proc.tables_for_4.n_allocated() < proc.tables_for_4.quantity() {
+            println!("{} - Client being seated in a table for 4", Scheduler::timestamp());
+            let mut client = proc.tables_for_4_queue.pop().unwrap();
+            proc.tables_for_4.allocate_for(1, client.as_mut()).unwrap();
+            proc.seated_clients.push(client);
         }
     };
 }
@@ -296,6 +251,10 @@ EntitySetWrapper! {
     pub struct TableQueue;
 }
 
+EntitySetWrapper! {
+    pub struct SeatedClients;
+}
+
 EventWrapper! {
     pub struct Leave {
         client_id: uuid::Uuid,
@@ -303,7 +262,7 @@ EventWrapper! {
     };
 
     @execute = |event| {
-        println!("{:.2} - Client leaving", Scheduler::time());
+        println!("{} - Client leaving", Scheduler::timestamp());
 
         let mut client_pos = 0;
         for (i, client) in event.seat.downcast_ref::<TableSeats>().unwrap().clients.borrow().iter().enumerate() {
@@ -312,11 +271,16 @@ EventWrapper! {
             }
         }
 
-        event.seat.downcast_ref::<TableSeats>().unwrap().clients.borrow_mut().remove(client_pos);
-        match event.seat.release(1) {
+        let mut client = event.seat.downcast_ref::<TableSeats>().unwrap().clients.borrow_mut().remove(client_pos);
+        client.touch_event(event.name());
+        match event.seat.release_for(1, client.as_mut()) {
             Ok(()) => println!("Seat deallocated"),
             Err(_) => println!("Error")
         }
+
+        if let Err(error) = Scheduler::instance().unwrap().dispose(client) {
+            println!("Error: {}", error);
+        }
     };
 }
 
@@ -330,6 +294,13 @@ EntitySetWrapper! {
 
 fn main() {
     if let Ok(scheduler) = Scheduler::new() {
+        // Times are in minutes, starting on a Tuesday at opening time
+        scheduler.set_time_unit(TimeUnit::Minutes);
+        scheduler.set_calendar(DateTime::new(2024, 1, 2, 11, 0, 0.0).unwrap());
+
+        // Statistics only count once the restaurant has filled up
+        scheduler.set_warm_up(Duration::hours(1.0).sim());
+
         // Create waiters
         // let n_waiters = 3;
         // let waiters = scheduler.manage_entity_set(Waiters::new("Waiters", EntitySetMode::FIFO));
@@ -365,14 +336,15 @@ fn main() {
         // Order queues
         let order_queue =
             scheduler.manage_entity_set(OrderQueue::new("Order queue", EntitySetMode::FIFO));
-        let ordering_clients =
-            scheduler.manage_entity_set(OrderQueue::new("Ordering queue", EntitySetMode::FIFO));
+        let ordering_clients = scheduler
+            .manage_entity_set(OrderQueue::new("Ordering queue", EntitySetMode::FIFO).with_value_added());
 
         // Kitchen queues
         let food_prep_queue = scheduler
             .manage_entity_set(FoodPreparationQueue::new("Food waiting for prep", EntitySetMode::FIFO));
-        let food_in_preparation_queue = scheduler
-            .manage_entity_set(FoodPreparationQueue::new("Food in preparation", EntitySetMode::FIFO));
+        let food_in_preparation_queue = scheduler.manage_entity_set(
+            FoodPreparationQueue::new("Food in preparation", EntitySetMode::FIFO).with_value_added(),
+        );
         let prepared_food_queue = scheduler
             .manage_entity_set(PreparedFoodQueue::new("Prepared Food", EntitySetMode::FIFO));
 
@@ -383,6 +355,8 @@ fn main() {
             scheduler.manage_entity_set(TableQueue::new("Table for 2 queue", EntitySetMode::FIFO));
         let table_for_4_queue =
             scheduler.manage_entity_set(TableQueue::new("Table for 4 queue", EntitySetMode::FIFO));
+        let seated_clients =
+            scheduler.manage_entity_set(SeatedClients::new("Seated clients", EntitySetMode::FIFO));
 
         // Tables
         let counter_seats =
@@ -392,13 +366,25 @@ fn main() {
         let tables_for_4 =
             scheduler.manage_resource(TableSeats::new("Tables for 4", 7, RefCell::new(vec![])));
 
-        // First client (schedules more clients)
-        let first_arrival = Arrival::new(
+        // Client arrivals
+        let party_size = sdm::DiscreteUniform::new(1, 4).unwrap();
+        scheduler.manage_source(Source::new(
             "Client arrival",
-            f32::round(sdm::distributions::Uniform::gen(1.0, 4.0)) as u32,
+            sdm::distributions::Uniform::new(3.0, 20.0),
             order_queue.clone(),
-        );
-        scheduler.schedule_now(Box::new(first_arrival));
+            move |time| {
+                let n_people = party_size.gen() as u32;
+                println!("{} - Client arrived with size {}", Scheduler::format_time(time), n_people);
+
+                let mut client = Client::new("Client", time, n_people, false);
+                client.set_attribute("party_size", AttributeValue::Number(n_people as f64));
+                client.enable_history();
+                Box::new(client)
+            },
+        )
+        .with_opening_hours(
+            WeeklySchedule::new(Duration::hours(11.0), Duration::hours(23.0), &[Weekday::Monday]).unwrap(),
+        ));
 
         // Processes
         scheduler.start_process_now(Box::new(Order::new(
@@ -415,24 +401,17 @@ fn main() {
         )));
         scheduler.start_process_now(Box::new(PrepareFood::new(
             "Prepare Food",
-            sdm::distributions::Uniform::new(10.0, 20.0), // CANNOT BE NEGATIVE
+            sdm::distributions::Uniform::new(10.0, 20.0),
             food_in_preparation_queue.clone(),
             food_prep_queue.clone(),
             prepared_food_queue.clone(),
             cooks.clone(),
             RefCell::new(vec![])
         )));
-        scheduler.start_process_now(Box::new(ServeFood::new(
-            "Serve food",
-            sdm::distributions::Uniform::new(1.0, 2.0),
-            prepared_food_queue.clone(),
-            counter_seats.clone(),
-            tables_for_2.clone(),
-            tables_for_4.clone()
-        )));
         scheduler.start_process_now(Box::new(SeatClient::new(
             "Seat clients",
             sdm::distributions::Uniform::new(1.0, 2.0),
+            seated_clients.clone(),
             counter_queue.clone(),
             table_for_2_queue.clone(),
             table_for_4_queue.clone(),
@@ -441,7 +420,35 @@ fn main() {
             tables_for_4.clone()
         )));
 
-        // Let's get this show on the road
-        scheduler.simulate();
+        // Serve food once both the food and its client are ready
+        let clients_to_serve =
+            scheduler.manage_entity_set(SeatedClients::new("Clients to serve", EntitySetMode::FIFO));
+        scheduler.manage_match(MatchFood::new(
+            "Match food",
+            vec![
+                (prepared_food_queue.clone(), |food| food.downcast_ref::<Food>().unwrap().client_id),
+                (seated_clients.clone(), |client| *client.id()),
+            ],
+            clients_to_serve.clone()
+        ));
+        scheduler.start_process_now(Box::new(ServeFood::new(
+            "Serve food",
+            sdm::distributions::Uniform::new(1.0, 2.0),
+            clients_to_serve.clone(),
+            counter_seats.clone(),
+            tables_for_2.clone(),
+            tables_for_4.clone()
+        )));
+
+        // Let's get this show on the road, or step through it with `--debug`
+        if std::env::args().any(|arg| arg == "--debug") {
+            // The debugger prints its own stops, without the step banners
+            scheduler.set_quiet(true);
+            if let Err(error) = Debugger::new().and_then(|mut debugger| debugger.repl()) {
+                println!("Debugger error: {}", error);
+            }
+        } else {
+            scheduler.simulate();
+        }
     }
 }
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+
+use super::experiment::Summary;
+
+// Batch size selection starts from at most this many batches, doubling the size
+// until successive batch means look uncorrelated
+const MAX_BATCHES: usize = 256;
+const MIN_BATCHES: usize = 10;
+const MAX_LAG1_AUTOCORRELATION: f64 = 0.1;
+
+/// Welch's procedure to choose a warm-up length: averages each period across replications,
+/// then smooths the averages with a moving average of `window` periods on each side, narrowed
+/// near the start to the periods available. The last `window` periods lack a full right side,
+/// so only the first `periods - window` smoothed values are returned. The warm-up ends where
+/// the returned curve flattens out.
+pub fn welch_moving_average(replications: &[Vec<f64>], window: usize) -> Result<Vec<f64>> {
+    if replications.is_empty() {
+        return Err(anyhow!("Welch's procedure requires at least one replication"));
+    }
+
+    let periods = replications.iter().map(|replication| replication.len()).min().unwrap();
+    if periods <= window {
+        return Err(anyhow!(
+            "Welch's procedure requires more than {} periods per replication, found {}",
+            window,
+            periods
+        ));
+    }
+
+    let averages: Vec<f64> = (0..periods)
+        .map(|i| replications.iter().map(|replication| replication[i]).sum::<f64>() / replications.len() as f64)
+        .collect();
+
+    // Near the start, the window shrinks to the periods available on both sides
+    Ok((0..periods - window)
+        .map(|i| {
+            let half_width = usize::min(i, window);
+            let centered = &averages[i - half_width..=i + half_width];
+            centered.iter().sum::<f64>() / centered.len() as f64
+        })
+        .collect())
+}
+
+/// Lag-1 autocorrelation of a series, 0 for a constant one
+pub fn lag1_autocorrelation(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+    if variance == 0.0 {
+        return 0.0;
+    }
+
+    let covariance: f64 = values.windows(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum();
+
+    covariance / variance
+}
+
+/// Confidence interval from the means of consecutive batches of a single long run
+#[derive(Debug, Clone)]
+pub struct BatchMeans {
+    pub batch_size: usize,
+    pub means: Vec<f64>,
+    pub lag1_autocorrelation: f64,
+    pub summary: Summary, // Over the batch means, `replications` counts batches
+}
+
+impl BatchMeans {
+    /// `batches` batches of equal size, observations left over at the end are dropped.
+    /// `observations` should exclude the warm-up, e.g. `Tally::observations` after the reset of a
+    /// tally built `with_observations`.
+    pub fn new(name: &str, observations: &[f64], batches: usize, confidence: f64) -> Result<Self> {
+        if batches < 2 {
+            return Err(anyhow!("Batch means require at least 2 batches"));
+        }
+
+        let batch_size = observations.len() / batches;
+        if batch_size == 0 {
+            return Err(anyhow!(
+                "{} observations are not enough for {} batches",
+                observations.len(),
+                batches
+            ));
+        }
+
+        let means: Vec<f64> = observations
+            .chunks_exact(batch_size)
+            .take(batches)
+            .map(|batch| batch.iter().sum::<f64>() / batch_size as f64)
+            .collect();
+
+        Ok(Self {
+            batch_size,
+            lag1_autocorrelation: lag1_autocorrelation(&means),
+            summary: Summary::new(name, &means, confidence),
+            means,
+        })
+    }
+
+    /// Doubles the batch size until the lag-1 autocorrelation of the batch means drops
+    /// below 0.1, failing if the run is too short to get there with at least 10 batches
+    pub fn automatic(name: &str, observations: &[f64], confidence: f64) -> Result<Self> {
+        let mut batch_size = usize::max(1, observations.len() / MAX_BATCHES);
+        while observations.len() / batch_size >= MIN_BATCHES {
+            let batch_means = Self::new(name, observations, observations.len() / batch_size, confidence)?;
+            if batch_means.lag1_autocorrelation < MAX_LAG1_AUTOCORRELATION {
+                return Ok(batch_means);
+            }
+
+            batch_size *= 2;
+        }
+
+        Err(anyhow!(
+            "Batch means of \"{}\" are still correlated with {} batches, the run is too short",
+            name,
+            MIN_BATCHES
+        ))
+    }
+}
@@ -0,0 +1,146 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Number(f64),
+    Text(String),
+    Time(f64),
+    Uuid(Uuid),
+}
+
+impl AttributeValue {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_time(&self) -> Option<f64> {
+        match self {
+            Self::Time(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        match self {
+            Self::Uuid(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Total order, e.g. to sort entities: numbers, then texts, times and ids, each in
+    /// ascending order. Numbers and times are ordered as by `f64::total_cmp`.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        let rank = |value: &Self| match value {
+            Self::Number(_) => 0,
+            Self::Text(_) => 1,
+            Self::Time(_) => 2,
+            Self::Uuid(_) => 3,
+        };
+
+        match (self, other) {
+            (Self::Number(a), Self::Number(b)) | (Self::Time(a), Self::Time(b)) => a.total_cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.cmp(b),
+            (Self::Uuid(a), Self::Uuid(b)) => a.cmp(b),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+/// Values are only comparable to values of the same kind
+impl PartialOrd for AttributeValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Number(a), Self::Number(b)) => a.partial_cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.partial_cmp(b),
+            (Self::Time(a), Self::Time(b)) => a.partial_cmp(b),
+            (Self::Uuid(a), Self::Uuid(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Text(value) => write!(f, "{}", value),
+            Self::Time(value) => write!(f, "{:.2}", value),
+            Self::Uuid(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<Uuid> for AttributeValue {
+    fn from(value: Uuid) -> Self {
+        Self::Uuid(value)
+    }
+}
+
+/// Key-value store carried by every `Entity`
+#[derive(Debug, Default, Clone)]
+pub struct Attributes(HashMap<String, AttributeValue>);
+
+impl Attributes {
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AttributeValue> {
+        self.0.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<AttributeValue>) -> Option<AttributeValue> {
+        self.0.insert(name.to_string(), value.into())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<AttributeValue> {
+        self.0.remove(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &AttributeValue)> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
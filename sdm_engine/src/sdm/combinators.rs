@@ -0,0 +1,310 @@
+use anyhow::{anyhow, Result};
+
+use super::distributions::{numeric_quantile, Distrib, Uniform};
+
+const MAX_REJECTIONS: u32 = 10_000;
+
+/// Lowest probability of `[min, max]` for which rejection sampling is accepted
+const MIN_ACCEPTANCE: f64 = 0.01;
+
+/// Draws used to estimate the probability of `[min, max]` without a CDF
+const PILOT_DRAWS: u32 = 10_000;
+
+/// Restricts a distribution to `[min, max]`.
+/// Samples by inverse CDF when the inner distribution has one, by rejection otherwise, in which
+/// case `[min, max]` must have a probability of at least `MIN_ACCEPTANCE`.
+pub struct Truncated {
+    inner: Box<dyn Distrib>,
+    min: f64,
+    max: f64,
+}
+
+impl Distrib for Truncated {
+    fn gen(&self) -> f64 {
+        if let Some(x) = self.quantile(Uniform::gen(0.0, 1.0)) {
+            return x;
+        }
+
+        for _ in 0..MAX_REJECTIONS {
+            let x = self.inner.gen();
+            if (self.min..=self.max).contains(&x) {
+                return x;
+            }
+        }
+
+        // Less likely than 1e-40 given the acceptance checked by `new`
+        panic!("Truncated rejected {} samples in a row", MAX_REJECTIONS)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let (low, high) = self.cdf_bounds()?;
+        let cdf = self.inner.cdf(x.clamp(self.min, self.max))?;
+        Some(((cdf - low) / (high - low)).clamp(0.0, 1.0))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        let (low, high) = self.cdf_bounds()?;
+        if x < self.min || x > self.max {
+            return Some(0.0);
+        }
+
+        Some(self.inner.pdf(x)? / (high - low))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        let (low, high) = self.cdf_bounds()?;
+        let x = self.inner.quantile(low + p * (high - low))?;
+        Some(x.clamp(self.min, self.max))
+    }
+}
+
+impl Truncated {
+    pub fn new(inner: impl Distrib + 'static, min: f64, max: f64) -> Result<Self> {
+        if min.is_nan() || max.is_nan() || min >= max {
+            return Err(anyhow!("Truncated requires min < max"));
+        }
+
+        let truncated = Self {
+            inner: Box::new(inner),
+            min,
+            max,
+        };
+
+        let probability = match truncated.cdf_bounds() {
+            Some((low, high)) => high - low,
+            None => {
+                let accepted = (0..PILOT_DRAWS)
+                    .filter(|_| (min..=max).contains(&truncated.inner.gen()))
+                    .count();
+                accepted as f64 / PILOT_DRAWS as f64
+            }
+        };
+
+        if probability <= 0.0 {
+            return Err(anyhow!("Truncation interval has zero probability"));
+        }
+
+        let by_inverse_cdf = truncated.quantile(0.5).is_some();
+        if !by_inverse_cdf && probability < MIN_ACCEPTANCE {
+            return Err(anyhow!(
+                "Truncation interval has probability {}, too low to sample by rejection",
+                probability
+            ));
+        }
+
+        Ok(truncated)
+    }
+
+    fn cdf_bounds(&self) -> Option<(f64, f64)> {
+        Some((self.inner.cdf(self.min)?, self.inner.cdf(self.max)?))
+    }
+}
+
+/// `scale * X + offset`
+pub struct Affine {
+    inner: Box<dyn Distrib>,
+    scale: f64,
+    offset: f64,
+}
+
+impl Distrib for Affine {
+    fn gen(&self) -> f64 {
+        self.scale * self.inner.gen() + self.offset
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.scale * self.inner.mean()? + self.offset)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.scale * self.scale * self.inner.variance()?)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let cdf = self.inner.cdf((x - self.offset) / self.scale)?;
+        Some(if self.scale > 0.0 { cdf } else { 1.0 - cdf })
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(self.inner.pdf((x - self.offset) / self.scale)? / self.scale.abs())
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        let p = if self.scale > 0.0 { p } else { 1.0 - p };
+        Some(self.scale * self.inner.quantile(p)? + self.offset)
+    }
+}
+
+impl Affine {
+    pub fn new(inner: impl Distrib + 'static, scale: f64, offset: f64) -> Result<Self> {
+        if scale == 0.0 || !scale.is_finite() {
+            return Err(anyhow!("Affine requires a finite, non-zero scale"));
+        }
+
+        Ok(Self {
+            inner: Box::new(inner),
+            scale,
+            offset,
+        })
+    }
+
+    pub fn shifted(inner: impl Distrib + 'static, offset: f64) -> Self {
+        Self {
+            inner: Box::new(inner),
+            scale: 1.0,
+            offset,
+        }
+    }
+}
+
+/// Largest of two independent samples
+pub struct Maximum {
+    a: Box<dyn Distrib>,
+    b: Box<dyn Distrib>,
+}
+
+impl Distrib for Maximum {
+    fn gen(&self) -> f64 {
+        f64::max(self.a.gen(), self.b.gen())
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(self.a.cdf(x)? * self.b.cdf(x)?)
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(self.a.pdf(x)? * self.b.cdf(x)? + self.a.cdf(x)? * self.b.pdf(x)?)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        numeric_quantile(self, p)
+    }
+}
+
+impl Maximum {
+    pub fn new(a: impl Distrib + 'static, b: impl Distrib + 'static) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+        }
+    }
+}
+
+/// Smallest of two independent samples
+pub struct Minimum {
+    a: Box<dyn Distrib>,
+    b: Box<dyn Distrib>,
+}
+
+impl Distrib for Minimum {
+    fn gen(&self) -> f64 {
+        f64::min(self.a.gen(), self.b.gen())
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(1.0 - (1.0 - self.a.cdf(x)?) * (1.0 - self.b.cdf(x)?))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(self.a.pdf(x)? * (1.0 - self.b.cdf(x)?) + (1.0 - self.a.cdf(x)?) * self.b.pdf(x)?)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        numeric_quantile(self, p)
+    }
+}
+
+impl Minimum {
+    pub fn new(a: impl Distrib + 'static, b: impl Distrib + 'static) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+        }
+    }
+}
+
+/// Picks one of the components at random, according to their weights
+pub struct Mixture {
+    components: Vec<(f64, Box<dyn Distrib>)>,
+}
+
+impl Distrib for Mixture {
+    fn gen(&self) -> f64 {
+        let u = Uniform::gen(0.0, 1.0);
+
+        let mut cumulative = 0f64;
+        for (weight, component) in self.components.iter() {
+            cumulative += weight;
+            if u < cumulative {
+                return component.gen();
+            }
+        }
+
+        self.components.last().unwrap().1.gen()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        let mut mean = 0f64;
+        for (weight, component) in self.components.iter() {
+            mean += weight * component.mean()?;
+        }
+
+        Some(mean)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let mut second_moment = 0f64;
+        for (weight, component) in self.components.iter() {
+            second_moment += weight * (component.variance()? + component.mean()?.powi(2));
+        }
+
+        Some(second_moment - self.mean()?.powi(2))
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let mut cdf = 0f64;
+        for (weight, component) in self.components.iter() {
+            cdf += weight * component.cdf(x)?;
+        }
+
+        Some(cdf)
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        let mut pdf = 0f64;
+        for (weight, component) in self.components.iter() {
+            pdf += weight * component.pdf(x)?;
+        }
+
+        Some(pdf)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        numeric_quantile(self, p)
+    }
+}
+
+impl Mixture {
+    /// Weights are normalized to sum to one
+    pub fn new(components: Vec<(f64, Box<dyn Distrib>)>) -> Result<Self> {
+        if components.is_empty() {
+            return Err(anyhow!("Mixture requires at least one component"));
+        }
+
+        if components.iter().any(|(weight, _)| *weight < 0.0 || !weight.is_finite()) {
+            return Err(anyhow!("Mixture weights must be finite and non-negative"));
+        }
+
+        let total: f64 = components.iter().map(|(weight, _)| weight).sum();
+        if total <= 0.0 {
+            return Err(anyhow!("Mixture weights must not all be zero"));
+        }
+
+        Ok(Self {
+            components: components
+                .into_iter()
+                .map(|(weight, component)| (weight / total, component))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+
+use super::distributions::{Distrib, Gaussian};
+use super::special;
+
+// Standard normal values are integrated over [-Z_MAX, Z_MAX]
+const Z_MAX: f64 = 7.0;
+const FINE_STEP: f64 = 0.01;
+const COARSE_STEP: f64 = 0.1;
+
+/// `F^-1(Phi(z))` of a distribution, tabulated to integrate over normal values
+struct NormalTransform {
+    values: Vec<f64>,
+    mean: f64,
+    std: f64,
+}
+
+impl NormalTransform {
+    fn new(distrib: &dyn Distrib) -> Result<Self> {
+        if !has_finite_variance(distrib) {
+            return Err(anyhow!("Correlated inputs require marginals with a finite variance"));
+        }
+
+        let points = (2.0 * Z_MAX / FINE_STEP).round() as usize + 1;
+        let values = (0..points)
+            .map(|i| {
+                let z = -Z_MAX + i as f64 * FINE_STEP;
+                distrib
+                    .sample_from_u(special::normal_cdf(z))
+                    .ok_or_else(|| anyhow!("Correlated inputs require distributions with a quantile function"))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+
+        // Computed once, as every bisection step of `base_correlation` needs them
+        let mut transform = Self {
+            values,
+            mean: 0.0,
+            std: 0.0,
+        };
+        let (mean, std) = mean_std(&transform);
+        transform.mean = mean;
+        transform.std = std;
+
+        Ok(transform)
+    }
+
+    fn at(&self, z: f64) -> f64 {
+        let position = ((z.clamp(-Z_MAX, Z_MAX) + Z_MAX) / FINE_STEP).min((self.values.len() - 1) as f64);
+        let i = usize::min(position as usize, self.values.len() - 2);
+        let fraction = position - i as f64;
+
+        self.values[i] + fraction * (self.values[i + 1] - self.values[i])
+    }
+}
+
+/// Normal values and trapezoid weights to integrate against the standard normal density
+fn normal_grid() -> impl Iterator<Item = (f64, f64)> {
+    let points = (2.0 * Z_MAX / COARSE_STEP).round() as usize + 1;
+    (0..points).map(|i| {
+        let z = -Z_MAX + i as f64 * COARSE_STEP;
+        (z, COARSE_STEP * (-z * z / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt())
+    })
+}
+
+/// Whether the variance is finite: as reported by the distribution, otherwise from the
+/// power-law index of both tails of the quantile function, which must exceed 2
+fn has_finite_variance(distrib: &dyn Distrib) -> bool {
+    if let Some(variance) = distrib.variance() {
+        return variance.is_finite();
+    } else if distrib.mean().is_some() {
+        // Moments are known, so a missing variance is an infinite one
+        return false;
+    }
+
+    let median = match distrib.quantile(0.5) {
+        Some(median) => median,
+        None => return true,
+    };
+
+    let (near, far) = (special::normal_cdf(-Z_MAX + 1.0), special::normal_cdf(-Z_MAX));
+    [(near, far), (1.0 - near, 1.0 - far)].iter().all(|(near_u, far_u)| {
+        let (x_near, x_far) = match (distrib.sample_from_u(*near_u), distrib.sample_from_u(*far_u)) {
+            (Some(x_near), Some(x_far)) => (x_near, x_far),
+            _ => return true,
+        };
+
+        let (d_near, d_far) = ((x_near - median).abs(), (x_far - median).abs());
+        if !d_far.is_finite() {
+            false
+        } else if d_far <= d_near || d_near <= 0.0 {
+            true
+        } else {
+            (near / far).ln() / (d_far / d_near).ln() > 2.0
+        }
+    })
+}
+
+fn mean_std(transform: &NormalTransform) -> (f64, f64) {
+    let (mut mean, mut second_moment) = (0f64, 0f64);
+    for (z, weight) in normal_grid() {
+        let x = transform.at(z);
+        mean += weight * x;
+        second_moment += weight * x * x;
+    }
+
+    (mean, (second_moment - mean * mean).max(0.0).sqrt())
+}
+
+/// Correlation of `F^-1(Phi(Z1))` and `G^-1(Phi(Z2))` for standard normals with correlation `base`
+fn correlation(a: &NormalTransform, b: &NormalTransform, base: f64) -> f64 {
+    let complement = (1.0 - base * base).max(0.0).sqrt();
+
+    // Z2 = base * U + sqrt(1 - base^2) * V, with U and V independent
+    let mut product = 0f64;
+    for (u, weight_u) in normal_grid() {
+        let x = a.at(u);
+        for (v, weight_v) in normal_grid() {
+            product += weight_u * weight_v * x * b.at(base * u + complement * v);
+        }
+    }
+
+    (product - a.mean * b.mean) / (a.std * b.std)
+}
+
+/// Base normal correlation giving `target` once transformed, found by bisection
+fn base_correlation(a: &NormalTransform, b: &NormalTransform, target: f64) -> Result<f64> {
+    let (min, max) = (correlation(a, b, -1.0), correlation(a, b, 1.0));
+    if target < min || target > max {
+        return Err(anyhow!(
+            "Correlation {} is not feasible for these marginals, it must be within [{:.3}, {:.3}]",
+            target,
+            min,
+            max
+        ));
+    }
+
+    let (mut low, mut high) = (-1f64, 1f64);
+    for _ in 0..50 {
+        let mid = (low + high) / 2.0;
+        if correlation(a, b, mid) < target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok((low + high) / 2.0)
+}
+
+/// Correlation between two marginals when driven by normals with correlation `base`
+pub fn transformed_correlation(a: &dyn Distrib, b: &dyn Distrib, base: f64) -> Result<f64> {
+    Ok(correlation(&NormalTransform::new(a)?, &NormalTransform::new(b)?, base.clamp(-1.0, 1.0)))
+}
+
+/// Autoregressive-to-anything (ARTA) process: an AR(1) standard normal series `Z` mapped
+/// through `F^-1(Phi(Z))`, so every value follows the marginal but successive values are correlated
+pub struct Autocorrelated {
+    marginal: Box<dyn Distrib>,
+    phi: f64,
+    state: RefCell<Option<f64>>,
+}
+
+impl Distrib for Autocorrelated {
+    fn gen(&self) -> f64 {
+        let mut state = self.state.borrow_mut();
+        let z = match *state {
+            Some(z) => self.phi * z + (1.0 - self.phi * self.phi).sqrt() * Gaussian::marsaglia_polar_gen(),
+            None => Gaussian::marsaglia_polar_gen(),
+        };
+        *state = Some(z);
+
+        self.marginal.sample_from_u(special::normal_cdf(z)).unwrap()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        self.marginal.mean()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        self.marginal.variance()
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        self.marginal.cdf(x)
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        self.marginal.pdf(x)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        self.marginal.quantile(p)
+    }
+}
+
+impl Autocorrelated {
+    /// Lag-1 autocorrelation `lag1_correlation` between successive values
+    pub fn new(marginal: impl Distrib + 'static, lag1_correlation: f64) -> Result<Self> {
+        let transform = NormalTransform::new(&marginal)?;
+        let phi = base_correlation(&transform, &transform, lag1_correlation)?;
+
+        Self::from_base(marginal, phi)
+    }
+
+    /// Autoregressive coefficient `phi` of the underlying normal series
+    pub fn from_base(marginal: impl Distrib + 'static, phi: f64) -> Result<Self> {
+        if !(-1.0 < phi && phi < 1.0) {
+            return Err(anyhow!("Autocorrelated requires -1 < phi < 1"));
+        }
+
+        if marginal.sample_from_u(0.5).is_none() {
+            return Err(anyhow!("Autocorrelated requires a distribution with a quantile function"));
+        }
+
+        Ok(Self {
+            marginal: Box::new(marginal),
+            phi,
+            state: RefCell::new(None),
+        })
+    }
+
+    pub fn phi(&self) -> f64 {
+        self.phi
+    }
+}
+
+/// NORmal-To-Anything: correlated vectors with arbitrary marginals
+pub struct Norta {
+    marginals: Vec<Box<dyn Distrib>>,
+    base: Vec<Vec<f64>>,
+    cholesky: Vec<Vec<f64>>,
+}
+
+impl Norta {
+    /// `correlation[i][j]` is the target correlation between the i-th and j-th marginals
+    pub fn new(marginals: Vec<Box<dyn Distrib>>, correlation: &[Vec<f64>]) -> Result<Self> {
+        let n = marginals.len();
+        if correlation.len() != n || correlation.iter().any(|row| row.len() != n) {
+            return Err(anyhow!("Correlation matrix must be {}x{}", n, n));
+        }
+
+        for (i, row) in correlation.iter().enumerate() {
+            if row[i] != 1.0 {
+                return Err(anyhow!("Correlation matrix must have a unit diagonal"));
+            }
+
+            for (j, value) in row.iter().enumerate().take(i) {
+                if *value != correlation[j][i] || value.abs() > 1.0 {
+                    return Err(anyhow!("Correlation matrix must be symmetric, with entries in [-1, 1]"));
+                }
+            }
+        }
+
+        let transforms = marginals
+            .iter()
+            .map(|marginal| NormalTransform::new(marginal.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut base = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            base[i][i] = 1.0;
+            for j in 0..i {
+                base[i][j] = if correlation[i][j] == 0.0 {
+                    0.0
+                } else {
+                    base_correlation(&transforms[i], &transforms[j], correlation[i][j])?
+                };
+                base[j][i] = base[i][j];
+            }
+        }
+
+        let cholesky = Self::cholesky(&base)?;
+
+        Ok(Self {
+            marginals,
+            base,
+            cholesky,
+        })
+    }
+
+    /// Lower-triangular `L` with `L * L^T = matrix`
+    fn cholesky(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+        let n = matrix.len();
+        let mut lower = vec![vec![0.0; n]; n];
+
+        for i in 0..n {
+            for j in 0..=i {
+                let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+                if i == j {
+                    let diagonal = matrix[i][i] - sum;
+                    if diagonal <= 0.0 {
+                        return Err(anyhow!(
+                            "The normal correlation matrix is not positive definite, these correlations cannot be \
+                             reached with NORTA"
+                        ));
+                    }
+                    lower[i][i] = diagonal.sqrt();
+                } else {
+                    lower[i][j] = (matrix[i][j] - sum) / lower[j][j];
+                }
+            }
+        }
+
+        Ok(lower)
+    }
+
+    pub fn gen(&self) -> Vec<f64> {
+        let independent: Vec<f64> = (0..self.dimensions()).map(|_| Gaussian::marsaglia_polar_gen()).collect();
+
+        self.cholesky
+            .iter()
+            .zip(self.marginals.iter())
+            .map(|(row, marginal)| {
+                let z: f64 = row.iter().zip(independent.iter()).map(|(l, e)| l * e).sum();
+                marginal.sample_from_u(special::normal_cdf(z)).unwrap()
+            })
+            .collect()
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.marginals.len()
+    }
+
+    /// Correlations of the underlying normal vector
+    pub fn base_correlation(&self) -> &[Vec<f64>] {
+        &self.base
+    }
+}
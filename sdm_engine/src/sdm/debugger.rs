@@ -0,0 +1,545 @@
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::rc::Rc;
+use uuid::Uuid;
+
+use super::observer::Observer;
+use super::{Entity, Event, Process, Scheduler};
+
+const HELP: &str = "\
+step [n]                      Simulate n steps, 1 by default
+run                           Simulate until a breakpoint or the end
+until <time>                  Simulate until a breakpoint or <time>
+break event <name>            Stop after an event executes
+break process <name>          Stop after a process starts or ends a cycle
+break entity <id>             Stop after an entity, by id prefix, enters or leaves a set
+break if <expr> <op> <value>  Stop once a condition becomes true, e.g. \"break if size Queue > 5\"
+breakpoints                   List breakpoints
+delete <n>                    Delete breakpoint n
+watch [expr]                  Show <expr> at every stop, or show every watch
+unwatch <n>                   Delete watch n
+fel                           Future events
+processes                     Running processes and their pending callbacks
+sets                          Entity sets and their sizes
+set <name>                    Entities in a set
+resources                     Resource allocations
+quit                          Leave the debugger
+
+Expressions: time, events, size <set>, allocated <resource>, stat <statistic>
+Operators: <, <=, >, >=, ==, !=";
+
+/// Quantity of the model, watched or compared in conditional breakpoints
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Time,
+    Events,            // Events in the FEL
+    Size(String),      // Entities in a set
+    Allocated(String), // Units allocated of a resource
+    Statistic(String), // Any statistic of `Scheduler::statistics_summary`
+}
+
+impl Expression {
+    /// `time`, `events`, `size <set>`, `allocated <resource>` or `stat <statistic>`
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let (keyword, name) = text.split_once(' ').map_or((text, ""), |(keyword, name)| (keyword, name.trim()));
+
+        match (keyword, name) {
+            ("time", "") => Ok(Self::Time),
+            ("events", "") => Ok(Self::Events),
+            ("size", name) if !name.is_empty() => Ok(Self::Size(name.to_string())),
+            ("allocated", name) if !name.is_empty() => Ok(Self::Allocated(name.to_string())),
+            ("stat", name) if !name.is_empty() => Ok(Self::Statistic(name.to_string())),
+            _ => Err(anyhow!("Unknown expression \"{}\"", text)),
+        }
+    }
+
+    pub fn evaluate(&self, scheduler: &Scheduler) -> Result<f64> {
+        match self {
+            Self::Time => Ok(Scheduler::time()),
+            Self::Events => Ok(scheduler.scheduled_events().len() as f64),
+            Self::Size(name) => scheduler
+                .entity_sets()
+                .iter()
+                .find(|entity_set| entity_set.name() == name)
+                .map(|entity_set| entity_set.size() as f64)
+                .ok_or_else(|| anyhow!("No entity set named \"{}\"", name)),
+            Self::Allocated(name) => scheduler
+                .resources()
+                .iter()
+                .find(|resource| resource.name() == name)
+                .map(|resource| resource.n_allocated() as f64)
+                .ok_or_else(|| anyhow!("No resource named \"{}\"", name)),
+            Self::Statistic(name) => scheduler
+                .statistics_summary()
+                .into_iter()
+                .find(|(known, _)| known == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| anyhow!("No statistic named \"{}\"", name)),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Time => write!(f, "time"),
+            Self::Events => write!(f, "events"),
+            Self::Size(name) => write!(f, "size {}", name),
+            Self::Allocated(name) => write!(f, "allocated {}", name),
+            Self::Statistic(name) => write!(f, "stat {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Operator {
+    const SYMBOLS: [(&'static str, Self); 6] = [
+        ("<", Self::Less),
+        ("<=", Self::LessOrEqual),
+        (">", Self::Greater),
+        (">=", Self::GreaterOrEqual),
+        ("==", Self::Equal),
+        ("!=", Self::NotEqual),
+    ];
+
+    pub fn parse(symbol: &str) -> Option<Self> {
+        Self::SYMBOLS
+            .iter()
+            .find(|(known, _)| *known == symbol)
+            .map(|(_, operator)| *operator)
+    }
+
+    pub fn holds(&self, a: f64, b: f64) -> bool {
+        match self {
+            Self::Less => a < b,
+            Self::LessOrEqual => a <= b,
+            Self::Greater => a > b,
+            Self::GreaterOrEqual => a >= b,
+            Self::Equal => a == b,
+            Self::NotEqual => a != b,
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (symbol, _) = Self::SYMBOLS.iter().find(|(_, operator)| operator == self).unwrap();
+        write!(f, "{}", symbol)
+    }
+}
+
+pub enum Breakpoint {
+    Event(String),
+    Process(String),
+    Entity(String), // Prefix of the entity's id
+    Condition(Expression, Operator, f64),
+    Predicate(String, Box<dyn Fn(&Scheduler) -> bool>), // Named predicate, set from code
+}
+
+impl Breakpoint {
+    /// `event <name>`, `process <name>`, `entity <id>` or `if <expr> <op> <value>`
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let (keyword, rest) = text
+            .split_once(' ')
+            .ok_or_else(|| anyhow!("Unknown breakpoint \"{}\"", text))?;
+        let rest = rest.trim();
+
+        match keyword {
+            "event" => Ok(Self::Event(rest.to_string())),
+            "process" => Ok(Self::Process(rest.to_string())),
+            "entity" => Ok(Self::Entity(rest.to_string())),
+            "if" => {
+                let tokens: Vec<&str> = rest.split_whitespace().collect();
+                let (i, operator) = tokens
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, token)| Some((i, Operator::parse(token)?)))
+                    .ok_or_else(|| anyhow!("Condition \"{}\" has no operator", rest))?;
+                let value = tokens[i + 1..]
+                    .join(" ")
+                    .parse()
+                    .map_err(|_| anyhow!("Condition \"{}\" must compare to a number", rest))?;
+
+                Ok(Self::Condition(Expression::parse(&tokens[..i].join(" "))?, operator, value))
+            }
+            _ => Err(anyhow!("Unknown breakpoint \"{}\"", text)),
+        }
+    }
+
+    /// Whether a condition or predicate holds, always false for other breakpoints
+    fn holds(&self, scheduler: &Scheduler) -> bool {
+        match self {
+            Self::Condition(expression, operator, value) => {
+                matches!(expression.evaluate(scheduler), Ok(evaluated) if operator.holds(evaluated, *value))
+            }
+            Self::Predicate(_, predicate) => predicate(scheduler),
+            _ => false,
+        }
+    }
+
+    fn hit_by(&self, occurrence: &Occurrence) -> bool {
+        match (self, occurrence) {
+            (Self::Event(name), Occurrence::Event(event)) => name == event,
+            (Self::Process(name), Occurrence::Process(process)) => name == process,
+            (Self::Entity(prefix), Occurrence::Entity(id)) => id.to_string().starts_with(prefix.as_str()),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Event(name) => write!(f, "event \"{}\"", name),
+            Self::Process(name) => write!(f, "process \"{}\"", name),
+            Self::Entity(prefix) => write!(f, "entity {}", prefix),
+            Self::Condition(expression, operator, value) => write!(f, "if {} {} {}", expression, operator, value),
+            Self::Predicate(name, _) => write!(f, "predicate \"{}\"", name),
+        }
+    }
+}
+
+/// Why the debugger stopped simulating
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stop {
+    Steps,                   // Every requested step was simulated
+    Time,                    // The requested time was reached
+    Breakpoints(Vec<usize>), // Indices of the breakpoints hit
+    Complete,                // Nothing is left to simulate
+}
+
+enum Occurrence {
+    Event(String),
+    Process(String),
+    Entity(Uuid),
+}
+
+// What happened during the current step, checked against the breakpoints
+#[derive(Default)]
+struct StepLog {
+    occurrences: RefCell<Vec<Occurrence>>,
+}
+
+impl Observer for StepLog {
+    fn on_event_executed(&self, event: &dyn Event) {
+        self.occurrences
+            .borrow_mut()
+            .push(Occurrence::Event(event.name().to_string()));
+    }
+
+    fn on_process_started(&self, process: &dyn Process, _duration: f64) {
+        self.occurrences
+            .borrow_mut()
+            .push(Occurrence::Process(process.name().to_string()));
+    }
+
+    fn on_process_ended(&self, process: &dyn Process) {
+        self.occurrences
+            .borrow_mut()
+            .push(Occurrence::Process(process.name().to_string()));
+    }
+
+    fn on_entity_pushed(&self, _entity_set: &str, entity: &dyn Entity) {
+        self.occurrences.borrow_mut().push(Occurrence::Entity(*entity.id()));
+    }
+
+    fn on_entity_popped(&self, _entity_set: &str, entity: &dyn Entity) {
+        self.occurrences.borrow_mut().push(Occurrence::Entity(*entity.id()));
+    }
+}
+
+/// Step debugger of the current scheduler, driven from code or from the terminal with `repl`
+pub struct Debugger {
+    log: Rc<StepLog>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Expression>,
+}
+
+impl Debugger {
+    pub fn new() -> Result<Self> {
+        let log = Scheduler::instance()?.add_observer(StepLog::default());
+
+        Ok(Self {
+            log,
+            breakpoints: vec![],
+            watches: vec![],
+        })
+    }
+
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint) -> Self {
+        self.breakpoints.push(breakpoint);
+        self
+    }
+
+    pub fn with_watch(mut self, expression: Expression) -> Self {
+        self.watches.push(expression);
+        self
+    }
+
+    /// Simulates one step, returning the breakpoints it hit. Conditions are hit when they
+    /// become true, so that running on does not stop at every step while they hold.
+    fn step_once(&self, scheduler: &Scheduler) -> Vec<usize> {
+        let held: Vec<bool> = self.breakpoints.iter().map(|breakpoint| breakpoint.holds(scheduler)).collect();
+        self.log.occurrences.borrow_mut().clear();
+
+        scheduler.simulate_one_step();
+
+        let occurrences = self.log.occurrences.take();
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .filter(|(i, breakpoint)| {
+                occurrences.iter().any(|occurrence| breakpoint.hit_by(occurrence))
+                    || (!held[*i] && breakpoint.holds(scheduler))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Simulates up to `steps` steps, stopping early at breakpoints
+    pub fn step(&self, steps: usize) -> Result<Stop> {
+        let scheduler = Scheduler::instance()?;
+        for _ in 0..steps {
+            if scheduler.next_time().is_none() {
+                return Ok(Stop::Complete);
+            }
+
+            let hits = self.step_once(scheduler);
+            if !hits.is_empty() {
+                return Ok(Stop::Breakpoints(hits));
+            }
+        }
+
+        Ok(Stop::Steps)
+    }
+
+    /// Simulates until a breakpoint, `end_time` if any, or the end of the simulation
+    pub fn run_until(&self, end_time: Option<f64>) -> Result<Stop> {
+        let scheduler = Scheduler::instance()?;
+        loop {
+            match (scheduler.next_time(), end_time) {
+                (None, None) => return Ok(Stop::Complete),
+                (None, Some(end_time)) => {
+                    scheduler.simulate_until(end_time);
+                    return Ok(Stop::Time);
+                }
+                (Some(time), Some(end_time)) if time > end_time => {
+                    scheduler.simulate_until(end_time);
+                    return Ok(Stop::Time);
+                }
+                _ => {
+                    let hits = self.step_once(scheduler);
+                    if !hits.is_empty() {
+                        return Ok(Stop::Breakpoints(hits));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Where the simulation stopped, and the value of every watch
+    fn location(&self, stop: &Stop) -> String {
+        let mut lines = vec![];
+        match stop {
+            Stop::Breakpoints(hits) => {
+                for i in hits {
+                    lines.push(format!("Breakpoint {}: {}", i + 1, self.breakpoints[*i]));
+                }
+            }
+            Stop::Complete => lines.push("Simulation complete".to_string()),
+            Stop::Steps | Stop::Time => {}
+        }
+
+        lines.push(format!("Stopped at {}", Scheduler::timestamp()));
+        lines.extend(self.watch_lines());
+        lines.join("\n")
+    }
+
+    fn watch_lines(&self) -> Vec<String> {
+        let scheduler = Scheduler::instance();
+        self.watches
+            .iter()
+            .enumerate()
+            .map(|(i, expression)| {
+                let value = match &scheduler {
+                    Ok(scheduler) => match expression.evaluate(scheduler) {
+                        Ok(value) => value.to_string(),
+                        Err(error) => error.to_string(),
+                    },
+                    Err(error) => error.to_string(),
+                };
+                format!("{}: {} = {}", i + 1, expression, value)
+            })
+            .collect()
+    }
+
+    fn index(text: &str, count: usize, kind: &str) -> Result<usize> {
+        match text.trim().parse::<usize>() {
+            Ok(n) if 1 <= n && n <= count => Ok(n - 1),
+            _ => Err(anyhow!("No {} numbered \"{}\"", kind, text.trim())),
+        }
+    }
+
+    /// Runs one command, returning what it prints
+    pub fn execute(&mut self, command: &str) -> Result<String> {
+        let command = command.trim();
+        let (keyword, rest) = command.split_once(' ').unwrap_or((command, ""));
+        let rest = rest.trim();
+        let scheduler = Scheduler::instance()?;
+
+        match keyword {
+            "step" | "s" => {
+                let steps = if rest.is_empty() {
+                    1
+                } else {
+                    rest.parse().map_err(|_| anyhow!("Invalid step count \"{}\"", rest))?
+                };
+                let stop = self.step(steps)?;
+                Ok(self.location(&stop))
+            }
+            "run" | "r" => {
+                let stop = self.run_until(None)?;
+                Ok(self.location(&stop))
+            }
+            "until" => {
+                let end_time = rest.parse().map_err(|_| anyhow!("Invalid time \"{}\"", rest))?;
+                let stop = self.run_until(Some(end_time))?;
+                Ok(self.location(&stop))
+            }
+            "break" | "b" => {
+                self.breakpoints.push(Breakpoint::parse(rest)?);
+                Ok(format!("Breakpoint {}: {}", self.breakpoints.len(), self.breakpoints.last().unwrap()))
+            }
+            "breakpoints" => Ok(self
+                .breakpoints
+                .iter()
+                .enumerate()
+                .map(|(i, breakpoint)| format!("{}: {}", i + 1, breakpoint))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            "delete" => {
+                let i = Self::index(rest, self.breakpoints.len(), "breakpoint")?;
+                Ok(format!("Deleted breakpoint {}", self.breakpoints.remove(i)))
+            }
+            "watch" => {
+                if !rest.is_empty() {
+                    self.watches.push(Expression::parse(rest)?);
+                }
+                Ok(self.watch_lines().join("\n"))
+            }
+            "unwatch" => {
+                let i = Self::index(rest, self.watches.len(), "watch")?;
+                Ok(format!("Deleted watch {}", self.watches.remove(i)))
+            }
+            "fel" => Ok(scheduler
+                .scheduled_events()
+                .iter()
+                .map(|(time, name)| format!("- {} \"{}\"", Scheduler::format_time(*time), name))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            "processes" => {
+                let mut lines: Vec<String> = scheduler
+                    .running_processes()
+                    .iter()
+                    .map(|name| format!("- \"{}\"", name))
+                    .collect();
+                lines.push("Callbacks:".to_string());
+                for (time, name) in scheduler.scheduled_process_callbacks() {
+                    lines.push(format!("- {} \"{}\"", Scheduler::format_time(time), name));
+                }
+                Ok(lines.join("\n"))
+            }
+            "sets" => Ok(scheduler
+                .entity_sets()
+                .iter()
+                .map(|entity_set| format!("- {}: {}", entity_set.name(), entity_set.size()))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            "set" => {
+                let entity_set = scheduler
+                    .entity_sets()
+                    .into_iter()
+                    .find(|entity_set| entity_set.name() == rest)
+                    .ok_or_else(|| anyhow!("No entity set named \"{}\"", rest))?;
+
+                let mut lines = vec![];
+                entity_set.for_each(&mut |entity| {
+                    let mut attributes: Vec<String> = entity
+                        .attributes()
+                        .iter()
+                        .map(|(name, value)| format!("{} = {}", name, value))
+                        .collect();
+                    attributes.sort();
+
+                    let waited = entity_set.time_in_set(*entity.id()).unwrap_or(0.0);
+                    lines.push(format!(
+                        "- {} {} ({:.2} in set) {}",
+                        entity.id(),
+                        entity.name(),
+                        waited,
+                        attributes.join(", ")
+                    ));
+                });
+                Ok(lines.join("\n"))
+            }
+            "resources" => Ok(scheduler
+                .resources()
+                .iter()
+                .map(|resource| format!("- {}: {}/{}", resource.name(), resource.n_allocated(), resource.quantity()))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            "help" | "h" => Ok(HELP.to_string()),
+            _ => Err(anyhow!("Unknown command \"{}\", try \"help\"", command)),
+        }
+    }
+
+    /// Reads commands from `input` until it ends or a `quit`, printing to `output`
+    pub fn run(&mut self, input: impl BufRead, mut output: impl io::Write) -> Result<()> {
+        write!(output, "(sdm) ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            match line.trim() {
+                "quit" | "q" | "exit" => break,
+                "" => {}
+                command => match self.execute(command) {
+                    Ok(printed) if printed.is_empty() => {}
+                    Ok(printed) => writeln!(output, "{}", printed)?,
+                    Err(error) => writeln!(output, "Error: {}", error)?,
+                },
+            }
+
+            write!(output, "(sdm) ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Interactive session on the terminal
+    pub fn repl(&mut self) -> Result<()> {
+        self.run(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl Drop for Debugger {
+    fn drop(&mut self) {
+        if let Ok(scheduler) = Scheduler::instance() {
+            scheduler.remove_observer(&self.log);
+        }
+    }
+}
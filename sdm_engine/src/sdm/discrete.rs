@@ -0,0 +1,484 @@
+use anyhow::{anyhow, Result};
+
+use super::distributions::Uniform;
+use super::special;
+
+pub trait DiscreteDistrib {
+    fn gen(&self) -> i64;
+
+    /// Theoretical mean, if known and finite
+    fn mean(&self) -> Option<f64> {
+        None
+    }
+
+    /// Theoretical variance, if known and finite
+    fn variance(&self) -> Option<f64> {
+        None
+    }
+
+    /// P(X <= x), if known
+    fn cdf(&self, _x: i64) -> Option<f64> {
+        None
+    }
+
+    /// Smallest `x` with P(X <= x) >= p, if known
+    fn quantile(&self, _p: f64) -> Option<i64> {
+        None
+    }
+
+    /// Sample driven by `u` in `[0, 1]` instead of the internal generator, e.g. to use
+    /// Latin hypercube or Sobol points. `None` if the distribution has no quantile function.
+    fn sample_from_u(&self, u: f64) -> Option<i64> {
+        self.quantile(u.clamp(f64::EPSILON, 1.0 - f64::EPSILON))
+    }
+}
+
+impl DiscreteDistrib for Box<dyn DiscreteDistrib> {
+    fn gen(&self) -> i64 {
+        self.as_ref().gen()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        self.as_ref().mean()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        self.as_ref().variance()
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        self.as_ref().cdf(x)
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        self.as_ref().quantile(p)
+    }
+
+    fn sample_from_u(&self, u: f64) -> Option<i64> {
+        self.as_ref().sample_from_u(u)
+    }
+}
+
+/// Smallest `x >= min` with `cdf(x) >= p`, by doubling then bisection
+fn invert_cdf(cdf: impl Fn(i64) -> f64, p: f64, min: i64) -> i64 {
+    let mut low = min;
+    if cdf(low) >= p {
+        return low;
+    }
+
+    let mut step = 1;
+    let mut high = low + step;
+    while cdf(high) < p {
+        low = high;
+        step = step.saturating_mul(2);
+        high = low.saturating_add(step);
+        if high == i64::MAX {
+            break;
+        }
+    }
+
+    // cdf(low) < p <= cdf(high)
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if cdf(middle) >= p {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+
+    high
+}
+
+fn unit() -> f64 {
+    Uniform::gen(0.0, 1.0)
+}
+
+fn check_probability(p: f64, name: &str) -> Result<()> {
+    if (0.0..=1.0).contains(&p) {
+        Ok(())
+    } else {
+        Err(anyhow!("{} requires 0 <= p <= 1", name))
+    }
+}
+
+pub struct Bernoulli {
+    p: f64,
+}
+
+impl DiscreteDistrib for Bernoulli {
+    fn gen(&self) -> i64 {
+        (unit() < self.p) as i64
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.p)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.p * (1.0 - self.p))
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        Some(match x {
+            x if x < 0 => 0.0,
+            0 => 1.0 - self.p,
+            _ => 1.0,
+        })
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        Some((p > 1.0 - self.p) as i64)
+    }
+}
+
+impl Bernoulli {
+    pub fn new(p: f64) -> Result<Self> {
+        check_probability(p, "Bernoulli")?;
+        Ok(Self { p })
+    }
+}
+
+/// Uniform over the integers in `[min, max]`
+pub struct DiscreteUniform {
+    min: i64,
+    max: i64,
+}
+
+impl DiscreteDistrib for DiscreteUniform {
+    fn gen(&self) -> i64 {
+        // Wide enough for the span between the extreme `i64` bounds
+        let span = self.max as i128 - self.min as i128 + 1;
+        i128::min(self.min as i128 + (unit() * span as f64) as i128, self.max as i128) as i64
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some((self.min as f64 + self.max as f64) / 2.0)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let span = (self.max as i128 - self.min as i128 + 1) as f64;
+        Some((span * span - 1.0) / 12.0)
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        let span = self.max as i128 - self.min as i128 + 1;
+        let below = (x as i128 - self.min as i128 + 1).clamp(0, span);
+        Some(below as f64 / span as f64)
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        let span = self.max as i128 - self.min as i128 + 1;
+        let offset = ((p * span as f64).ceil() as i128 - 1).clamp(0, span - 1);
+        Some((self.min as i128 + offset) as i64)
+    }
+}
+
+impl DiscreteUniform {
+    pub fn new(min: i64, max: i64) -> Result<Self> {
+        if min > max {
+            return Err(anyhow!("DiscreteUniform requires min <= max"));
+        }
+
+        Ok(Self { min, max })
+    }
+}
+
+pub struct Poisson {
+    mean: f64,
+}
+
+impl DiscreteDistrib for Poisson {
+    fn gen(&self) -> i64 {
+        if self.mean < 10.0 {
+            // Knuth's method, in O(mean)
+            let limit = (-self.mean).exp();
+            let mut count = 0;
+            let mut product = unit();
+            while product > limit {
+                count += 1;
+                product *= unit();
+            }
+
+            return count;
+        }
+
+        // Hörmann's transformed rejection (PTRS), in constant expected time
+        let sqrt_mean = self.mean.sqrt();
+        let b = 0.931 + 2.53 * sqrt_mean;
+        let a = -0.059 + 0.02483 * b;
+        let inv_alpha = 1.1239 + 1.1328 / (b - 3.4);
+        let v_r = 0.9277 - 3.6224 / (b - 2.0);
+
+        loop {
+            let u = unit() - 0.5;
+            let v = unit();
+            let us = 0.5 - u.abs();
+            let k = ((2.0 * a / us + b) * u + self.mean + 0.43).floor();
+
+            if us >= 0.07 && v <= v_r {
+                return k as i64;
+            }
+
+            if k < 0.0 || (us < 0.013 && v > us) {
+                continue;
+            }
+
+            let log_accept = -self.mean + k * self.mean.ln() - special::ln_gamma(k + 1.0);
+            if v.ln() + inv_alpha.ln() - (a / (us * us) + b).ln() <= log_accept {
+                return k as i64;
+            }
+        }
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.mean)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.mean)
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        if x < 0 {
+            return Some(0.0);
+        }
+
+        Some(1.0 - special::gamma_p(x as f64 + 1.0, self.mean))
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        Some(invert_cdf(|x| self.cdf(x).unwrap(), p, 0))
+    }
+}
+
+impl Poisson {
+    pub fn new(mean: f64) -> Result<Self> {
+        if !mean.is_finite() || mean <= 0.0 {
+            return Err(anyhow!("Poisson requires finite mean > 0"));
+        }
+
+        Ok(Self { mean })
+    }
+}
+
+/// Number of successes in `trials` Bernoulli trials
+pub struct Binomial {
+    trials: u32,
+    p: f64,
+}
+
+impl DiscreteDistrib for Binomial {
+    fn gen(&self) -> i64 {
+        // Skip over failures with geometric jumps, using the rarer outcome
+        let p = f64::min(self.p, 1.0 - self.p);
+        let successes = if p <= 0.0 {
+            0
+        } else {
+            let geometric = Geometric { p };
+            let mut successes = 0;
+            let mut position = geometric.gen();
+            while position < self.trials as i64 {
+                successes += 1;
+                position += geometric.gen() + 1;
+            }
+            successes
+        };
+
+        if self.p <= 0.5 {
+            successes
+        } else {
+            self.trials as i64 - successes
+        }
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.trials as f64 * self.p)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.trials as f64 * self.p * (1.0 - self.p))
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        if x < 0 {
+            return Some(0.0);
+        } else if x >= self.trials as i64 {
+            return Some(1.0);
+        }
+
+        let x = x as f64;
+        Some(special::beta_inc(self.trials as f64 - x, x + 1.0, 1.0 - self.p))
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        Some(invert_cdf(|x| self.cdf(x).unwrap(), p, 0))
+    }
+}
+
+impl Binomial {
+    pub fn new(trials: u32, p: f64) -> Result<Self> {
+        check_probability(p, "Binomial")?;
+        Ok(Self { trials, p })
+    }
+}
+
+/// Number of failures before the first success
+pub struct Geometric {
+    p: f64,
+}
+
+impl DiscreteDistrib for Geometric {
+    fn gen(&self) -> i64 {
+        if self.p >= 1.0 {
+            return 0;
+        }
+
+        ((1.0 - unit()).ln() / (1.0 - self.p).ln()).floor() as i64
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some((1.0 - self.p) / self.p)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some((1.0 - self.p) / (self.p * self.p))
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        if x < 0 {
+            return Some(0.0);
+        }
+
+        Some(1.0 - (1.0 - self.p).powf(x as f64 + 1.0))
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        Some(invert_cdf(|x| self.cdf(x).unwrap(), p, 0))
+    }
+}
+
+impl Geometric {
+    pub fn new(p: f64) -> Result<Self> {
+        if !(p > 0.0 && p <= 1.0) {
+            return Err(anyhow!("Geometric requires 0 < p <= 1"));
+        }
+
+        Ok(Self { p })
+    }
+}
+
+/// Number of failures before the `successes`-th success
+pub struct NegativeBinomial {
+    successes: u32,
+    p: f64,
+}
+
+impl DiscreteDistrib for NegativeBinomial {
+    fn gen(&self) -> i64 {
+        let geometric = Geometric { p: self.p };
+        (0..self.successes).map(|_| geometric.gen()).sum()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.successes as f64 * (1.0 - self.p) / self.p)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.successes as f64 * (1.0 - self.p) / (self.p * self.p))
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        if x < 0 {
+            return Some(0.0);
+        }
+
+        Some(special::beta_inc(self.successes as f64, x as f64 + 1.0, self.p))
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        Some(invert_cdf(|x| self.cdf(x).unwrap(), p, 0))
+    }
+}
+
+impl NegativeBinomial {
+    pub fn new(successes: u32, p: f64) -> Result<Self> {
+        if successes == 0 || !(p > 0.0 && p <= 1.0) {
+            return Err(anyhow!("NegativeBinomial requires successes > 0 and 0 < p <= 1"));
+        }
+
+        Ok(Self { successes, p })
+    }
+}
+
+/// Arbitrary table of `(value, probability)` pairs
+pub struct DiscreteTable {
+    values: Vec<i64>,
+    cumulative: Vec<f64>,
+    mean: f64,
+    variance: f64,
+}
+
+impl DiscreteDistrib for DiscreteTable {
+    fn gen(&self) -> i64 {
+        let u = unit();
+        let idx = self.cumulative.iter().position(|c| u < *c).unwrap_or(self.values.len() - 1);
+        self.values[idx]
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.mean)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.variance)
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        let below = self.values.partition_point(|v| *v <= x);
+        Some(if below == 0 { 0.0 } else { self.cumulative[below - 1] })
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        let idx = self.cumulative.partition_point(|c| *c < p);
+        Some(self.values[usize::min(idx, self.values.len() - 1)])
+    }
+}
+
+impl DiscreteTable {
+    pub fn new(table: &[(i64, f64)]) -> Result<Self> {
+        if table.is_empty() {
+            return Err(anyhow!("DiscreteTable requires at least one value"));
+        }
+
+        if table.iter().any(|(_, p)| !p.is_finite() || *p < 0.0) {
+            return Err(anyhow!("DiscreteTable probabilities must be finite and non-negative"));
+        }
+
+        let total: f64 = table.iter().map(|(_, p)| p).sum();
+        if (total - 1.0).abs() > 1e-4 {
+            return Err(anyhow!("DiscreteTable probabilities must sum to 1, got {}", total));
+        }
+
+        // Sorted by value, so the cumulative probabilities are also the CDF
+        let mut sorted = table.to_vec();
+        sorted.sort_by_key(|(v, _)| *v);
+
+        let mut cumulative = vec![];
+        let mut sum = 0f64;
+        for (_, p) in sorted.iter() {
+            sum += p / total;
+            cumulative.push(sum);
+        }
+
+        let mean: f64 = table.iter().map(|(v, p)| *v as f64 * p).sum();
+        let variance: f64 = table.iter().map(|(v, p)| (*v as f64 - mean).powi(2) * p).sum();
+
+        Ok(Self {
+            values: sorted.iter().map(|(v, _)| *v).collect(),
+            cumulative,
+            mean,
+            variance,
+        })
+    }
+}
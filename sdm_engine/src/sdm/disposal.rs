@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+use super::Entity;
+
+/// Throughput statistics of a single entity type
+#[derive(Debug, Default, Clone)]
+pub struct DisposalStats {
+    pub created: u32,
+    pub disposed: u32,
+    pub total_system_time: f64,
+    pub max_system_time: f64,
+    wip_sum: u32,
+    wip_sum_count: u32,
+}
+
+impl DisposalStats {
+    pub fn work_in_process(&self) -> u32 {
+        self.created - self.disposed
+    }
+
+    pub fn average_work_in_process(&self) -> f64 {
+        if self.wip_sum_count == 0 {
+            return 0.0;
+        }
+        self.wip_sum as f64 / self.wip_sum_count as f64
+    }
+
+    pub fn average_system_time(&self) -> f64 {
+        if self.disposed == 0 {
+            return 0.0;
+        }
+        self.total_system_time / self.disposed as f64
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DisposalStatistics {
+    types: HashMap<String, DisposalStats>,
+}
+
+impl DisposalStatistics {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::new(),
+        }
+    }
+
+    pub fn created(&mut self, entity_type: &str) {
+        self.types.entry(entity_type.to_string()).or_default().created += 1;
+    }
+
+    /// Fails for entities whose creation was not counted, e.g. created without a `Scheduler`
+    pub fn dispose(&mut self, entity: &dyn Entity, now: f64) -> Result<()> {
+        let system_time = now - entity.creation_time();
+
+        let stats = self.types.entry(entity.entity_type().to_string()).or_default();
+        if stats.disposed >= stats.created {
+            return Err(anyhow!(
+                "More {} entities disposed than created, were some created without a scheduler?",
+                entity.entity_type()
+            ));
+        }
+
+        stats.disposed += 1;
+        stats.total_system_time += system_time;
+        if stats.max_system_time < system_time {
+            stats.max_system_time = system_time;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_analytics(&mut self) {
+        for stats in self.types.values_mut() {
+            stats.wip_sum += stats.work_in_process();
+            stats.wip_sum_count += 1;
+        }
+    }
+
+    /// Forget collected statistics, keeping entities still in the system as work-in-process
+    pub fn reset(&mut self) {
+        for stats in self.types.values_mut() {
+            *stats = DisposalStats {
+                created: stats.work_in_process(),
+                ..Default::default()
+            };
+        }
+    }
+
+    pub fn get(&self, entity_type: &str) -> Option<&DisposalStats> {
+        self.types.get(entity_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &DisposalStats)> {
+        self.types.iter()
+    }
+
+    pub fn total_disposed(&self) -> u32 {
+        self.types.values().map(|stats| stats.disposed).sum()
+    }
+
+    pub fn total_work_in_process(&self) -> u32 {
+        self.types.values().map(|stats| stats.work_in_process()).sum()
+    }
+}
@@ -1,62 +1,233 @@
+use anyhow::{anyhow, Result};
 use rand::distributions::Distribution;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+
+use super::special;
+
+thread_local! {
+    // Every random value of the engine comes from this generator, so a seed reproduces a run
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Reseed the random generator of the current thread, e.g. once per replication
+pub fn set_seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+pub(crate) fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
 
 pub trait Distrib {
-    fn gen(&self) -> f32;
+    fn gen(&self) -> f64;
+
+    /// Theoretical mean, if known and finite
+    fn mean(&self) -> Option<f64> {
+        None
+    }
+
+    /// Theoretical variance, if known and finite
+    fn variance(&self) -> Option<f64> {
+        None
+    }
+
+    /// Cumulative distribution function, if known in closed form
+    fn cdf(&self, _x: f64) -> Option<f64> {
+        None
+    }
+
+    /// Probability density function, if known in closed form
+    fn pdf(&self, _x: f64) -> Option<f64> {
+        None
+    }
+
+    /// Inverse of the cumulative distribution function, if known
+    fn quantile(&self, _p: f64) -> Option<f64> {
+        None
+    }
+
+    /// Sample driven by `u` in `[0, 1]` instead of the internal generator, e.g. to use
+    /// Latin hypercube or Sobol points. `None` if the distribution has no quantile function.
+    fn sample_from_u(&self, u: f64) -> Option<f64> {
+        self.quantile(u.clamp(f64::EPSILON, 1.0 - f64::EPSILON))
+    }
+}
+
+/// Quantile found by inverting the CDF numerically, for distributions without a closed form
+pub(crate) fn numeric_quantile(distrib: &(impl Distrib + ?Sized), p: f64) -> Option<f64> {
+    distrib.cdf(0.0)?;
+
+    let (low, high) = match (distrib.mean(), distrib.variance()) {
+        (Some(mean), Some(variance)) => (mean - variance.sqrt(), mean + variance.sqrt()),
+        _ => (-1.0, 1.0),
+    };
+
+    Some(special::invert_cdf(|x| distrib.cdf(x).unwrap(), p, low, high))
+}
+
+/// Whether every parameter is finite, rejecting NaN and infinities
+fn finite(parameters: &[f64]) -> bool {
+    parameters.iter().all(|parameter| parameter.is_finite())
+}
+
+fn gamma_pdf(shape: f64, scale: f64, x: f64) -> f64 {
+    if x < 0.0 {
+        return 0.0;
+    }
+
+    ((shape - 1.0) * x.ln() - x / scale - special::ln_gamma(shape) - shape * scale.ln()).exp()
+}
+
+impl Distrib for Box<dyn Distrib> {
+    fn gen(&self) -> f64 {
+        self.as_ref().gen()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        self.as_ref().mean()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        self.as_ref().variance()
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        self.as_ref().cdf(x)
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        self.as_ref().pdf(x)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        self.as_ref().quantile(p)
+    }
+
+    fn sample_from_u(&self, u: f64) -> Option<f64> {
+        self.as_ref().sample_from_u(u)
+    }
+}
+
+/// Degenerate distribution, always generating the same value
+pub struct Constant(pub f64);
+
+impl Distrib for Constant {
+    fn gen(&self) -> f64 {
+        self.0
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.0)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(0.0)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(if x < self.0 { 0.0 } else { 1.0 })
+    }
+
+    fn quantile(&self, _p: f64) -> Option<f64> {
+        Some(self.0)
+    }
 }
 
 pub struct Uniform {
-    min: f32,
-    max: f32,
+    min: f64,
+    max: f64,
 }
 
 impl Distrib for Uniform {
-    fn gen(&self) -> f32 {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(self.min..self.max)
+    fn gen(&self) -> f64 {
+        with_rng(|rng| rng.gen_range(self.min..self.max))
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some((self.min + self.max) / 2.0)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some((self.max - self.min).powi(2) / 12.0)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(((x - self.min) / (self.max - self.min)).clamp(0.0, 1.0))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(if (self.min..=self.max).contains(&x) { 1.0 / (self.max - self.min) } else { 0.0 })
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(self.min + p * (self.max - self.min))
     }
 }
 
 impl Uniform {
-    pub fn new(min: f32, max: f32) -> Self {
+    pub fn new(min: f64, max: f64) -> Self {
         Self { min, max }
     }
 
-    pub fn gen_n<const N: usize>(min: f32, max: f32) -> [f32; N] {
+    pub fn gen_n<const N: usize>(min: f64, max: f64) -> [f64; N] {
         let u_between = rand::distributions::Uniform::from(min..max);
-        let mut rng = rand::thread_rng();
 
-        let mut samples: [f32; N] = [0.0; N];
-        for i in 0..N {
-            samples[i] = u_between.sample(&mut rng);
-        }
+        let mut samples: [f64; N] = [0.0; N];
+        with_rng(|rng| {
+            for i in 0..N {
+                samples[i] = u_between.sample(rng);
+            }
+        });
 
         samples
     }
 
-    pub fn gen(min: f32, max: f32) -> f32 {
+    pub fn gen(min: f64, max: f64) -> f64 {
         <Self as Distrib>::gen(&Self { min, max })
     }
 }
 
 pub struct Gaussian {
-    mean: f32,
-    std: f32,
+    mean: f64,
+    std: f64,
 }
 
 impl Distrib for Gaussian {
-    fn gen(&self) -> f32 {
+    fn gen(&self) -> f64 {
         self.mean + Self::marsaglia_polar_gen() * self.std
     }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.mean)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.std * self.std)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(special::normal_cdf((x - self.mean) / self.std))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        let z = (x - self.mean) / self.std;
+        Some((-z * z / 2.0).exp() / (self.std * (2.0 * std::f64::consts::PI).sqrt()))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(self.mean + self.std * special::normal_quantile(p))
+    }
 }
 
 impl Gaussian {
-    pub fn new(mean: f32, std: f32) -> Self {
+    pub fn new(mean: f64, std: f64) -> Self {
         Self { mean, std }
     }
 
-    fn marsaglia_polar_gen() -> f32 {
-        let (mut v1, mut v2, mut s): (f32, f32, f32);
+    pub(crate) fn marsaglia_polar_gen() -> f64 {
+        let (mut v1, mut v2, mut s): (f64, f64, f64);
 
         loop {
             v1 = 2.0 * Uniform::gen(0.0, 1.0) - 1.0;
@@ -75,18 +246,512 @@ impl Gaussian {
 }
 
 pub struct Exponential {
-    mean: f32,
+    mean: f64,
 }
 
 impl Distrib for Exponential {
-    fn gen(&self) -> f32 {
+    fn gen(&self) -> f64 {
         let lambda = 1.0 / self.mean;
         (1.0 - Uniform::gen(0.0, 1.0)).ln() / (-lambda)
     }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.mean)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.mean * self.mean)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(if x < 0.0 { 0.0 } else { 1.0 - (-x / self.mean).exp() })
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(if x < 0.0 { 0.0 } else { (-x / self.mean).exp() / self.mean })
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(-self.mean * (1.0 - p).ln())
+    }
 }
 
 impl Exponential {
-    pub fn new(mean: f32) -> Self {
+    pub fn new(mean: f64) -> Self {
         Self { mean }
     }
 }
+
+pub struct Triangular {
+    min: f64,
+    mode: f64,
+    max: f64,
+}
+
+impl Distrib for Triangular {
+    fn gen(&self) -> f64 {
+        self.quantile(Uniform::gen(0.0, 1.0)).unwrap()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some((self.min + self.mode + self.max) / 3.0)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let (a, b, c) = (self.min, self.max, self.mode);
+        Some((a * a + b * b + c * c - a * b - a * c - b * c) / 18.0)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let (a, b, c) = (self.min, self.max, self.mode);
+        Some(if x <= a {
+            0.0
+        } else if x <= c {
+            (x - a).powi(2) / ((b - a) * (c - a))
+        } else if x < b {
+            1.0 - (b - x).powi(2) / ((b - a) * (b - c))
+        } else {
+            1.0
+        })
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        let (a, b, c) = (self.min, self.max, self.mode);
+        Some(if x < a || x > b {
+            0.0
+        } else if x < c {
+            2.0 * (x - a) / ((b - a) * (c - a))
+        } else if x > c {
+            2.0 * (b - x) / ((b - a) * (b - c))
+        } else {
+            2.0 / (b - a)
+        })
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        let range = self.max - self.min;
+        let split = (self.mode - self.min) / range;
+
+        Some(if p < split {
+            self.min + (p * range * (self.mode - self.min)).sqrt()
+        } else {
+            self.max - ((1.0 - p) * range * (self.max - self.mode)).sqrt()
+        })
+    }
+}
+
+impl Triangular {
+    pub fn new(min: f64, mode: f64, max: f64) -> Result<Self> {
+        if !finite(&[min, mode, max]) || min > mode || mode > max || min >= max {
+            return Err(anyhow!("Triangular requires min <= mode <= max and min < max"));
+        }
+
+        Ok(Self { min, mode, max })
+    }
+}
+
+/// Distribution of `exp(X)`, with `X` normally distributed
+pub struct Lognormal {
+    mu: f64,
+    sigma: f64,
+}
+
+impl Distrib for Lognormal {
+    fn gen(&self) -> f64 {
+        (self.mu + self.sigma * Gaussian::marsaglia_polar_gen()).exp()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some((self.mu + self.sigma * self.sigma / 2.0).exp())
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let sigma2 = self.sigma * self.sigma;
+        Some((sigma2.exp() - 1.0) * (2.0 * self.mu + sigma2).exp())
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        if x <= 0.0 {
+            return Some(0.0);
+        }
+
+        Some(special::normal_cdf((x.ln() - self.mu) / self.sigma))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        if x <= 0.0 {
+            return Some(0.0);
+        }
+
+        let z = (x.ln() - self.mu) / self.sigma;
+        Some((-z * z / 2.0).exp() / (x * self.sigma * (2.0 * std::f64::consts::PI).sqrt()))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some((self.mu + self.sigma * special::normal_quantile(p)).exp())
+    }
+}
+
+impl Lognormal {
+    /// Parameters of the underlying normal distribution
+    pub fn new(mu: f64, sigma: f64) -> Result<Self> {
+        if !finite(&[mu, sigma]) || sigma <= 0.0 {
+            return Err(anyhow!("Lognormal requires finite mu and sigma > 0"));
+        }
+
+        Ok(Self { mu, sigma })
+    }
+
+    /// Mean and standard deviation of the lognormal itself
+    pub fn from_mean_std(mean: f64, std: f64) -> Result<Self> {
+        if !finite(&[mean, std]) || mean <= 0.0 || std <= 0.0 {
+            return Err(anyhow!("Lognormal requires finite mean > 0 and std > 0"));
+        }
+
+        let sigma2 = (1.0 + (std * std) / (mean * mean)).ln();
+        Self::new(mean.ln() - sigma2 / 2.0, sigma2.sqrt())
+    }
+}
+
+pub struct Weibull {
+    shape: f64,
+    scale: f64,
+}
+
+impl Distrib for Weibull {
+    fn gen(&self) -> f64 {
+        self.quantile(Uniform::gen(0.0, 1.0)).unwrap()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.scale * special::gamma(1.0 + 1.0 / self.shape))
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let g1 = special::gamma(1.0 + 1.0 / self.shape);
+        let g2 = special::gamma(1.0 + 2.0 / self.shape);
+        Some(self.scale * self.scale * (g2 - g1 * g1))
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(if x <= 0.0 { 0.0 } else { 1.0 - (-(x / self.scale).powf(self.shape)).exp() })
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        if x < 0.0 {
+            return Some(0.0);
+        }
+
+        let y = x / self.scale;
+        Some(self.shape / self.scale * y.powf(self.shape - 1.0) * (-y.powf(self.shape)).exp())
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(self.scale * (-(1.0 - p).ln()).powf(1.0 / self.shape))
+    }
+}
+
+impl Weibull {
+    pub fn new(shape: f64, scale: f64) -> Result<Self> {
+        if !finite(&[shape, scale]) || shape <= 0.0 || scale <= 0.0 {
+            return Err(anyhow!("Weibull requires finite shape > 0 and scale > 0"));
+        }
+
+        Ok(Self { shape, scale })
+    }
+}
+
+pub struct Gamma {
+    shape: f64,
+    scale: f64,
+}
+
+impl Distrib for Gamma {
+    fn gen(&self) -> f64 {
+        self.scale * Self::marsaglia_tsang_gen(self.shape)
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.shape * self.scale)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.shape * self.scale * self.scale)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(special::gamma_p(self.shape, x / self.scale))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(gamma_pdf(self.shape, self.scale, x))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(numeric_quantile(self, p)?.max(0.0))
+    }
+}
+
+impl Gamma {
+    pub fn new(shape: f64, scale: f64) -> Result<Self> {
+        if !finite(&[shape, scale]) || shape <= 0.0 || scale <= 0.0 {
+            return Err(anyhow!("Gamma requires finite shape > 0 and scale > 0"));
+        }
+
+        Ok(Self { shape, scale })
+    }
+
+    /// Standard gamma variate (unit scale)
+    pub(crate) fn marsaglia_tsang_gen(shape: f64) -> f64 {
+        if shape < 1.0 {
+            let u = 1.0 - Uniform::gen(0.0, 1.0);
+            return Self::marsaglia_tsang_gen(shape + 1.0) * u.powf(1.0 / shape);
+        }
+
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+
+        loop {
+            let x = Gaussian::marsaglia_polar_gen();
+            let v = (1.0 + c * x).powi(3);
+            if v <= 0.0 {
+                continue;
+            }
+
+            let u = 1.0 - Uniform::gen(0.0, 1.0);
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+}
+
+/// Sum of `phases` exponential phases, each with mean `phase_mean`
+pub struct Erlang {
+    phases: u32,
+    phase_mean: f64,
+}
+
+impl Distrib for Erlang {
+    fn gen(&self) -> f64 {
+        let mut product = 1f64;
+        for _ in 0..self.phases {
+            product *= 1.0 - Uniform::gen(0.0, 1.0);
+        }
+
+        -self.phase_mean * product.ln()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.phases as f64 * self.phase_mean)
+    }
+
+    fn variance(&self) -> Option<f64> {
+        Some(self.phases as f64 * self.phase_mean * self.phase_mean)
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(special::gamma_p(self.phases as f64, x / self.phase_mean))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        Some(gamma_pdf(self.phases as f64, self.phase_mean, x))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(numeric_quantile(self, p)?.max(0.0))
+    }
+}
+
+impl Erlang {
+    pub fn new(phases: u32, phase_mean: f64) -> Result<Self> {
+        if phases == 0 || !phase_mean.is_finite() || phase_mean <= 0.0 {
+            return Err(anyhow!("Erlang requires phases > 0 and finite phase_mean > 0"));
+        }
+
+        Ok(Self { phases, phase_mean })
+    }
+}
+
+/// Beta distribution scaled to `[min, max]`
+pub struct Beta {
+    alpha: f64,
+    beta: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Distrib for Beta {
+    fn gen(&self) -> f64 {
+        let x = Gamma::marsaglia_tsang_gen(self.alpha);
+        let y = Gamma::marsaglia_tsang_gen(self.beta);
+
+        self.min + (self.max - self.min) * x / (x + y)
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.min + (self.max - self.min) * self.alpha / (self.alpha + self.beta))
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let sum = self.alpha + self.beta;
+        let range = self.max - self.min;
+        Some(range * range * self.alpha * self.beta / (sum * sum * (sum + 1.0)))
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(special::beta_inc(self.alpha, self.beta, (x - self.min) / (self.max - self.min)))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        if x < self.min || x > self.max {
+            return Some(0.0);
+        }
+
+        let range = self.max - self.min;
+        let y = (x - self.min) / range;
+        let ln_beta = special::ln_gamma(self.alpha) + special::ln_gamma(self.beta)
+            - special::ln_gamma(self.alpha + self.beta);
+
+        Some(((self.alpha - 1.0) * y.ln() + (self.beta - 1.0) * (1.0 - y).ln() - ln_beta).exp() / range)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(numeric_quantile(self, p)?.clamp(self.min, self.max))
+    }
+}
+
+impl Beta {
+    pub fn new(alpha: f64, beta: f64) -> Result<Self> {
+        Self::new_scaled(alpha, beta, 0.0, 1.0)
+    }
+
+    pub fn new_scaled(alpha: f64, beta: f64, min: f64, max: f64) -> Result<Self> {
+        if !finite(&[alpha, beta]) || alpha <= 0.0 || beta <= 0.0 {
+            return Err(anyhow!("Beta requires finite alpha > 0 and beta > 0"));
+        }
+
+        if !finite(&[min, max]) || min >= max {
+            return Err(anyhow!("Beta requires finite min < max"));
+        }
+
+        Ok(Self {
+            alpha,
+            beta,
+            min,
+            max,
+        })
+    }
+}
+
+pub struct Pareto {
+    scale: f64,
+    shape: f64,
+}
+
+impl Distrib for Pareto {
+    fn gen(&self) -> f64 {
+        self.quantile(Uniform::gen(0.0, 1.0)).unwrap()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        if self.shape > 1.0 {
+            Some(self.shape * self.scale / (self.shape - 1.0))
+        } else {
+            None
+        }
+    }
+
+    fn variance(&self) -> Option<f64> {
+        if self.shape > 2.0 {
+            let a = self.shape;
+            Some(self.scale * self.scale * a / ((a - 1.0) * (a - 1.0) * (a - 2.0)))
+        } else {
+            None
+        }
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        Some(if x <= self.scale { 0.0 } else { 1.0 - (self.scale / x).powf(self.shape) })
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        if x < self.scale {
+            return Some(0.0);
+        }
+
+        Some(self.shape * self.scale.powf(self.shape) / x.powf(self.shape + 1.0))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(self.scale / (1.0 - p).powf(1.0 / self.shape))
+    }
+}
+
+impl Pareto {
+    pub fn new(scale: f64, shape: f64) -> Result<Self> {
+        if !finite(&[scale, shape]) || scale <= 0.0 || shape <= 0.0 {
+            return Err(anyhow!("Pareto requires finite scale > 0 and shape > 0"));
+        }
+
+        Ok(Self { scale, shape })
+    }
+}
+
+/// Johnson SU: `xi + lambda * sinh((Z - gamma) / delta)`, with `Z` standard normal
+pub struct JohnsonSU {
+    gamma: f64,
+    delta: f64,
+    xi: f64,
+    lambda: f64,
+}
+
+impl Distrib for JohnsonSU {
+    fn gen(&self) -> f64 {
+        let z = Gaussian::marsaglia_polar_gen();
+        self.xi + self.lambda * ((z - self.gamma) / self.delta).sinh()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        let w = (1.0 / (self.delta * self.delta)).exp();
+        Some(self.xi - self.lambda * w.sqrt() * (self.gamma / self.delta).sinh())
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let w = (1.0 / (self.delta * self.delta)).exp();
+        let cosh = (2.0 * self.gamma / self.delta).cosh();
+        Some(self.lambda * self.lambda / 2.0 * (w - 1.0) * (w * cosh + 1.0))
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let z = self.gamma + self.delta * ((x - self.xi) / self.lambda).asinh();
+        Some(special::normal_cdf(z))
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        let y = (x - self.xi) / self.lambda;
+        let z = self.gamma + self.delta * y.asinh();
+        let scale = self.lambda * (2.0 * std::f64::consts::PI).sqrt() * (1.0 + y * y).sqrt();
+
+        Some(self.delta / scale * (-z * z / 2.0).exp())
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        Some(self.xi + self.lambda * ((special::normal_quantile(p) - self.gamma) / self.delta).sinh())
+    }
+}
+
+impl JohnsonSU {
+    pub fn new(gamma: f64, delta: f64, xi: f64, lambda: f64) -> Result<Self> {
+        if !finite(&[gamma, delta, xi, lambda]) || delta <= 0.0 || lambda <= 0.0 {
+            return Err(anyhow!("Johnson SU requires finite parameters, delta > 0 and lambda > 0"));
+        }
+
+        Ok(Self {
+            gamma,
+            delta,
+            xi,
+            lambda,
+        })
+    }
+}
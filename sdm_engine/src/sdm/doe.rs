@@ -0,0 +1,357 @@
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use std::thread;
+
+use super::experiment::{Experiment, ExperimentResults};
+use super::qmc::latin_hypercube;
+use super::report::csv_field;
+use super::scenario::Parameters;
+use super::Scheduler;
+
+/// Model parameter varied by a design, with the levels it can take
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factor {
+    name: String,
+    levels: Vec<f64>,
+    continuous: bool,
+}
+
+impl Factor {
+    /// Discrete levels, e.g. table counts
+    pub fn new(name: &str, levels: &[f64]) -> Result<Self> {
+        if levels.is_empty() {
+            return Err(anyhow!("Factor \"{}\" requires at least one level", name));
+        }
+
+        if levels.iter().any(|level| !level.is_finite()) {
+            return Err(anyhow!("Factor \"{}\" levels must be finite", name));
+        }
+
+        for (i, level) in levels.iter().enumerate() {
+            if levels[..i].contains(level) {
+                return Err(anyhow!("Factor \"{}\" has level {} twice", name, level));
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            levels: levels.to_vec(),
+            continuous: false,
+        })
+    }
+
+    /// Every integer in `[low, high]`, e.g. staff counts
+    pub fn integer(name: &str, low: i64, high: i64) -> Result<Self> {
+        Self::new(name, &(low..=high).map(|level| level as f64).collect::<Vec<f64>>())
+    }
+
+    /// Any value in `[low, high]` for Latin hypercubes, `low` and `high` for factorials
+    pub fn continuous(name: &str, low: f64, high: f64) -> Result<Self> {
+        if !low.is_finite() || !high.is_finite() || low >= high {
+            return Err(anyhow!("Factor \"{}\" requires finite low < high", name));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            levels: vec![low, high],
+            continuous: true,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn levels(&self) -> &[f64] {
+        &self.levels
+    }
+
+    fn low(&self) -> f64 {
+        self.levels.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+
+    fn high(&self) -> f64 {
+        self.levels.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// One of the levels, or within the range for continuous factors
+    pub(crate) fn contains(&self, value: f64) -> bool {
+        if self.continuous {
+            self.low() <= value && value <= self.high()
+        } else {
+            self.levels.contains(&value)
+        }
+    }
+
+    /// First level, or the middle of the range for continuous factors
+    pub(crate) fn initial(&self) -> f64 {
+        if self.continuous {
+            (self.low() + self.high()) / 2.0
+        } else {
+            self.levels[0]
+        }
+    }
+
+    /// Adjacent level, or a step of up to a tenth of the range for continuous factors
+    pub(crate) fn neighbour(&self, value: f64, rng: &mut impl Rng) -> f64 {
+        if self.continuous {
+            let step = (rng.gen::<f64>() * 2.0 - 1.0) * (self.high() - self.low()) / 10.0;
+            return (value + step).clamp(self.low(), self.high());
+        }
+
+        let last = self.levels.len() - 1;
+        match self.levels.iter().position(|level| *level == value) {
+            Some(0) => self.levels[usize::min(1, last)],
+            Some(i) if i == last || rng.gen_bool(0.5) => self.levels[i - 1],
+            Some(i) => self.levels[i + 1],
+            None => self.levels[0],
+        }
+    }
+
+    /// Level of the stratum `u` falls in, or the point of the range for continuous factors
+    fn at(&self, u: f64) -> f64 {
+        if self.continuous {
+            self.low() + u * (self.high() - self.low())
+        } else {
+            self.levels[usize::min((u * self.levels.len() as f64) as usize, self.levels.len() - 1)]
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Design {
+    /// Every combination of levels
+    FullFactorial,
+    /// Two-level 2^(k-p) design at each factor's lowest and highest level. Each generator sets
+    /// a factor to the product of others, e.g. "D = ABC" with factors lettered in order.
+    FractionalFactorial(Vec<(usize, Vec<usize>)>),
+    LatinHypercube(usize),
+}
+
+impl Design {
+    /// Generators such as `["D = ABC", "E = BCD"]`
+    pub fn fractional_factorial(generators: &[&str]) -> Result<Self> {
+        let factor = |letter: char| {
+            if letter.is_ascii_uppercase() {
+                Ok(letter as usize - 'A' as usize)
+            } else {
+                Err(anyhow!("Factors are named by uppercase letters, found '{}'", letter))
+            }
+        };
+
+        let generators = generators
+            .iter()
+            .map(|generator| {
+                let (generated, product) = generator
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Generator \"{}\" must look like \"D = ABC\"", generator))?;
+
+                if product.trim().is_empty() {
+                    return Err(anyhow!("Generator \"{}\" must define a factor from at least one other", generator));
+                }
+
+                let mut generated = generated.trim().chars();
+                match (generated.next(), generated.next()) {
+                    (Some(letter), None) => Ok((
+                        factor(letter)?,
+                        product.trim().chars().map(factor).collect::<Result<Vec<usize>>>()?,
+                    )),
+                    _ => Err(anyhow!("Generator \"{}\" must define a single factor", generator)),
+                }
+            })
+            .collect::<Result<Vec<(usize, Vec<usize>)>>>()?;
+
+        for (i, (generated, _)) in generators.iter().enumerate() {
+            if generators[..i].iter().any(|(other, _)| other == generated) {
+                return Err(anyhow!(
+                    "Factor {} is defined by more than one generator",
+                    (b'A' + *generated as u8) as char
+                ));
+            }
+        }
+
+        Ok(Self::FractionalFactorial(generators))
+    }
+
+    /// Parameters of every design point
+    pub fn points(&self, factors: &[Factor]) -> Result<Vec<Parameters>> {
+        if factors.is_empty() {
+            return Err(anyhow!("A design requires at least one factor"));
+        }
+
+        let point = |values: Vec<f64>| {
+            let mut parameters = Parameters::new();
+            for (factor, value) in factors.iter().zip(values) {
+                parameters.set(factor.name(), value);
+            }
+            parameters
+        };
+
+        match self {
+            Self::FullFactorial => {
+                let mut points = vec![vec![]];
+                for factor in factors {
+                    points = points
+                        .into_iter()
+                        .flat_map(|point: Vec<f64>| {
+                            factor.levels.iter().map(move |level| [point.clone(), vec![*level]].concat())
+                        })
+                        .collect();
+                }
+
+                Ok(points.into_iter().map(point).collect())
+            }
+            Self::FractionalFactorial(generators) => {
+                let k = factors.len();
+                let generated: Vec<usize> = generators.iter().map(|(factor, _)| *factor).collect();
+                let base: Vec<usize> = (0..k).filter(|i| !generated.contains(i)).collect();
+
+                for (factor, product) in generators {
+                    if *factor >= k || product.is_empty() || product.iter().any(|i| *i >= k || generated.contains(i)) {
+                        return Err(anyhow!(
+                            "Generators must define one of the {} factors from factors not generated themselves",
+                            k
+                        ));
+                    }
+                }
+
+                let runs = 1usize << base.len();
+                Ok((0..runs)
+                    .map(|run| {
+                        // Standard order: the first base factor alternates fastest
+                        let mut signs = vec![1.0; k];
+                        for (bit, factor) in base.iter().enumerate() {
+                            signs[*factor] = if run >> bit & 1 == 1 { 1.0 } else { -1.0 };
+                        }
+                        for (factor, product) in generators {
+                            signs[*factor] = product.iter().map(|i| signs[*i]).product();
+                        }
+
+                        point(
+                            factors
+                                .iter()
+                                .zip(signs)
+                                .map(|(factor, sign)| if sign > 0.0 { factor.high() } else { factor.low() })
+                                .collect(),
+                        )
+                    })
+                    .collect())
+            }
+            Self::LatinHypercube(0) => Err(anyhow!("A Latin hypercube requires at least one point")),
+            Self::LatinHypercube(points) => Ok(latin_hypercube(*points, factors.len())
+                .into_iter()
+                .map(|u| point(factors.iter().zip(u).map(|(factor, u)| factor.at(u)).collect()))
+                .collect()),
+        }
+    }
+}
+
+/// Replications of each design point, the responses being every statistic of the model
+#[derive(Debug, Clone)]
+pub struct ResultsTable {
+    points: Vec<(Parameters, ExperimentResults)>,
+}
+
+impl ResultsTable {
+    pub fn points(&self) -> &[(Parameters, ExperimentResults)] {
+        &self.points
+    }
+
+    /// Mean of a response at each design point
+    pub fn means(&self, response: &str) -> Vec<(Parameters, f64)> {
+        self.points
+            .iter()
+            .filter_map(|(parameters, results)| Some((parameters.clone(), results.summary(response)?.mean)))
+            .collect()
+    }
+
+    /// One row per replication of each design point, with a column per factor and per response,
+    /// e.g. to fit a regression metamodel. Responses a replication did not report are left empty.
+    pub fn to_csv(&self) -> String {
+        let factors: Vec<&str> = match self.points.first() {
+            Some((parameters, _)) => parameters.iter().map(|(name, _)| name).collect(),
+            None => vec![],
+        };
+
+        let mut responses: Vec<&str> = vec![];
+        for (_, results) in self.points.iter() {
+            for name in results.names() {
+                if !responses.contains(&name) {
+                    responses.push(name);
+                }
+            }
+        }
+
+        let header = ["Point", "Replication"].iter().chain(factors.iter()).chain(responses.iter());
+        let mut csv = header.map(|name| csv_field(name)).collect::<Vec<String>>().join(",") + "\n";
+
+        for (point, (parameters, results)) in self.points.iter().enumerate() {
+            for replication in 0..results.replications() {
+                let mut row = vec![point.to_string(), replication.to_string()];
+                row.extend(parameters.iter().map(|(_, value)| value.to_string()));
+                row.extend(responses.iter().map(|response| match results.values(response) {
+                    Some(values) if !values[replication].is_nan() => values[replication].to_string(),
+                    _ => String::new(),
+                }));
+
+                csv += &(row.join(",") + "\n");
+            }
+        }
+
+        csv
+    }
+}
+
+/// Runs a parameterised model at every point of a design, with common random numbers
+pub struct Sweep<F> {
+    build: F,
+    run_length: f64,
+    seed: u64,
+    threads: usize,
+    factors: Vec<Factor>,
+}
+
+impl<F: Fn(&mut Scheduler, &Parameters) -> Result<()> + Sync> Sweep<F> {
+    /// `build` sets up the model for the given parameters on a fresh scheduler
+    pub fn new(run_length: f64, build: F) -> Self {
+        Self {
+            build,
+            run_length,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            factors: vec![],
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_factor(mut self, factor: Factor) -> Self {
+        self.factors.push(factor);
+        self
+    }
+
+    pub fn run(&self, design: &Design, replications: usize) -> Result<ResultsTable> {
+        let points = design
+            .points(&self.factors)?
+            .into_iter()
+            .map(|parameters| {
+                let results = Experiment::new(self.run_length, |scheduler| (self.build)(scheduler, &parameters))
+                    .with_seed(self.seed)
+                    .with_threads(self.threads)
+                    .run_parallel(replications)?;
+
+                Ok((parameters, results))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(ResultsTable { points })
+    }
+}
@@ -0,0 +1,293 @@
+use anyhow::{anyhow, Context, Result};
+use std::cell::RefCell;
+use std::path::Path;
+
+use super::distributions::{Distrib, Uniform};
+
+/// Splits a comma-separated line into fields. Fields may be quoted to hold commas, with
+/// doubled quotes standing for a quote, but may not span several lines.
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+/// Reads a numeric column, by header name, from a comma-separated file
+pub fn read_csv_column(path: impl AsRef<Path>, column: &str) -> Result<Vec<f64>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read \"{}\"", path.display()))?;
+
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or_else(|| anyhow!("\"{}\" is empty", path.display()))?;
+    let idx = split_csv_line(header)
+        .iter()
+        .position(|name| name.trim() == column)
+        .ok_or_else(|| anyhow!("No column \"{}\" in \"{}\"", column, path.display()))?;
+
+    let mut values = vec![];
+    for (line_number, line) in lines.enumerate() {
+        let field = split_csv_line(line)
+            .into_iter()
+            .nth(idx)
+            .ok_or_else(|| anyhow!("Line {} has no column \"{}\"", line_number + 2, column))?;
+
+        values.push(
+            field
+                .trim()
+                .parse()
+                .with_context(|| format!("Line {} is not a number: \"{}\"", line_number + 2, field))?,
+        );
+    }
+
+    Ok(values)
+}
+
+/// Continuous distribution with a piecewise-linear CDF through the sorted observations
+pub struct EmpiricalContinuous {
+    observations: Vec<f64>,
+}
+
+impl Distrib for EmpiricalContinuous {
+    fn gen(&self) -> f64 {
+        self.quantile(Uniform::gen(0.0, 1.0)).unwrap()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        let segments = (self.observations.len() - 1) as f64;
+        Some(
+            self.observations
+                .windows(2)
+                .map(|w| (w[0] + w[1]) / 2.0)
+                .sum::<f64>()
+                / segments,
+        )
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let segments = (self.observations.len() - 1) as f64;
+        let second_moment = self
+            .observations
+            .windows(2)
+            .map(|w| (w[0] * w[0] + w[0] * w[1] + w[1] * w[1]) / 3.0)
+            .sum::<f64>()
+            / segments;
+
+        Some(second_moment - self.mean().unwrap().powi(2))
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let (first, last) = (self.observations[0], *self.observations.last().unwrap());
+        if x <= first {
+            return Some(0.0);
+        } else if x >= last {
+            return Some(1.0);
+        }
+
+        let (i, low, high) = self.segment(x);
+        Some((i as f64 + (x - low) / (high - low)) / (self.observations.len() - 1) as f64)
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        if x < self.observations[0] || x >= *self.observations.last().unwrap() {
+            return Some(0.0);
+        }
+
+        let (_, low, high) = self.segment(x);
+        Some(1.0 / ((self.observations.len() - 1) as f64 * (high - low)))
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        let segments = self.observations.len() - 1;
+        let p = segments as f64 * p.clamp(0.0, 1.0);
+        let i = usize::min(p as usize, segments - 1);
+
+        let (low, high) = (self.observations[i], self.observations[i + 1]);
+        Some(low + (p - i as f64) * (high - low))
+    }
+}
+
+impl EmpiricalContinuous {
+    pub fn new(observations: &[f64]) -> Result<Self> {
+        if observations.len() < 2 {
+            return Err(anyhow!("EmpiricalContinuous requires at least two observations"));
+        }
+
+        if observations.iter().any(|x| !x.is_finite()) {
+            return Err(anyhow!("EmpiricalContinuous observations must be finite"));
+        }
+
+        let mut observations = observations.to_vec();
+        observations.sort_by(|a, b| a.total_cmp(b));
+
+        Ok(Self { observations })
+    }
+
+    pub fn from_csv(path: impl AsRef<Path>, column: &str) -> Result<Self> {
+        Self::new(&read_csv_column(path, column)?)
+    }
+
+    /// Index and bounds of the non-empty segment containing `x`, within the observed range
+    fn segment(&self, x: f64) -> (usize, f64, f64) {
+        let i = self.observations.partition_point(|observation| *observation <= x) - 1;
+        (i, self.observations[i], self.observations[i + 1])
+    }
+}
+
+/// Samples the observed values with their observed frequencies
+pub struct EmpiricalDiscrete {
+    values: Vec<f64>,
+    cumulative: Vec<f64>,
+}
+
+impl Distrib for EmpiricalDiscrete {
+    fn gen(&self) -> f64 {
+        self.quantile(Uniform::gen(0.0, 1.0)).unwrap()
+    }
+
+    fn mean(&self) -> Option<f64> {
+        Some(self.probabilities().map(|(value, p)| value * p).sum())
+    }
+
+    fn variance(&self) -> Option<f64> {
+        let mean = self.mean().unwrap();
+        Some(self.probabilities().map(|(value, p)| (value - mean).powi(2) * p).sum())
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        let count = self.values.partition_point(|value| *value <= x);
+        Some(if count == 0 { 0.0 } else { self.cumulative[count - 1] })
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        let idx = self.cumulative.iter().position(|c| p < *c).unwrap_or(self.values.len() - 1);
+        Some(self.values[idx])
+    }
+}
+
+impl EmpiricalDiscrete {
+    pub fn new(observations: &[f64]) -> Result<Self> {
+        if observations.is_empty() {
+            return Err(anyhow!("EmpiricalDiscrete requires at least one observation"));
+        }
+
+        if observations.iter().any(|x| !x.is_finite()) {
+            return Err(anyhow!("EmpiricalDiscrete observations must be finite"));
+        }
+
+        let mut sorted = observations.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let mut values: Vec<f64> = vec![];
+        let mut cumulative = vec![];
+        for (i, value) in sorted.iter().enumerate() {
+            if values.last() != Some(value) {
+                values.push(*value);
+                cumulative.push(0.0);
+            }
+
+            *cumulative.last_mut().unwrap() = (i + 1) as f64 / sorted.len() as f64;
+        }
+
+        Ok(Self { values, cumulative })
+    }
+
+    pub fn from_csv(path: impl AsRef<Path>, column: &str) -> Result<Self> {
+        Self::new(&read_csv_column(path, column)?)
+    }
+
+    fn probabilities(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.values.iter().enumerate().map(|(i, value)| {
+            let previous = if i == 0 { 0.0 } else { self.cumulative[i - 1] };
+            (*value, self.cumulative[i] - previous)
+        })
+    }
+}
+
+/// Replays recorded values in order. Running out of values is an error, unless the trace is
+/// built `with_wrap_around` to start over.
+pub struct TraceReplay {
+    values: Vec<f64>,
+    position: RefCell<usize>,
+    wrap_around: bool,
+}
+
+impl Distrib for TraceReplay {
+    /// Panics once the trace is exhausted, see `next_value` to handle it
+    fn gen(&self) -> f64 {
+        self.next_value()
+            .unwrap_or_else(|| panic!("Trace exhausted after {} values", self.values.len()))
+    }
+}
+
+impl TraceReplay {
+    pub fn new(values: Vec<f64>) -> Result<Self> {
+        if values.is_empty() {
+            return Err(anyhow!("TraceReplay requires at least one value"));
+        }
+
+        if values.iter().any(|x| !x.is_finite()) {
+            return Err(anyhow!("TraceReplay values must be finite"));
+        }
+
+        Ok(Self {
+            values,
+            position: RefCell::new(0),
+            wrap_around: false,
+        })
+    }
+
+    pub fn from_csv(path: impl AsRef<Path>, column: &str) -> Result<Self> {
+        Self::new(read_csv_column(path, column)?)
+    }
+
+    /// Start over from the first value once all have been used
+    pub fn with_wrap_around(mut self) -> Self {
+        self.wrap_around = true;
+        self
+    }
+
+    /// Next recorded value, or `None` once the trace is exhausted
+    pub fn next_value(&self) -> Option<f64> {
+        let mut position = self.position.borrow_mut();
+        if *position >= self.values.len() && !self.wrap_around {
+            return None;
+        }
+
+        let value = self.values[*position % self.values.len()];
+        *position += 1;
+
+        Some(value)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        !self.wrap_around && self.position() >= self.values.len()
+    }
+
+    /// Number of values generated so far
+    pub fn position(&self) -> usize {
+        *self.position.borrow()
+    }
+
+    /// Never 0, as empty traces are rejected
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+}
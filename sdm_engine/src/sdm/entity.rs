@@ -2,12 +2,22 @@ use downcast_rs::{Downcast, impl_downcast};
 use uuid::Uuid;
 use petri_engine::net::PetriNet;
 
+use super::history::{EntityHistory, HistoryKind};
+use super::{AttributeValue, Attributes, Scheduler};
+
 pub trait Entity: Downcast + std::fmt::Debug {
+    fn name(&self) -> &str;
+
+    /// Name of the entity's type, shared by every instance whatever its name
+    fn entity_type(&self) -> &'static str;
+
     fn id(&self) -> &Uuid;
 
     fn priority(&self) -> &Option<i32>;
 
-    fn time_since_creation(&self) -> f32;
+    fn creation_time(&self) -> f64;
+
+    fn time_since_creation(&self) -> f64;
 
     fn set_priority(&mut self, priority: i32);
 
@@ -19,6 +29,37 @@ pub trait Entity: Downcast + std::fmt::Debug {
     ) -> Option<PetriNet>;
 
     fn petri_net(&self) -> &Option<PetriNet>;
+
+    fn attributes(&self) -> &Attributes;
+
+    fn attributes_mut(&mut self) -> &mut Attributes;
+
+    fn attribute(&self, name: &str) -> Option<&AttributeValue> {
+        self.attributes().get(name)
+    }
+
+    fn set_attribute(&mut self, name: &str, value: AttributeValue) -> Option<AttributeValue> {
+        self.attributes_mut().set(name, value)
+    }
+
+    /// Start recording the entity's journey through the model
+    fn enable_history(&mut self);
+
+    fn history(&self) -> Option<&EntityHistory>;
+
+    fn history_mut(&mut self) -> Option<&mut EntityHistory>;
+
+    fn record(&mut self, kind: HistoryKind) {
+        if let Some(history) = self.history_mut() {
+            history.record(Scheduler::time(), kind);
+        }
+    }
+
+    /// Record an event involving the entity. Events do not know their entities, so the
+    /// scheduler cannot record them on its own.
+    fn touch_event(&mut self, event_name: &str) {
+        self.record(HistoryKind::Event(event_name.to_string()));
+    }
 }
 
 impl_downcast!(Entity);
@@ -32,13 +73,23 @@ macro_rules! EntityWrapper {
             id: uuid::Uuid,
             priority: Option<i32>,
             petri_net: Option<petri_engine::net::PetriNet>,
-            creation_time: f32,
+            creation_time: f64,
+            attributes: sdm_engine::sdm::Attributes,
+            history: Option<sdm_engine::sdm::history::EntityHistory>,
             $($(
                 $varvis $varname: $type,
             )*)?
         }
 
         impl sdm_engine::sdm::Entity for $name {
+            fn name(&self) -> &str {
+                &self.name
+            }
+
+            fn entity_type(&self) -> &'static str {
+                stringify!($name)
+            }
+
             fn id(&self) -> &uuid::Uuid {
                 &self.id
             }
@@ -47,7 +98,11 @@ macro_rules! EntityWrapper {
                 &self.priority
             }
 
-            fn time_since_creation(&self) -> f32 {
+            fn creation_time(&self) -> f64 {
+                self.creation_time
+            }
+
+            fn time_since_creation(&self) -> f64 {
                 sdm_engine::sdm::Scheduler::time() - self.creation_time
             }
 
@@ -66,27 +121,57 @@ macro_rules! EntityWrapper {
             fn petri_net(&self) -> &Option<petri_engine::net::PetriNet> {
                 &self.petri_net
             }
+
+            fn attributes(&self) -> &sdm_engine::sdm::Attributes {
+                &self.attributes
+            }
+
+            fn attributes_mut(&mut self) -> &mut sdm_engine::sdm::Attributes {
+                &mut self.attributes
+            }
+
+            fn enable_history(&mut self) {
+                if self.history.is_none() {
+                    self.history = Some(sdm_engine::sdm::history::EntityHistory::new(self.creation_time));
+                }
+            }
+
+            fn history(&self) -> Option<&sdm_engine::sdm::history::EntityHistory> {
+                self.history.as_ref()
+            }
+
+            fn history_mut(&mut self) -> Option<&mut sdm_engine::sdm::history::EntityHistory> {
+                self.history.as_mut()
+            }
         }
 
         impl $name {
-            pub fn new(name: &str, creation_time: f32 $(,$($varname: $type),*)?) -> Self {
+            pub fn new(name: &str, creation_time: f64 $(,$($varname: $type),*)?) -> Self {
+                sdm_engine::sdm::Scheduler::register_creation(stringify!($name));
+
                 Self {
                     name: name.to_string(),
                     id: uuid::Uuid::new_v4(),
                     priority: None,
                     petri_net: None,
                     creation_time,
+                    attributes: sdm_engine::sdm::Attributes::new(),
+                    history: None,
                     $($($varname,)*)?
                 }
             }
 
-            pub fn new_with_priority(name: &str, priority: i32, creation_time: f32 $(,$($varname: $type),*)?) -> Self {
+            pub fn new_with_priority(name: &str, priority: i32, creation_time: f64 $(,$($varname: $type),*)?) -> Self {
+                sdm_engine::sdm::Scheduler::register_creation(stringify!($name));
+
                 Self {
                     name: name.to_string(),
                     id: uuid::Uuid::new_v4(),
                     priority: Some(priority),
                     petri_net: None,
                     creation_time,
+                    attributes: sdm_engine::sdm::Attributes::new(),
+                    history: None,
                     $($($varname,)*)?
                 }
             }
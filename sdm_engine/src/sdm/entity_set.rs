@@ -1,4 +1,5 @@
 use anyhow::Result;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use super::Entity;
@@ -8,6 +9,7 @@ pub enum EntitySetMode {
     FIFO,
     LIFO,
     PRIORITY,
+    ATTRIBUTE(&'static str), // Same ordering as PRIORITY, using an attribute's value
 }
 
 impl Default for EntitySetMode {
@@ -37,17 +39,81 @@ pub trait EntitySet {
     where
         Self: Sized;
 
+    fn find(&self, predicate: &dyn Fn(&dyn Entity) -> bool) -> Option<Uuid>;
+
+    fn for_each(&self, func: &mut dyn FnMut(&dyn Entity));
+
+    fn time_in_set(&self, id: Uuid) -> Option<f64>;
+
     fn size(&self) -> usize;
 
-    fn average_size(&self) -> f32;
+    fn average_size(&self) -> f64;
 
     fn max_size(&self) -> Option<usize>;
 
     fn update_analytics(&self);
 
-    fn average_time_in_set(&self) -> f32;
+    fn average_time_in_set(&self) -> f64;
+
+    fn max_time_in_set(&self) -> f64;
+
+    /// Keep the time in set per value of `attribute`, for `average_time_in_set_by`
+    fn group_time_by(&self, attribute: &str);
 
-    fn max_time_in_set(&self) -> f32;
+    /// Average time in set grouped by the value of an attribute passed to `group_time_by`,
+    /// counting the entities that left the set since then
+    fn average_time_in_set_by(&self, attribute: &str) -> HashMap<String, f64>;
+
+    /// Forget collected statistics, keeping the entities in the set
+    fn reset_statistics(&self);
+}
+/// Running sums of the time spent in a set by the entities that left it
+#[derive(Debug, Default)]
+pub struct TimeInSet {
+    sum: f64,
+    count: u32,
+    groups: HashMap<String, HashMap<String, (f64, u32)>>,
+}
+
+impl TimeInSet {
+    pub fn record(&mut self, time: f64, entity: &dyn Entity) {
+        self.sum += time;
+        self.count += 1;
+
+        for (attribute, groups) in self.groups.iter_mut() {
+            if let Some(value) = entity.attribute(attribute) {
+                let group = groups.entry(value.to_string()).or_insert((0.0, 0));
+                group.0 += time;
+                group.1 += 1;
+            }
+        }
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn group_by(&mut self, attribute: &str) {
+        self.groups.entry(attribute.to_string()).or_default();
+    }
+
+    /// Sum and count per value of `attribute`, if the times are grouped by it
+    pub fn groups(&self, attribute: &str) -> Option<&HashMap<String, (f64, u32)>> {
+        self.groups.get(attribute)
+    }
+
+    /// Forget the recorded times, keeping the grouping attributes
+    pub fn reset(&mut self) {
+        self.sum = 0.0;
+        self.count = 0;
+        for groups in self.groups.values_mut() {
+            groups.clear();
+        }
+    }
 }
 
 #[macro_export]
@@ -59,11 +125,12 @@ macro_rules! EntitySetWrapper {
             id: uuid::Uuid,
             mode: sdm_engine::sdm::EntitySetMode,
             max_size: Option<usize>,
+            activity: bool,
             average_size_sum: std::cell::RefCell<u32>,
             average_size_sum_count: std::cell::RefCell<u32>,
-            removed_time_in_set: std::cell::RefCell<Vec<f32>>,
-            max_time_in_set: std::cell::RefCell<f32>,
-            container: std::cell::RefCell<Vec<(f32, Box<dyn Entity>)>>,
+            removed_time_in_set: std::cell::RefCell<sdm_engine::sdm::entity_set::TimeInSet>,
+            max_time_in_set: std::cell::RefCell<f64>,
+            container: std::cell::RefCell<Vec<(f64, Box<dyn Entity>)>>,
             $($(
                 $varname: $type,
             )*)?
@@ -75,27 +142,50 @@ macro_rules! EntitySetWrapper {
             }
 
             fn sort_container(&self) {
-                self.container
-                .borrow_mut()
-                .sort_by(|a, b| a.1.priority().cmp(&b.1.priority()).reverse())
+                match self.mode {
+                    EntitySetMode::ATTRIBUTE(attribute) => self.container
+                        .borrow_mut()
+                        .sort_by(|a, b| {
+                            // Entities without the attribute rank below any value
+                            match (a.1.attribute(attribute), b.1.attribute(attribute)) {
+                                (Some(a), Some(b)) => a.total_cmp(b),
+                                (a, b) => a.is_some().cmp(&b.is_some()),
+                            }
+                            .reverse()
+                        }),
+                    _ => self.container
+                        .borrow_mut()
+                        .sort_by(|a, b| a.1.priority().cmp(&b.1.priority()).reverse()),
+                }
             }
 
-            fn push(&self, entity: Box<dyn sdm_engine::sdm::Entity>) {
+            fn push(&self, mut entity: Box<dyn sdm_engine::sdm::Entity>) {
                 let time = sdm_engine::sdm::Scheduler::time();
+                entity.record(self.entered_kind());
+                let id = *entity.id();
                 match self.mode {
                     EntitySetMode::FIFO => self.container.borrow_mut().push((time, entity)),
                     EntitySetMode::LIFO => self.container.borrow_mut().insert(0, (time, entity)),
-                    EntitySetMode::PRIORITY => {
+                    EntitySetMode::PRIORITY | EntitySetMode::ATTRIBUTE(_) => {
                         self.container.borrow_mut().push((time, entity));
                         self.sort_container();
                     },
                 }
+
+                // Notified once in the set, so observers see it there
+                let container = self.container.borrow();
+                if let Some((_, entity)) = container.iter().find(|(_, entity)| entity.id() == &id) {
+                    sdm_engine::sdm::Scheduler::notify(|observer| observer.on_entity_pushed(&self.name, entity.as_ref()));
+                }
             }
 
             fn pop(&self) -> Option<Box<dyn sdm_engine::sdm::Entity>> {
-                if let Some((time, value)) = self.container.borrow_mut().pop() {
+                let popped = self.container.borrow_mut().pop();
+                if let Some((time, mut value)) = popped {
                     let s_time = sdm_engine::sdm::Scheduler::time();
-                    self.removed_time_in_set.borrow_mut().push(s_time - time);
+                    self.removed_time_in_set.borrow_mut().record(s_time - time, value.as_ref());
+                    value.record(self.left_kind());
+                    sdm_engine::sdm::Scheduler::notify(|observer| observer.on_entity_popped(&self.name, value.as_ref()));
                     Some(value)
                 } else {
                     None
@@ -116,10 +206,12 @@ macro_rules! EntitySetWrapper {
                 }
 
                 if let Some(i) = idx {
-                    let (time, removed) = self.container.borrow_mut().remove(i);
+                    let (time, mut removed) = self.container.borrow_mut().remove(i);
+                    removed.record(self.left_kind());
+                    sdm_engine::sdm::Scheduler::notify(|observer| observer.on_entity_popped(&self.name, removed.as_ref()));
 
                     let s_time = sdm_engine::sdm::Scheduler::time();
-                    self.removed_time_in_set.borrow_mut().push(s_time - time);
+                    self.removed_time_in_set.borrow_mut().record(s_time - time, removed.as_ref());
 
                     return Some(removed);
                 }
@@ -156,12 +248,40 @@ macro_rules! EntitySetWrapper {
                 }
             }
 
+            fn find(&self, predicate: &dyn Fn(&dyn sdm_engine::sdm::Entity) -> bool) -> Option<uuid::Uuid> {
+                self.container
+                    .borrow()
+                    .iter()
+                    .find(|elem| predicate(elem.1.as_ref()))
+                    .map(|elem| *elem.1.id())
+            }
+
+            fn for_each(&self, func: &mut dyn FnMut(&dyn sdm_engine::sdm::Entity)) {
+                for elem in self.container.borrow().iter() {
+                    func(elem.1.as_ref());
+                }
+            }
+
+            fn time_in_set(&self, id: uuid::Uuid) -> Option<f64> {
+                self.container
+                    .borrow()
+                    .iter()
+                    .find(|elem| elem.1.id() == &id)
+                    .map(|elem| sdm_engine::sdm::Scheduler::time() - elem.0)
+            }
+
             fn size(&self) -> usize {
                 self.container.borrow().len()
             }
 
-            fn average_size(&self) -> f32 {
-                *self.average_size_sum.borrow() as f32 / *self.average_size_sum_count.borrow() as f32
+            /// 0 until analytics are updated, e.g. right after a reset
+            fn average_size(&self) -> f64 {
+                let count = *self.average_size_sum_count.borrow();
+                if count == 0 {
+                    return 0.0;
+                }
+
+                *self.average_size_sum.borrow() as f64 / count as f64
             }
 
             fn max_size(&self) -> Option<usize> {
@@ -181,25 +301,59 @@ macro_rules! EntitySetWrapper {
                 }
             }
 
-            fn average_time_in_set(&self) -> f32 {
-                let mut sum = 0f32;
-
-                // Items previously removed
-                for time in self.removed_time_in_set.borrow().iter() {
-                    sum += time;
-                }
+            fn average_time_in_set(&self) -> f64 {
+                let removed = self.removed_time_in_set.borrow();
+                let mut sum = removed.sum();
 
                 // Items present
                 for (time_added, _) in self.container.borrow().iter() {
                     sum += (sdm_engine::sdm::Scheduler::time() - time_added);
                 }
 
-                sum / (self.removed_time_in_set.borrow().len() + self.container.borrow().len()) as f32
+                let count = removed.count() as usize + self.container.borrow().len();
+                if count == 0 {
+                    return 0.0;
+                }
+
+                sum / count as f64
             }
 
-            fn max_time_in_set(&self) -> f32 {
+            fn max_time_in_set(&self) -> f64 {
                 self.max_time_in_set.borrow().clone()
             }
+
+            fn group_time_by(&self, attribute: &str) {
+                self.removed_time_in_set.borrow_mut().group_by(attribute);
+            }
+
+            fn average_time_in_set_by(&self, attribute: &str) -> std::collections::HashMap<String, f64> {
+                let mut groups: std::collections::HashMap<String, (f64, u32)> = self.removed_time_in_set
+                    .borrow()
+                    .groups(attribute)
+                    .cloned()
+                    .unwrap_or_default();
+
+                // Items present
+                for (time_added, entity) in self.container.borrow().iter() {
+                    if let Some(value) = entity.attribute(attribute) {
+                        let group = groups.entry(value.to_string()).or_insert((0.0, 0));
+                        group.0 += sdm_engine::sdm::Scheduler::time() - time_added;
+                        group.1 += 1;
+                    }
+                }
+
+                groups
+                    .into_iter()
+                    .map(|(value, (sum, count))| (value, sum / count as f64))
+                    .collect()
+            }
+
+            fn reset_statistics(&self) {
+                *self.average_size_sum.borrow_mut() = 0;
+                *self.average_size_sum_count.borrow_mut() = 0;
+                self.removed_time_in_set.borrow_mut().reset();
+                *self.max_time_in_set.borrow_mut() = 0.0;
+            }
         }
 
         impl $name {
@@ -209,10 +363,11 @@ macro_rules! EntitySetWrapper {
                     id: uuid::Uuid::new_v4(),
                     mode,
                     max_size: None,
+                    activity: false,
                     average_size_sum: std::cell::RefCell::new(0u32),
                     average_size_sum_count: std::cell::RefCell::new(0u32),
-                    removed_time_in_set: std::cell::RefCell::new(vec![]),
-                    max_time_in_set: std::cell::RefCell::new(0f32),
+                    removed_time_in_set: std::cell::RefCell::new(Default::default()),
+                    max_time_in_set: std::cell::RefCell::new(0f64),
                     container: std::cell::RefCell::new(vec![]),
                     $($($varname,)*)?
                 }
@@ -224,14 +379,38 @@ macro_rules! EntitySetWrapper {
                     id: uuid::Uuid::new_v4(),
                     mode,
                     max_size: Some(max_size),
+                    activity: false,
                     average_size_sum: std::cell::RefCell::new(0u32),
                     average_size_sum_count: std::cell::RefCell::new(0u32),
-                    removed_time_in_set: std::cell::RefCell::new(vec![]),
-                    max_time_in_set: std::cell::RefCell::new(0f32),
+                    removed_time_in_set: std::cell::RefCell::new(Default::default()),
+                    max_time_in_set: std::cell::RefCell::new(0f64),
                     container: std::cell::RefCell::new(vec![]),
                     $($($varname,)*)?
                 }
             }
+
+            /// Entities in the set are being worked on rather than waiting, so their time in
+            /// the set counts as value-added while they hold a resource
+            pub fn with_value_added(mut self) -> Self {
+                self.activity = true;
+                self
+            }
+
+            fn entered_kind(&self) -> sdm_engine::sdm::history::HistoryKind {
+                if self.activity {
+                    sdm_engine::sdm::history::HistoryKind::EnteredActivity(self.name.clone())
+                } else {
+                    sdm_engine::sdm::history::HistoryKind::EnteredSet(self.name.clone())
+                }
+            }
+
+            fn left_kind(&self) -> sdm_engine::sdm::history::HistoryKind {
+                if self.activity {
+                    sdm_engine::sdm::history::HistoryKind::LeftActivity(self.name.clone())
+                } else {
+                    sdm_engine::sdm::history::HistoryKind::LeftSet(self.name.clone())
+                }
+            }
         }
     };
 }
@@ -0,0 +1,317 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use super::distributions::set_seed;
+use super::special;
+use super::Scheduler;
+
+/// Mean and t-based confidence interval of one statistic across replications
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub name: String,
+    pub replications: usize, // Replications that reported the statistic
+    pub mean: f64,
+    pub std_dev: f64,
+    pub half_width: f64,
+    pub confidence: f64,
+}
+
+impl Summary {
+    /// NaN values, i.e. replications that did not report the statistic, are left out
+    pub fn new(name: &str, values: &[f64], confidence: f64) -> Self {
+        let values: Vec<f64> = values.iter().copied().filter(|value| !value.is_nan()).collect();
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std_dev = (values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+        let t = special::student_t_quantile(1.0 - (1.0 - confidence) / 2.0, n - 1.0);
+
+        Self {
+            name: name.to_string(),
+            replications: values.len(),
+            mean,
+            std_dev,
+            half_width: t * std_dev / n.sqrt(),
+            confidence,
+        }
+    }
+
+    pub fn low(&self) -> f64 {
+        self.mean - self.half_width
+    }
+
+    pub fn high(&self) -> f64 {
+        self.mean + self.half_width
+    }
+
+    /// Half-width relative to the magnitude of the mean, infinite for a mean of 0 unless
+    /// every value is 0
+    pub fn relative_precision(&self) -> f64 {
+        if self.half_width == 0.0 {
+            return 0.0;
+        }
+
+        self.half_width / self.mean.abs()
+    }
+
+    /// Half-width within `precision` of the mean, or within `precision` itself for a mean of 0
+    pub fn is_precise(&self, precision: f64) -> bool {
+        if self.mean == 0.0 {
+            self.half_width <= precision
+        } else {
+            self.relative_precision() <= precision
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.4} ± {:.4} ({}% CI, std dev {:.4}, {} replications)",
+            self.name,
+            self.mean,
+            self.half_width,
+            (self.confidence * 1000.0).round() / 10.0,
+            self.std_dev,
+            self.replications
+        )
+    }
+}
+
+/// Value of every statistic in each replication
+#[derive(Debug, Clone)]
+pub struct ExperimentResults {
+    statistics: Vec<(String, Vec<f64>)>,
+    replications: usize,
+    confidence: f64,
+}
+
+impl ExperimentResults {
+    fn new(confidence: f64) -> Self {
+        Self {
+            statistics: vec![],
+            replications: 0,
+            confidence,
+        }
+    }
+
+    /// Statistics missing from a replication are recorded as NaN, so that the values of every
+    /// statistic stay aligned by replication
+    fn add_replication(&mut self, values: Vec<(String, f64)>) {
+        for (name, value) in values {
+            match self.statistics.iter_mut().find(|(known, _)| *known == name) {
+                Some((_, known_values)) => known_values.push(value),
+                None => {
+                    let mut known_values = vec![f64::NAN; self.replications];
+                    known_values.push(value);
+                    self.statistics.push((name, known_values));
+                }
+            }
+        }
+
+        self.replications += 1;
+        for (_, known_values) in self.statistics.iter_mut() {
+            known_values.resize(self.replications, f64::NAN);
+        }
+    }
+
+    pub fn replications(&self) -> usize {
+        self.replications
+    }
+
+    /// Names of every statistic, in the order they were first reported
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.statistics.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Values of a statistic in each replication, NaN where it was not reported
+    pub fn values(&self, name: &str) -> Option<&[f64]> {
+        self.statistics
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    pub fn summary(&self, name: &str) -> Option<Summary> {
+        Some(Summary::new(name, self.values(name)?, self.confidence))
+    }
+
+    pub fn summaries(&self) -> Vec<Summary> {
+        self.statistics
+            .iter()
+            .map(|(name, values)| Summary::new(name, values, self.confidence))
+            .collect()
+    }
+
+    pub fn print(&self) {
+        println!("+++++++++++++++ {} replications +++++++++++++++", self.replications);
+        for summary in self.summaries() {
+            println!("- {}", summary);
+        }
+        println!("+++++++++++++++++++++++++++++++++++++++++++");
+    }
+}
+
+/// Independent replications of a model, each on a fresh `Scheduler` with its own seed
+pub struct Experiment<F> {
+    build: F,
+    run_length: f64,
+    seed: u64,
+    confidence: f64,
+    threads: usize,
+}
+
+impl<F: Fn(&mut Scheduler) -> Result<()>> Experiment<F> {
+    /// `build` sets up the model on a fresh scheduler, which is then simulated for `run_length`
+    pub fn new(run_length: f64, build: F) -> Self {
+        Self {
+            build,
+            run_length,
+            seed: 0,
+            confidence: 0.95,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+
+    /// Replication `i` is seeded with `seed + i`
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    /// Threads used by `run_parallel`, all available cores by default
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Statistics at the end of one replication
+    pub fn run_replication(&self, replication: u64) -> Result<Vec<(String, f64)>> {
+        set_seed(self.seed.wrapping_add(replication));
+
+        let scheduler = Scheduler::new()?;
+        scheduler.set_quiet(true);
+
+        let result = (self.build)(scheduler).map(|_| {
+            scheduler.simulate_until(self.run_length);
+            scheduler.statistics_summary()
+        });
+
+        Scheduler::destroy();
+        result
+    }
+
+    pub fn run(&self, replications: usize) -> Result<ExperimentResults> {
+        self.check(replications)?;
+
+        let mut results = ExperimentResults::new(self.confidence);
+        for replication in 0..replications {
+            results.add_replication(self.run_replication(replication as u64)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Adds replications after the first `initial` ones until the confidence interval of every
+    /// statistic in `statistics` is within `relative_precision` of its mean, or until
+    /// `max_replications`. Check `is_precise` on the results for the latter case. Statistics
+    /// with a mean of 0 are held to `relative_precision` as an absolute half-width instead.
+    pub fn run_until_precision(
+        &self,
+        statistics: &[&str],
+        relative_precision: f64,
+        initial: usize,
+        max_replications: usize,
+    ) -> Result<ExperimentResults> {
+        self.check(initial)?;
+        if relative_precision <= 0.0 {
+            return Err(anyhow!("Relative precision must be positive"));
+        }
+
+        let mut results = self.run(initial)?;
+        for name in statistics {
+            if results.values(name).is_none() {
+                return Err(anyhow!("The model has no statistic named \"{}\"", name));
+            }
+        }
+
+        while results.replications() < max_replications {
+            let precise = statistics
+                .iter()
+                .all(|name| results.summary(name).unwrap().is_precise(relative_precision));
+            if precise {
+                break;
+            }
+
+            results.add_replication(self.run_replication(results.replications() as u64)?);
+        }
+
+        Ok(results)
+    }
+
+    fn check(&self, replications: usize) -> Result<()> {
+        if replications < 2 {
+            return Err(anyhow!("Confidence intervals require at least 2 replications"));
+        }
+
+        if !(0.0 < self.confidence && self.confidence < 1.0) {
+            return Err(anyhow!("Confidence level must be within (0, 1)"));
+        }
+
+        if self.run_length <= 0.0 {
+            return Err(anyhow!("Run length must be positive"));
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: Fn(&mut Scheduler) -> Result<()> + Sync> Experiment<F> {
+    /// Same results as `run`, with replications spread over a pool of threads that each build
+    /// their own model on their own scheduler. Results are aggregated in replication order,
+    /// whatever order the replications finish in.
+    pub fn run_parallel(&self, replications: usize) -> Result<ExperimentResults> {
+        self.check(replications)?;
+
+        let mut results = ExperimentResults::new(self.confidence);
+        self.run_more(&mut results, replications)?;
+
+        Ok(results)
+    }
+
+    /// Adds `replications` more replications to `results`, in parallel, numbered after the
+    /// ones already there
+    pub fn run_more(&self, results: &mut ExperimentResults, replications: usize) -> Result<()> {
+        let first = results.replications();
+        let next_replication = AtomicUsize::new(0);
+        let outputs = Mutex::new((0..replications).map(|_| None).collect::<Vec<_>>());
+
+        thread::scope(|scope| {
+            for _ in 0..usize::min(self.threads, replications) {
+                scope.spawn(|| loop {
+                    let replication = next_replication.fetch_add(1, Ordering::Relaxed);
+                    if replication >= replications {
+                        break;
+                    }
+
+                    let output = self.run_replication((first + replication) as u64);
+                    outputs.lock().unwrap()[replication] = Some(output);
+                });
+            }
+        });
+
+        for output in outputs.into_inner().unwrap() {
+            results.add_replication(output.expect("Every replication has run")?);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,479 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+use super::distributions::{
+    Beta, Distrib, Erlang, Exponential, Gamma, Gaussian, JohnsonSU, Lognormal, Pareto, Triangular, Uniform, Weibull,
+};
+use super::special;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    Uniform,
+    Gaussian,
+    Exponential,
+    Lognormal,
+    Gamma,
+    Weibull,
+    Triangular,
+    Beta,
+    Erlang,
+    Pareto,
+    JohnsonSU,
+}
+
+impl Family {
+    pub const ALL: [Family; 11] = [
+        Family::Uniform,
+        Family::Gaussian,
+        Family::Exponential,
+        Family::Lognormal,
+        Family::Gamma,
+        Family::Weibull,
+        Family::Triangular,
+        Family::Beta,
+        Family::Erlang,
+        Family::Pareto,
+        Family::JohnsonSU,
+    ];
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Goodness-of-fit statistic used to rank fitted candidates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criterion {
+    KolmogorovSmirnov,
+    AndersonDarling,
+    ChiSquare,
+}
+
+/// A fitted distribution with its goodness-of-fit statistics.
+///
+/// The p-values are approximate: the parameters are estimated from the same samples, which
+/// makes the tests conservative, i.e. p-values too high. Use them to rank and screen candidates
+/// rather than as exact significance levels.
+pub struct Fit {
+    pub family: Family,
+    pub parameters: Vec<(&'static str, f64)>,
+    pub distribution: Box<dyn Distrib>,
+    pub kolmogorov_smirnov: f64,
+    /// Approximate, from the Kolmogorov distribution for fully specified parameters
+    pub kolmogorov_smirnov_p_value: f64,
+    pub anderson_darling: f64,
+    pub chi_square: f64,
+    pub chi_square_degrees_of_freedom: u32,
+    /// Approximate, with a degree of freedom removed per estimated parameter
+    pub chi_square_p_value: f64,
+}
+
+impl Fit {
+    pub fn statistic(&self, criterion: Criterion) -> f64 {
+        match criterion {
+            Criterion::KolmogorovSmirnov => self.kolmogorov_smirnov,
+            Criterion::AndersonDarling => self.anderson_darling,
+            Criterion::ChiSquare => self.chi_square,
+        }
+    }
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|(name, value)| format!("{}={:.4}", name, value))
+            .collect();
+
+        write!(
+            f,
+            "{}({}) KS={:.4} (approx. p={:.3}) AD={:.4} Chi2={:.4} (df={}, approx. p={:.3})",
+            self.family,
+            parameters.join(", "),
+            self.kolmogorov_smirnov,
+            self.kolmogorov_smirnov_p_value,
+            self.anderson_darling,
+            self.chi_square,
+            self.chi_square_degrees_of_freedom,
+            self.chi_square_p_value
+        )
+    }
+}
+
+struct Summary {
+    n: f64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    variance: f64,
+}
+
+impl Summary {
+    fn new(samples: &[f64]) -> Self {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+
+        Self {
+            n,
+            min: samples.iter().cloned().fold(f64::INFINITY, f64::min),
+            max: samples.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            variance: samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n,
+        }
+    }
+
+    fn mean_ln(&self, samples: &[f64]) -> f64 {
+        samples.iter().map(|x| x.ln()).sum::<f64>() / self.n
+    }
+}
+
+/// Quantile of sorted samples, interpolating between order statistics
+fn sample_quantile(sorted: &[f64], p: f64) -> f64 {
+    let position = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let i = usize::min(position as usize, sorted.len() - 2);
+    sorted[i] + (position - i as f64) * (sorted[i + 1] - sorted[i])
+}
+
+/// Named parameters and the resulting distribution
+type Estimate = (Vec<(&'static str, f64)>, Box<dyn Distrib>);
+
+/// Estimates parameters by maximum likelihood, except for the triangular, beta and Erlang
+/// (method of moments) and the Johnson SU (Slifker and Shapiro's percentile method).
+/// `samples` must be sorted.
+fn estimate(samples: &[f64], family: Family) -> Result<Estimate> {
+    let summary = Summary::new(samples);
+    let positive = summary.min > 0.0;
+
+    if summary.variance <= 0.0 {
+        return Err(anyhow!("Samples have no variability"));
+    }
+
+    match family {
+        Family::Uniform => Ok((
+            vec![("min", summary.min), ("max", summary.max)],
+            Box::new(Uniform::new(summary.min, summary.max)),
+        )),
+        Family::Gaussian => {
+            let std = summary.variance.sqrt();
+            Ok((
+                vec![("mean", summary.mean), ("std", std)],
+                Box::new(Gaussian::new(summary.mean, std)),
+            ))
+        }
+        Family::Exponential => {
+            if summary.min < 0.0 {
+                return Err(anyhow!("Exponential requires non-negative samples"));
+            }
+
+            Ok((
+                vec![("mean", summary.mean)],
+                Box::new(Exponential::new(summary.mean)),
+            ))
+        }
+        Family::Lognormal => {
+            if !positive {
+                return Err(anyhow!("Lognormal requires positive samples"));
+            }
+
+            let mu = summary.mean_ln(samples);
+            let sigma = (samples.iter().map(|x| (x.ln() - mu).powi(2)).sum::<f64>() / summary.n).sqrt();
+            Ok((
+                vec![("mu", mu), ("sigma", sigma)],
+                Box::new(Lognormal::new(mu, sigma)?),
+            ))
+        }
+        Family::Gamma => {
+            if !positive {
+                return Err(anyhow!("Gamma requires positive samples"));
+            }
+
+            // Newton iterations on the shape, from Minka's initial approximation
+            let s = summary.mean.ln() - summary.mean_ln(samples);
+            let mut shape = (3.0 - s + ((s - 3.0).powi(2) + 24.0 * s).sqrt()) / (12.0 * s);
+            for _ in 0..50 {
+                let step = (shape.ln() - special::digamma(shape) - s) / (1.0 / shape - special::trigamma(shape));
+                shape = f64::max(shape - step, shape / 10.0);
+                if step.abs() < 1e-10 * shape {
+                    break;
+                }
+            }
+
+            let scale = summary.mean / shape;
+            Ok((
+                vec![("shape", shape), ("scale", scale)],
+                Box::new(Gamma::new(shape, scale)?),
+            ))
+        }
+        Family::Weibull => {
+            if !positive {
+                return Err(anyhow!("Weibull requires positive samples"));
+            }
+
+            // Bisection on the profile likelihood equation for the shape
+            let mean_ln = summary.mean_ln(samples);
+            let equation = |k: f64| {
+                let (mut sum, mut weighted) = (0.0, 0.0);
+                for x in samples.iter() {
+                    let xk = (x / summary.max).powf(k);
+                    sum += xk;
+                    weighted += xk * x.ln();
+                }
+
+                weighted / sum - 1.0 / k - mean_ln
+            };
+
+            let (mut low, mut high) = (1e-3f64, 1e3f64);
+            for _ in 0..200 {
+                let mid = (low * high).sqrt();
+                if equation(mid) > 0.0 {
+                    high = mid;
+                } else {
+                    low = mid;
+                }
+            }
+
+            let shape = (low * high).sqrt();
+            let scale = summary.max
+                * (samples.iter().map(|x| (x / summary.max).powf(shape)).sum::<f64>() / summary.n).powf(1.0 / shape);
+            Ok((
+                vec![("shape", shape), ("scale", scale)],
+                Box::new(Weibull::new(shape, scale)?),
+            ))
+        }
+        Family::Triangular => {
+            let mode = (3.0 * summary.mean - summary.min - summary.max).clamp(summary.min, summary.max);
+            Ok((
+                vec![("min", summary.min), ("mode", mode), ("max", summary.max)],
+                Box::new(Triangular::new(summary.min, mode, summary.max)?),
+            ))
+        }
+        Family::Beta => {
+            // Widen the observed range by the expected gap to the bounds
+            let margin = (summary.max - summary.min) / (summary.n - 1.0);
+            let (min, max) = (summary.min - margin, summary.max + margin);
+            let mean = (summary.mean - min) / (max - min);
+            let variance = summary.variance / (max - min).powi(2);
+
+            let common = mean * (1.0 - mean) / variance - 1.0;
+            if common <= 0.0 {
+                return Err(anyhow!("Samples are too dispersed for a beta"));
+            }
+
+            let (alpha, beta) = (mean * common, (1.0 - mean) * common);
+            Ok((
+                vec![("alpha", alpha), ("beta", beta), ("min", min), ("max", max)],
+                Box::new(Beta::new_scaled(alpha, beta, min, max)?),
+            ))
+        }
+        Family::Erlang => {
+            if summary.min < 0.0 {
+                return Err(anyhow!("Erlang requires non-negative samples"));
+            }
+
+            let phases = f64::max((summary.mean * summary.mean / summary.variance).round(), 1.0);
+            let phase_mean = summary.mean / phases;
+            Ok((
+                vec![("phases", phases), ("phase_mean", phase_mean)],
+                Box::new(Erlang::new(phases as u32, phase_mean)?),
+            ))
+        }
+        Family::Pareto => {
+            if !positive {
+                return Err(anyhow!("Pareto requires positive samples"));
+            }
+
+            let scale = summary.min;
+            let shape = summary.n / samples.iter().map(|x| (x / scale).ln()).sum::<f64>();
+            Ok((
+                vec![("scale", scale), ("shape", shape)],
+                Box::new(Pareto::new(scale, shape)?),
+            ))
+        }
+        Family::JohnsonSU => {
+            // Quantiles at -3z, -z, z and 3z standard deviations
+            let z = 0.524;
+            let quantile = |z: f64| sample_quantile(samples, special::normal_cdf(z));
+            let m = quantile(3.0 * z) - quantile(z);
+            let n = quantile(-z) - quantile(-3.0 * z);
+            let p = quantile(z) - quantile(-z);
+
+            let (m, n) = (m / p, n / p);
+            if m * n <= 1.0 {
+                return Err(anyhow!("Sample tails are too light for a Johnson SU"));
+            }
+
+            let root = (m * n - 1.0).sqrt();
+            let delta = 2.0 * z / (0.5 * (m + n)).acosh();
+            let gamma = delta * ((n - m) / (2.0 * root)).asinh();
+            let lambda = 2.0 * p * root / ((m + n - 2.0) * (m + n + 2.0).sqrt());
+            let xi = (quantile(z) + quantile(-z)) / 2.0 + p * (n - m) / (2.0 * (m + n - 2.0));
+            Ok((
+                vec![("gamma", gamma), ("delta", delta), ("xi", xi), ("lambda", lambda)],
+                Box::new(JohnsonSU::new(gamma, delta, xi, lambda)?),
+            ))
+        }
+    }
+}
+
+fn cdf_values(sorted: &[f64], distribution: &dyn Distrib) -> Result<Vec<f64>> {
+    sorted
+        .iter()
+        .map(|x| distribution.cdf(*x).ok_or_else(|| anyhow!("Distribution has no CDF")))
+        .collect()
+}
+
+/// Kolmogorov-Smirnov statistic and its asymptotic p-value
+pub fn kolmogorov_smirnov(sorted: &[f64], distribution: &dyn Distrib) -> Result<(f64, f64)> {
+    let n = sorted.len() as f64;
+    let cdf = cdf_values(sorted, distribution)?;
+
+    let mut d = 0f64;
+    for (i, f) in cdf.iter().enumerate() {
+        d = d.max((i + 1) as f64 / n - f).max(f - i as f64 / n);
+    }
+
+    // Stephens' approximation of the Kolmogorov distribution
+    let lambda = (n.sqrt() + 0.12 + 0.11 / n.sqrt()) * d;
+    let mut p_value = 0f64;
+    for j in 1..=100 {
+        let j = j as f64;
+        p_value += 2.0 * (-1f64).powf(j - 1.0) * (-2.0 * j * j * lambda * lambda).exp();
+    }
+
+    Ok((d, p_value.clamp(0.0, 1.0)))
+}
+
+pub fn anderson_darling(sorted: &[f64], distribution: &dyn Distrib) -> Result<f64> {
+    let n = sorted.len();
+    let cdf: Vec<f64> = cdf_values(sorted, distribution)?
+        .into_iter()
+        .map(|p| p.clamp(1e-12, 1.0 - 1e-12))
+        .collect();
+
+    let mut sum = 0f64;
+    for i in 0..n {
+        sum += (2 * i + 1) as f64 * (cdf[i].ln() + (1.0 - cdf[n - 1 - i]).ln());
+    }
+
+    Ok(-(n as f64) - sum / n as f64)
+}
+
+/// Chi-square statistic over equal-width bins, merged so every bin expects at least 5 samples.
+/// Returns the statistic and the number of bins used.
+pub fn chi_square(sorted: &[f64], distribution: &dyn Distrib) -> Result<(f64, usize)> {
+    let n = sorted.len() as f64;
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let n_bins = (n.sqrt().ceil() as usize).clamp(5, 50);
+    let width = (max - min) / n_bins as f64;
+
+    let mut observed = vec![0f64; n_bins];
+    for x in sorted.iter() {
+        observed[usize::min(((x - min) / width) as usize, n_bins - 1)] += 1.0;
+    }
+
+    // (expected, observed), outer bins extend to infinity
+    let mut bins = vec![];
+    let mut previous_cdf = 0f64;
+    for (i, observed) in observed.into_iter().enumerate() {
+        let cdf = if i + 1 == n_bins {
+            1.0
+        } else {
+            let upper = min + (i + 1) as f64 * width;
+            distribution
+                .cdf(upper)
+                .ok_or_else(|| anyhow!("Distribution has no CDF"))?
+        };
+
+        bins.push((n * (cdf - previous_cdf), observed));
+        previous_cdf = cdf;
+    }
+
+    let mut merged: Vec<(f64, f64)> = vec![];
+    for (expected, observed) in bins {
+        match merged.last_mut() {
+            Some(last) if last.0 < 5.0 => {
+                last.0 += expected;
+                last.1 += observed;
+            }
+            _ => merged.push((expected, observed)),
+        }
+    }
+
+    if merged.len() > 1 && merged.last().unwrap().0 < 5.0 {
+        let (expected, observed) = merged.pop().unwrap();
+        let last = merged.last_mut().unwrap();
+        last.0 += expected;
+        last.1 += observed;
+    }
+
+    let statistic = merged
+        .iter()
+        .map(|(expected, observed)| (observed - expected).powi(2) / f64::max(*expected, 1e-12))
+        .sum();
+
+    Ok((statistic, merged.len()))
+}
+
+fn check_samples(samples: &[f64]) -> Result<()> {
+    if samples.len() < 5 {
+        return Err(anyhow!("At least 5 samples are needed to fit a distribution"));
+    }
+
+    if samples.iter().any(|x| !x.is_finite()) {
+        return Err(anyhow!("Samples must be finite to fit a distribution"));
+    }
+
+    Ok(())
+}
+
+/// Fits a single family to the samples
+pub fn fit(samples: &[f64], family: Family) -> Result<Fit> {
+    check_samples(samples)?;
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let (parameters, distribution) = estimate(&sorted, family)?;
+    let (ks, ks_p_value) = kolmogorov_smirnov(&sorted, distribution.as_ref())?;
+    let ad = anderson_darling(&sorted, distribution.as_ref())?;
+    let (chi2, bins) = chi_square(&sorted, distribution.as_ref())?;
+    let degrees_of_freedom = bins.saturating_sub(1 + parameters.len()).max(1) as u32;
+
+    Ok(Fit {
+        family,
+        parameters,
+        distribution,
+        kolmogorov_smirnov: ks,
+        kolmogorov_smirnov_p_value: ks_p_value,
+        anderson_darling: ad,
+        chi_square: chi2,
+        chi_square_degrees_of_freedom: degrees_of_freedom,
+        chi_square_p_value: 1.0 - special::gamma_p(degrees_of_freedom as f64 / 2.0, chi2 / 2.0),
+    })
+}
+
+/// Fits every candidate family that supports the samples, best first according to `criterion`
+pub fn fit_all(samples: &[f64], criterion: Criterion) -> Result<Vec<Fit>> {
+    check_samples(samples)?;
+
+    let mut fits: Vec<Fit> = Family::ALL
+        .iter()
+        .filter_map(|family| fit(samples, *family).ok())
+        .collect();
+
+    if fits.is_empty() {
+        return Err(anyhow!("No candidate distribution could be fitted"));
+    }
+
+    fits.sort_by(|a, b| a.statistic(criterion).total_cmp(&b.statistic(criterion)));
+
+    Ok(fits)
+}
+
+/// Best fitting distribution according to `criterion`, ready to be used in the model
+pub fn best_fit(samples: &[f64], criterion: Criterion) -> Result<Box<dyn Distrib>> {
+    Ok(fit_all(samples, criterion)?.remove(0).distribution)
+}
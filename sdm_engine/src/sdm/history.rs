@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use super::Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryKind {
+    EnteredSet(String),
+    LeftSet(String),
+    /// Entered a set of entities being worked on, see `with_value_added`
+    EnteredActivity(String),
+    LeftActivity(String),
+    Seized(String, i32),
+    Released(String, i32),
+    /// Events are not tied to entities, they are only recorded through `Entity::touch_event`
+    Event(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryRecord {
+    pub time: f64,
+    pub kind: HistoryKind,
+}
+
+/// Journey of a single entity through the model
+#[derive(Debug, Default, Clone)]
+pub struct EntityHistory {
+    start: f64,
+    records: Vec<HistoryRecord>,
+}
+
+impl EntityHistory {
+    pub fn new(start: f64) -> Self {
+        Self {
+            start,
+            records: vec![],
+        }
+    }
+
+    pub fn record(&mut self, time: f64, kind: HistoryKind) {
+        self.records.push(HistoryRecord { time, kind });
+    }
+
+    pub fn records(&self) -> &[HistoryRecord] {
+        &self.records
+    }
+
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    pub fn cycle_time(&self, now: f64) -> f64 {
+        now - self.start
+    }
+
+    /// Time spent holding at least one resource outside of waiting sets. Time in sets built
+    /// `with_value_added` still counts, e.g. a client ordering, but not a seated client waiting
+    /// for food.
+    pub fn value_added_time(&self, now: f64) -> f64 {
+        let mut held: HashMap<&str, i32> = HashMap::new();
+        let mut waiting: HashMap<&str, i32> = HashMap::new();
+        let mut total = 0f64;
+        let mut since = None;
+
+        for record in self.records.iter() {
+            match &record.kind {
+                HistoryKind::Seized(resource, quantity) => {
+                    *held.entry(resource).or_insert(0) += quantity;
+                }
+                HistoryKind::Released(resource, quantity) => {
+                    *held.entry(resource).or_insert(0) -= quantity;
+                }
+                HistoryKind::EnteredSet(set) => {
+                    *waiting.entry(set).or_insert(0) += 1;
+                }
+                HistoryKind::LeftSet(set) => {
+                    *waiting.entry(set).or_insert(0) -= 1;
+                }
+                _ => continue,
+            }
+
+            let holding = held.values().any(|quantity| *quantity > 0);
+            let adding_value = holding && waiting.values().all(|count| *count <= 0);
+            match (adding_value, since) {
+                (true, None) => since = Some(record.time),
+                (false, Some(start)) => {
+                    total += record.time - start;
+                    since = None;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(start) = since {
+            total += now - start;
+        }
+
+        total
+    }
+
+    /// Time not adding value, i.e. waiting
+    pub fn waiting_time(&self, now: f64) -> f64 {
+        self.cycle_time(now) - self.value_added_time(now)
+    }
+
+    /// Total time spent in each `EntitySet` visited
+    pub fn time_in_sets(&self, now: f64) -> HashMap<String, f64> {
+        let mut entered: HashMap<&str, f64> = HashMap::new();
+        let mut times: HashMap<String, f64> = HashMap::new();
+
+        for record in self.records.iter() {
+            match &record.kind {
+                HistoryKind::EnteredSet(set) | HistoryKind::EnteredActivity(set) => {
+                    entered.insert(set, record.time);
+                }
+                HistoryKind::LeftSet(set) | HistoryKind::LeftActivity(set) => {
+                    if let Some(start) = entered.remove(set.as_str()) {
+                        *times.entry(set.clone()).or_insert(0.0) += record.time - start;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (set, start) in entered {
+            *times.entry(set.to_string()).or_insert(0.0) += now - start;
+        }
+
+        times
+    }
+}
+
+/// Aggregated histories of a single entity type
+#[derive(Debug, Default, Clone)]
+pub struct TypeHistory {
+    pub count: u32,
+    pub total_cycle_time: f64,
+    pub total_value_added_time: f64,
+    pub total_waiting_time: f64,
+    pub max_cycle_time: f64,
+}
+
+/// Averages are 0 until a history is collected
+impl TypeHistory {
+    pub fn average_cycle_time(&self) -> f64 {
+        self.average(self.total_cycle_time)
+    }
+
+    pub fn average_value_added_time(&self) -> f64 {
+        self.average(self.total_value_added_time)
+    }
+
+    pub fn average_waiting_time(&self) -> f64 {
+        self.average(self.total_waiting_time)
+    }
+
+    fn average(&self, total: f64) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            total / self.count as f64
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HistoryStatistics {
+    types: HashMap<String, TypeHistory>,
+}
+
+impl HistoryStatistics {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::new(),
+        }
+    }
+
+    /// Adds the history of an entity to its type statistics, if it has one
+    pub fn collect(&mut self, entity: &dyn Entity, now: f64) {
+        if let Some(history) = entity.history() {
+            let cycle_time = history.cycle_time(now);
+            let value_added_time = history.value_added_time(now);
+
+            let stats = self.types.entry(entity.entity_type().to_string()).or_default();
+            stats.count += 1;
+            stats.total_cycle_time += cycle_time;
+            stats.total_value_added_time += value_added_time;
+            stats.total_waiting_time += cycle_time - value_added_time;
+            if stats.max_cycle_time < cycle_time {
+                stats.max_cycle_time = cycle_time;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.types.clear();
+    }
+
+    pub fn get(&self, entity_type: &str) -> Option<&TypeHistory> {
+        self.types.get(entity_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &TypeHistory)> {
+        self.types.iter()
+    }
+}
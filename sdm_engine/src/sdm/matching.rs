@@ -0,0 +1,152 @@
+use std::rc::Rc;
+use uuid::Uuid;
+
+use super::{Entity, EntitySet};
+
+/// Extracts the key used to pair entities across `EntitySet`s
+pub type MatchKey = fn(&dyn Entity) -> Uuid;
+
+pub trait Match {
+    fn name(&self) -> &str;
+
+    fn sets(&self) -> &[(Rc<dyn EntitySet>, MatchKey)];
+
+    /// Releases one group of matching entities, returns whether a match was found
+    fn try_match(&self) -> bool;
+
+    fn matches(&self) -> u32;
+
+    fn average_wait_time(&self) -> f64;
+
+    fn max_wait_time(&self) -> f64;
+
+    /// Forget collected statistics, e.g. after the warm-up period
+    fn reset_statistics(&self);
+}
+
+/// Looks for an entity in every set sharing a key with an entity of the first set.
+/// Returns the IDs of the matched entities, in the same order as `sets`.
+pub fn find_match(sets: &[(Rc<dyn EntitySet>, MatchKey)]) -> Option<Vec<Uuid>> {
+    let (first_set, first_key) = sets.first()?;
+
+    let mut candidates = vec![];
+    first_set.for_each(&mut |entity| candidates.push((*entity.id(), first_key(entity))));
+
+    for (id, key) in candidates {
+        let mut ids = vec![id];
+
+        for (set, set_key) in sets.iter().skip(1) {
+            match set.find(&|entity| set_key(entity) == key) {
+                Some(other) => ids.push(other),
+                None => break,
+            }
+        }
+
+        if ids.len() == sets.len() {
+            return Some(ids);
+        }
+    }
+
+    None
+}
+
+#[macro_export]
+macro_rules! MatchWrapper {
+    ( $vis:vis struct $name:ident $({ $($varvis:vis $varname:ident : $type:ty),* $(,)? })? ;
+      $( @on_match = |$match_var:ident, $entities_var:ident| $match_code:block ; )?
+    ) => {
+        $vis struct $name {
+            name: String,
+            id: uuid::Uuid,
+            sets: Vec<(std::rc::Rc<dyn sdm_engine::sdm::EntitySet>, sdm_engine::sdm::matching::MatchKey)>,
+            matches: std::cell::RefCell<u32>,
+            wait_times: std::cell::RefCell<Vec<f64>>,
+            max_wait_time: std::cell::RefCell<f64>,
+            on_match: Option<fn(&Self, Vec<Box<dyn sdm_engine::sdm::Entity>>) -> ()>,
+            $($(
+                $varvis $varname: $type,
+            )*)?
+        }
+
+        impl sdm_engine::sdm::Match for $name {
+            fn name(&self) -> &str {
+                &self.name
+            }
+
+            fn sets(&self) -> &[(std::rc::Rc<dyn sdm_engine::sdm::EntitySet>, sdm_engine::sdm::matching::MatchKey)] {
+                &self.sets
+            }
+
+            fn try_match(&self) -> bool {
+                if let Some(ids) = sdm_engine::sdm::matching::find_match(&self.sets) {
+                    let mut entities = vec![];
+
+                    for ((set, _), id) in self.sets.iter().zip(ids) {
+                        let waited = set.time_in_set(id).unwrap_or(0.0);
+                        self.wait_times.borrow_mut().push(waited);
+                        if *self.max_wait_time.borrow() < waited {
+                            *self.max_wait_time.borrow_mut() = waited;
+                        }
+
+                        entities.push(set.remove(id).expect("Matched entity left its set"));
+                    }
+
+                    *self.matches.borrow_mut() += 1;
+
+                    if let Some(func) = self.on_match {
+                        func(self, entities);
+                    }
+
+                    true
+                } else {
+                    false
+                }
+            }
+
+            fn matches(&self) -> u32 {
+                *self.matches.borrow()
+            }
+
+            fn average_wait_time(&self) -> f64 {
+                let wait_times = self.wait_times.borrow();
+                if wait_times.is_empty() {
+                    return 0.0;
+                }
+                wait_times.iter().sum::<f64>() / wait_times.len() as f64
+            }
+
+            fn max_wait_time(&self) -> f64 {
+                *self.max_wait_time.borrow()
+            }
+
+            fn reset_statistics(&self) {
+                *self.matches.borrow_mut() = 0;
+                self.wait_times.borrow_mut().clear();
+                *self.max_wait_time.borrow_mut() = 0.0;
+            }
+        }
+
+        impl $name {
+            pub fn new(
+                name: &str,
+                sets: Vec<(std::rc::Rc<dyn sdm_engine::sdm::EntitySet>, sdm_engine::sdm::matching::MatchKey)>
+                $(,$($varname: $type),*)?
+            ) -> Self {
+                let mut on_match: Option<fn(&Self, Vec<Box<dyn sdm_engine::sdm::Entity>>) -> ()> = None;
+
+                $(on_match = Some(|$match_var, $entities_var| $match_code);)?
+
+                Self {
+                    name: name.to_string(),
+                    id: uuid::Uuid::new_v4(),
+                    sets,
+                    matches: std::cell::RefCell::new(0u32),
+                    wait_times: std::cell::RefCell::new(vec![]),
+                    max_wait_time: std::cell::RefCell::new(0f64),
+                    on_match,
+                    $($($varname,)*)?
+                }
+            }
+        }
+    };
+}
@@ -1,15 +1,62 @@
+pub mod analysis;
+pub mod attribute;
+pub mod combinators;
+pub mod correlated;
+pub mod debugger;
+pub mod discrete;
+pub mod disposal;
+pub mod doe;
 pub mod distributions;
+pub mod empirical;
 pub mod entity;
 pub mod entity_set;
 pub mod event;
+pub mod experiment;
+pub mod fitting;
+pub mod history;
+pub mod matching;
+pub mod observer;
+pub mod optimization;
 pub mod process;
+pub mod qmc;
+pub mod report;
 pub mod resource;
+pub mod scenario;
 pub mod scheduler;
+pub mod source;
+mod special;
+pub mod statistics;
+pub mod time;
+pub mod trace;
 
-pub use distributions::{Distrib, Exponential, Gaussian, Uniform};
+pub use attribute::{AttributeValue, Attributes};
+pub use combinators::{Affine, Maximum, Minimum, Mixture, Truncated};
+pub use correlated::{Autocorrelated, Norta};
+pub use debugger::Debugger;
+pub use discrete::{
+    Bernoulli, Binomial, DiscreteDistrib, DiscreteTable, DiscreteUniform, Geometric,
+    NegativeBinomial, Poisson,
+};
+pub use distributions::{
+    Beta, Constant, Distrib, Erlang, Exponential, Gamma, Gaussian, JohnsonSU, Lognormal, Pareto, Triangular,
+    Uniform, Weibull,
+};
+pub use empirical::{EmpiricalContinuous, EmpiricalDiscrete, TraceReplay};
+pub use doe::{Design, Factor, Sweep};
 pub use entity::Entity;
 pub use entity_set::{EntitySet, EntitySetMode};
 pub use event::Event;
+pub use experiment::{Experiment, ExperimentResults};
+pub use matching::Match;
+pub use observer::Observer;
+pub use optimization::{Annealing, Optimizer};
 pub use process::Process;
+pub use qmc::{QuasiRandom, QuasiRandomDiscrete, Sobol};
+pub use report::{SimulationReport, TimeSeries};
 pub use resource::Resource;
+pub use scenario::{Goal, Parameters, Scenarios};
 pub use scheduler::Scheduler;
+pub use source::{ArrivalSchedule, Source};
+pub use statistics::{Statistic, Tally, TimePersistent};
+pub use time::{Calendar, DateTime, Duration, TimeUnit, Weekday, WeeklySchedule};
+pub use trace::{Replay, Trace, TraceRecorder};
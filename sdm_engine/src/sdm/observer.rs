@@ -0,0 +1,76 @@
+use super::scheduler::ANALYTICS_PRINT_TIME;
+use super::{Entity, Event, Process, Scheduler};
+
+/// Hooks called by the scheduler as the simulation unfolds, e.g. for loggers, animators,
+/// validators and metrics collectors. Every hook does nothing by default.
+///
+/// Hooks run in the middle of a step: they may read the model and the scheduler, but should
+/// not change them.
+pub trait Observer {
+    /// `event` was added to the FEL to execute at `time`
+    fn on_event_scheduled(&self, _event: &dyn Event, _time: f64) {}
+
+    fn on_event_executed(&self, _event: &dyn Event) {}
+
+    /// `event` was removed from the FEL before its time
+    fn on_event_cancelled(&self, _event: &dyn Event, _time: f64) {}
+
+    /// `process` was queued to join the running processes at `time`
+    fn on_process_scheduled(&self, _process: &dyn Process, _time: f64) {}
+
+    /// `process` joined the running processes at `time`
+    fn on_process_activated(&self, _process: &dyn Process, _time: f64) {}
+
+    /// `process` started a cycle that ends after `duration`
+    fn on_process_started(&self, _process: &dyn Process, _duration: f64) {}
+
+    fn on_process_ended(&self, _process: &dyn Process) {}
+
+    fn on_entity_pushed(&self, _entity_set: &str, _entity: &dyn Entity) {}
+
+    /// `entity` left `entity_set`, popped or removed
+    fn on_entity_popped(&self, _entity_set: &str, _entity: &dyn Entity) {}
+
+    fn on_entities_matched(&self, _matcher: &str) {}
+
+    fn on_resource_allocated(&self, _resource: &str, _quantity: i32) {}
+
+    fn on_resource_released(&self, _resource: &str, _quantity: i32) {}
+
+    fn on_clock_advanced(&self, _from: f64, _to: f64) {}
+
+    /// Statistics were updated at the current time
+    fn on_analytics_updated(&self) {}
+
+    /// Statistics were reset at the end of the warm-up period
+    fn on_warm_up_ended(&self) {}
+
+    /// A step ended, leaving `events` in the FEL and `process_callbacks` scheduled
+    fn on_step_completed(&self, _events: usize, _process_callbacks: usize) {}
+}
+
+/// Console output of the scheduler, notified unless it is quiet
+#[derive(Debug, Default)]
+pub struct ConsoleLogger;
+
+impl Observer for ConsoleLogger {
+    fn on_process_activated(&self, process: &dyn Process, time: f64) {
+        println!("{} - Starting process \"{}\"", Scheduler::format_time(time), process.name());
+    }
+
+    fn on_entities_matched(&self, matcher: &str) {
+        println!("{} - Matched entities on \"{}\"", Scheduler::timestamp(), matcher);
+    }
+
+    fn on_analytics_updated(&self) {
+        if Scheduler::time() % ANALYTICS_PRINT_TIME == 0.0 {
+            if let Ok(scheduler) = Scheduler::instance() {
+                scheduler.print_analytics();
+            }
+        }
+    }
+
+    fn on_warm_up_ended(&self) {
+        println!("{} - Warm-up complete, statistics reset", Scheduler::timestamp());
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::thread;
+
+use super::doe::Factor;
+use super::experiment::{Experiment, ExperimentResults, Summary};
+use super::scenario::Parameters;
+use super::Scheduler;
+
+/// Statistics of one replication, as seen by objectives and constraints
+pub struct Outcome<'a> {
+    results: &'a ExperimentResults,
+    replication: usize,
+}
+
+impl<'a> Outcome<'a> {
+    pub fn get(&self, statistic: &str) -> Result<f64> {
+        match self.results.values(statistic) {
+            Some(values) if !values[self.replication].is_nan() => Ok(values[self.replication]),
+            _ => Err(anyhow!(
+                "Statistic \"{}\" is missing from replication {}",
+                statistic,
+                self.replication
+            )),
+        }
+    }
+}
+
+type Function = Box<dyn Fn(&Parameters, &Outcome) -> Result<f64> + Sync>;
+
+/// Penalised cost of a candidate in each of its replications
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub parameters: Parameters,
+    pub results: ExperimentResults,
+    pub costs: Vec<f64>,
+    pub violations: Vec<f64>, // Mean excess of each constraint over its limit, 0 when met
+}
+
+impl Evaluation {
+    /// Whether every constraint is met on average, penalties aside
+    pub fn is_feasible(&self) -> bool {
+        self.violations.iter().all(|violation| *violation <= 0.0)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.costs.iter().sum::<f64>() / self.costs.len() as f64
+    }
+
+    pub fn summary(&self, confidence: f64) -> Summary {
+        Summary::new("Cost", &self.costs, confidence)
+    }
+}
+
+/// Simulated annealing schedule and replication budget per candidate
+#[derive(Debug, Clone)]
+pub struct Annealing {
+    pub iterations: usize,
+    pub initial_temperature: f64,
+    pub cooling: f64, // Temperature factor applied after every iteration
+    pub replications: usize,
+    pub max_replications: usize,
+}
+
+impl Default for Annealing {
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            initial_temperature: 1.0,
+            cooling: 0.95,
+            replications: 5,
+            max_replications: 40,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizationResult {
+    pub best: Evaluation,
+    pub evaluations: Vec<Evaluation>, // Every candidate simulated, in order
+    pub trajectory: Vec<f64>,         // Mean cost of the current candidate after each iteration
+}
+
+/// Searches the levels of the factors for the lowest expected cost, the cost of a replication
+/// being the objective plus a penalty for every constraint violated on average
+pub struct Optimizer<F> {
+    build: F,
+    run_length: f64,
+    seed: u64,
+    confidence: f64,
+    threads: usize,
+    factors: Vec<Factor>,
+    start: Option<Parameters>,
+    objective: Option<Function>,
+    constraints: Vec<(Function, f64)>,
+    penalty: f64,
+}
+
+impl<F: Fn(&mut Scheduler, &Parameters) -> Result<()> + Sync> Optimizer<F> {
+    /// `build` sets up the model for the given parameters on a fresh scheduler
+    pub fn new(run_length: f64, build: F) -> Self {
+        Self {
+            build,
+            run_length,
+            seed: 0,
+            confidence: 0.95,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            factors: vec![],
+            start: None,
+            objective: None,
+            constraints: vec![],
+            penalty: 1000.0,
+        }
+    }
+
+    /// Seeds both the replications and the search
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Confidence level at which two candidates are told apart
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_factor(mut self, factor: Factor) -> Self {
+        self.factors.push(factor);
+        self
+    }
+
+    /// Starting candidate, with a level of every factor. The first level of every factor, or
+    /// the middle of continuous ones, otherwise.
+    pub fn with_start(mut self, start: Parameters) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Cost to minimise, e.g. wages plus the cost of waiting
+    pub fn with_objective(
+        mut self,
+        objective: impl Fn(&Parameters, &Outcome) -> Result<f64> + Sync + 'static,
+    ) -> Self {
+        self.objective = Some(Box::new(objective));
+        self
+    }
+
+    /// Requires the mean of `constraint` over the replications to be at most `limit`
+    pub fn with_constraint(
+        mut self,
+        constraint: impl Fn(&Parameters, &Outcome) -> Result<f64> + Sync + 'static,
+        limit: f64,
+    ) -> Self {
+        self.constraints.push((Box::new(constraint), limit));
+        self
+    }
+
+    /// Cost per unit of constraint violation
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    fn experiment<'a>(
+        &'a self,
+        parameters: &'a Parameters,
+    ) -> Experiment<impl Fn(&mut Scheduler) -> Result<()> + Sync + 'a> {
+        Experiment::new(self.run_length, move |scheduler| (self.build)(scheduler, parameters))
+            .with_seed(self.seed)
+            .with_confidence(self.confidence)
+            .with_threads(self.threads)
+    }
+
+    /// Costs in each replication, and the violation of each constraint
+    fn costs(&self, parameters: &Parameters, results: &ExperimentResults) -> Result<(Vec<f64>, Vec<f64>)> {
+        let objective = self.objective.as_ref().ok_or_else(|| anyhow!("No objective was set"))?;
+        let outcome = |replication| Outcome { results, replication };
+
+        let mut violations = vec![];
+        for (constraint, limit) in self.constraints.iter() {
+            let values = (0..results.replications())
+                .map(|replication| constraint(parameters, &outcome(replication)))
+                .collect::<Result<Vec<f64>>>()?;
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            violations.push(f64::max(0.0, mean - limit));
+        }
+
+        let penalty = self.penalty * violations.iter().sum::<f64>();
+        let costs = (0..results.replications())
+            .map(|replication| Ok(objective(parameters, &outcome(replication))? + penalty))
+            .collect::<Result<Vec<f64>>>()?;
+
+        Ok((costs, violations))
+    }
+
+    /// Evaluation of `parameters`, simulated only the first time they are seen
+    fn evaluate(
+        &self,
+        evaluations: &mut Vec<Evaluation>,
+        parameters: Parameters,
+        replications: usize,
+    ) -> Result<usize> {
+        if let Some(known) = evaluations.iter().position(|evaluation| evaluation.parameters == parameters) {
+            return Ok(known);
+        }
+
+        let results = self.experiment(&parameters).run_parallel(replications)?;
+        let (costs, violations) = self.costs(&parameters, &results)?;
+        evaluations.push(Evaluation {
+            parameters,
+            results,
+            costs,
+            violations,
+        });
+
+        Ok(evaluations.len() - 1)
+    }
+
+    fn replicate(&self, evaluation: &mut Evaluation, replications: usize) -> Result<()> {
+        let more = replications.saturating_sub(evaluation.costs.len());
+        if more > 0 {
+            self.experiment(&evaluation.parameters)
+                .run_more(&mut evaluation.results, more)?;
+            (evaluation.costs, evaluation.violations) = self.costs(&evaluation.parameters, &evaluation.results)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds replications to both candidates until their paired cost difference is significant
+    /// or both reach `max_replications`, so that noise alone does not decide between them
+    fn separate(&self, evaluations: &mut [Evaluation], a: usize, b: usize, max_replications: usize) -> Result<()> {
+        loop {
+            let replications = usize::max(evaluations[a].costs.len(), evaluations[b].costs.len());
+            self.replicate(&mut evaluations[a], replications)?;
+            self.replicate(&mut evaluations[b], replications)?;
+
+            let differences: Vec<f64> = evaluations[a]
+                .costs
+                .iter()
+                .zip(evaluations[b].costs.iter())
+                .map(|(x, y)| x - y)
+                .collect();
+            let difference = Summary::new("Difference", &differences, self.confidence);
+            let significant = !(difference.low() <= 0.0 && 0.0 <= difference.high());
+            if significant || replications >= max_replications {
+                return Ok(());
+            }
+
+            self.replicate(&mut evaluations[a], usize::min(2 * replications, max_replications))?;
+        }
+    }
+
+    pub fn simulated_annealing(&self, schedule: &Annealing) -> Result<OptimizationResult> {
+        if self.factors.is_empty() {
+            return Err(anyhow!("Optimization requires at least one factor"));
+        }
+        if schedule.replications < 2 || schedule.max_replications < schedule.replications {
+            return Err(anyhow!("Candidates require 2 <= replications <= max_replications"));
+        }
+        if !(0.0 < schedule.cooling && schedule.cooling <= 1.0) || schedule.initial_temperature <= 0.0 {
+            return Err(anyhow!("Annealing requires a positive temperature and a cooling factor in (0, 1]"));
+        }
+
+        // Independent of the replications' generators, so the search is reproducible
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let start = match &self.start {
+            Some(start) => {
+                // Checked before any simulation runs
+                for factor in self.factors.iter() {
+                    let value = start.get(factor.name())?;
+                    if !factor.contains(value) {
+                        return Err(anyhow!("Start value {} is not a level of \"{}\"", value, factor.name()));
+                    }
+                }
+                start.clone()
+            }
+            None => {
+                let mut start = Parameters::new();
+                for factor in self.factors.iter() {
+                    start.set(factor.name(), factor.initial());
+                }
+                start
+            }
+        };
+
+        let mut evaluations = vec![];
+        let mut current = self.evaluate(&mut evaluations, start, schedule.replications)?;
+        let mut best = current;
+        let mut temperature = schedule.initial_temperature;
+        let mut trajectory = vec![];
+
+        for _ in 0..schedule.iterations {
+            let factor = &self.factors[rng.gen_range(0..self.factors.len())];
+            let mut parameters = evaluations[current].parameters.clone();
+            parameters.set(factor.name(), factor.neighbour(parameters.get(factor.name())?, &mut rng));
+
+            let candidate = self.evaluate(&mut evaluations, parameters, schedule.replications)?;
+            if candidate != current {
+                self.separate(&mut evaluations, candidate, current, schedule.max_replications)?;
+
+                let delta = evaluations[candidate].mean() - evaluations[current].mean();
+                if delta <= 0.0 || rng.gen::<f64>() < (-delta / temperature).exp() {
+                    current = candidate;
+                }
+
+                if current != best && evaluations[current].mean() < evaluations[best].mean() {
+                    self.separate(&mut evaluations, current, best, schedule.max_replications)?;
+                    if evaluations[current].mean() < evaluations[best].mean() {
+                        best = current;
+                    }
+                }
+            }
+
+            trajectory.push(evaluations[current].mean());
+            temperature *= schedule.cooling;
+        }
+
+        Ok(OptimizationResult {
+            best: evaluations[best].clone(),
+            evaluations,
+            trajectory,
+        })
+    }
+}
@@ -1,5 +1,5 @@
 pub trait Process {
-    fn duration(&self) -> f32;
+    fn duration(&self) -> f64;
 
     fn name(&self) -> &str;
 
@@ -7,7 +7,7 @@ pub trait Process {
 
     fn is_active(&self) -> bool;
 
-    fn start(&mut self) -> f32;
+    fn start(&mut self) -> f64;
 
     fn end(&mut self);
 
@@ -33,7 +33,7 @@ macro_rules! ProcessWrapper {
         }
 
         impl sdm_engine::sdm::Process for $name {
-            fn duration(&self) -> f32 {
+            fn duration(&self) -> f64 {
                 self.duration.gen()
             }
 
@@ -49,7 +49,7 @@ macro_rules! ProcessWrapper {
                 self.active
             }
 
-            fn start(&mut self) -> f32 {
+            fn start(&mut self) -> f64 {
                 if let Some(func) = self.on_start {
                     func(self);
                 }
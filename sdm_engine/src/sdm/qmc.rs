@@ -0,0 +1,288 @@
+use anyhow::{anyhow, Context, Result};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::discrete::DiscreteDistrib;
+use super::distributions::{with_rng, Distrib, Uniform};
+use super::empirical::TraceReplay;
+
+const BITS: usize = 32;
+
+/// Primitive polynomial degree, coefficients and initial direction numbers of the Sobol
+/// dimensions after the first (Joe & Kuo, new-joe-kuo-6.21201)
+const SOBOL_PARAMETERS: [(u32, u32, &[u32]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+/// `points` points in `[0, 1)^dimensions`, with exactly one point in each of the
+/// `points` equal strata of every dimension
+pub fn latin_hypercube(points: usize, dimensions: usize) -> Vec<Vec<f64>> {
+    let mut design = vec![vec![0.0; dimensions]; points];
+
+    for dimension in 0..dimensions {
+        let mut strata: Vec<usize> = (0..points).collect();
+        with_rng(|rng| strata.shuffle(rng));
+
+        for (point, stratum) in design.iter_mut().zip(strata) {
+            point[dimension] = (stratum as f64 + Uniform::gen(0.0, 1.0)) / points as f64;
+        }
+    }
+
+    design
+}
+
+/// Sobol low-discrepancy sequence, generated in Gray code order
+pub struct Sobol {
+    directions: Vec<[u32; BITS]>,
+    state: Vec<u32>,
+    shift: Vec<u32>,
+    index: u64,
+}
+
+impl Sobol {
+    pub const MAX_DIMENSIONS: usize = SOBOL_PARAMETERS.len() + 1;
+
+    pub fn new(dimensions: usize) -> Result<Self> {
+        if dimensions == 0 || dimensions > Self::MAX_DIMENSIONS {
+            return Err(anyhow!("Sobol supports 1 to {} dimensions", Self::MAX_DIMENSIONS));
+        }
+
+        let mut directions = vec![[0u32; BITS]; dimensions];
+        for (k, direction) in directions[0].iter_mut().enumerate() {
+            *direction = 1 << (BITS - 1 - k);
+        }
+
+        for (dimension, (degree, coefficients, initial)) in SOBOL_PARAMETERS.iter().take(dimensions - 1).enumerate() {
+            let s = *degree as usize;
+            let v = &mut directions[dimension + 1];
+
+            for k in 0..BITS {
+                v[k] = if k < s {
+                    initial[k] << (BITS - 1 - k)
+                } else {
+                    let mut value = v[k - s] ^ (v[k - s] >> s);
+                    for i in 1..s {
+                        if (coefficients >> (s - 1 - i)) & 1 == 1 {
+                            value ^= v[k - i];
+                        }
+                    }
+                    value
+                };
+            }
+        }
+
+        Ok(Self {
+            directions,
+            state: vec![0; dimensions],
+            shift: vec![0; dimensions],
+            index: 0,
+        })
+    }
+
+    /// Randomized by a random digital shift, so independent sequences can be replicated
+    pub fn new_shifted(dimensions: usize) -> Result<Self> {
+        let mut sobol = Self::new(dimensions)?;
+        sobol.shift = with_rng(|rng| (0..dimensions).map(|_| rng.gen()).collect());
+
+        Ok(sobol)
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.directions.len()
+    }
+}
+
+impl Iterator for Sobol {
+    type Item = Vec<f64>;
+
+    fn next(&mut self) -> Option<Vec<f64>> {
+        if self.index >= 1 << BITS {
+            return None;
+        }
+
+        let point = self
+            .state
+            .iter()
+            .zip(self.shift.iter())
+            .map(|(state, shift)| (state ^ shift) as f64 / (1u64 << BITS) as f64)
+            .collect();
+
+        // Flip the direction of the lowest zero bit of the index
+        let bit = self.index.trailing_ones() as usize;
+        if bit < BITS {
+            for (state, directions) in self.state.iter_mut().zip(self.directions.iter()) {
+                *state ^= directions[bit];
+            }
+        }
+        self.index += 1;
+
+        Some(point)
+    }
+}
+
+/// Maps each coordinate of each point through the inverse CDF of its dimension's distribution
+pub fn inverse_transform(distributions: &[&dyn Distrib], points: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+    points
+        .iter()
+        .map(|point| {
+            if point.len() != distributions.len() {
+                return Err(anyhow!(
+                    "Point has {} dimensions but there are {} distributions",
+                    point.len(),
+                    distributions.len()
+                ));
+            }
+
+            point
+                .iter()
+                .zip(distributions.iter())
+                .map(|(u, distribution)| {
+                    distribution
+                        .sample_from_u(*u)
+                        .ok_or_else(|| anyhow!("Distribution has no quantile function"))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn exhausted(uniforms: &TraceReplay) -> ! {
+    panic!("QuasiRandom exhausted after {} uniforms", uniforms.len())
+}
+
+/// Generates from a distribution using the given uniforms in order, e.g. one dimension of a
+/// Latin hypercube design. Exhausted once all have been used, unless built `with_wrap_around`.
+pub struct QuasiRandom {
+    inner: Box<dyn Distrib>,
+    uniforms: TraceReplay,
+}
+
+impl Distrib for QuasiRandom {
+    /// Panics once the uniforms are exhausted, see `next_value` to handle it
+    fn gen(&self) -> f64 {
+        self.next_value().unwrap_or_else(|| exhausted(&self.uniforms))
+    }
+
+    fn mean(&self) -> Option<f64> {
+        self.inner.mean()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        self.inner.variance()
+    }
+
+    fn cdf(&self, x: f64) -> Option<f64> {
+        self.inner.cdf(x)
+    }
+
+    fn pdf(&self, x: f64) -> Option<f64> {
+        self.inner.pdf(x)
+    }
+
+    fn quantile(&self, p: f64) -> Option<f64> {
+        self.inner.quantile(p)
+    }
+}
+
+impl QuasiRandom {
+    pub fn new(inner: impl Distrib + 'static, uniforms: Vec<f64>) -> Result<Self> {
+        let uniforms = TraceReplay::new(uniforms).context("QuasiRandom requires finite uniforms")?;
+
+        if inner.sample_from_u(0.5).is_none() {
+            return Err(anyhow!("QuasiRandom requires a distribution with a quantile function"));
+        }
+
+        Ok(Self {
+            inner: Box::new(inner),
+            uniforms,
+        })
+    }
+
+    /// Start over from the first uniform once all have been used
+    pub fn with_wrap_around(mut self) -> Self {
+        self.uniforms = self.uniforms.with_wrap_around();
+        self
+    }
+
+    /// Next sample, or `None` once the uniforms are exhausted
+    pub fn next_value(&self) -> Option<f64> {
+        self.uniforms.next_value().map(|u| self.inner.sample_from_u(u).unwrap())
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.uniforms.is_exhausted()
+    }
+}
+
+/// Discrete counterpart of `QuasiRandom`, e.g. for Poisson or binomial inputs
+pub struct QuasiRandomDiscrete {
+    inner: Box<dyn DiscreteDistrib>,
+    uniforms: TraceReplay,
+}
+
+impl DiscreteDistrib for QuasiRandomDiscrete {
+    /// Panics once the uniforms are exhausted, see `next_value` to handle it
+    fn gen(&self) -> i64 {
+        self.next_value().unwrap_or_else(|| exhausted(&self.uniforms))
+    }
+
+    fn mean(&self) -> Option<f64> {
+        self.inner.mean()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        self.inner.variance()
+    }
+
+    fn cdf(&self, x: i64) -> Option<f64> {
+        self.inner.cdf(x)
+    }
+
+    fn quantile(&self, p: f64) -> Option<i64> {
+        self.inner.quantile(p)
+    }
+}
+
+impl QuasiRandomDiscrete {
+    pub fn new(inner: impl DiscreteDistrib + 'static, uniforms: Vec<f64>) -> Result<Self> {
+        let uniforms = TraceReplay::new(uniforms).context("QuasiRandomDiscrete requires finite uniforms")?;
+
+        if inner.sample_from_u(0.5).is_none() {
+            return Err(anyhow!("QuasiRandomDiscrete requires a distribution with a quantile function"));
+        }
+
+        Ok(Self {
+            inner: Box::new(inner),
+            uniforms,
+        })
+    }
+
+    /// Start over from the first uniform once all have been used
+    pub fn with_wrap_around(mut self) -> Self {
+        self.uniforms = self.uniforms.with_wrap_around();
+        self
+    }
+
+    /// Next sample, or `None` once the uniforms are exhausted
+    pub fn next_value(&self) -> Option<i64> {
+        self.uniforms.next_value().map(|u| self.inner.sample_from_u(u).unwrap())
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.uniforms.is_exhausted()
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceReport {
+    pub name: String,
+    pub quantity: i32,
+    pub allocated: i32,
+    pub allocation_rate: f64,
+    pub average_allocation: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntitySetReport {
+    pub name: String,
+    pub size: usize,
+    pub average_size: f64,
+    pub average_time_in_set: f64,
+    pub max_time_in_set: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchReport {
+    pub name: String,
+    pub matches: u32,
+    pub average_wait_time: f64,
+    pub max_wait_time: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceReport {
+    pub name: String,
+    pub arrivals: u32,
+}
+
+/// Histories and disposals of one entity type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityTypeReport {
+    pub entity_type: String,
+    pub created: u32,
+    pub disposed: u32,
+    pub work_in_process: u32,
+    pub average_work_in_process: f64,
+    pub average_time_in_system: f64,
+    pub max_time_in_system: f64,
+    pub histories: u32,
+    pub average_cycle_time: f64,
+    pub average_value_added_time: f64,
+    pub average_waiting_time: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatisticReport {
+    pub name: String,
+    pub count: u32,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Snapshot of every statistic of a run, as printed by `Scheduler::print_analytics`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulationReport {
+    pub time: f64,
+    pub timestamp: String,
+    pub time_unit: String,
+    pub resources: Vec<ResourceReport>,
+    pub entity_sets: Vec<EntitySetReport>,
+    pub matches: Vec<MatchReport>,
+    pub sources: Vec<SourceReport>,
+    pub entity_types: Vec<EntityTypeReport>,
+    pub statistics: Vec<StatisticReport>,
+}
+
+// Section, name and metrics of one reported item
+type Item<'a> = (&'static str, &'a str, Vec<(&'static str, f64)>);
+
+impl SimulationReport {
+    fn items(&self) -> Vec<Item<'_>> {
+        let mut items: Vec<Item> = vec![];
+
+        for resource in self.resources.iter() {
+            items.push((
+                "resources",
+                &resource.name,
+                vec![
+                    ("quantity", resource.quantity as f64),
+                    ("allocated", resource.allocated as f64),
+                    ("allocation_rate", resource.allocation_rate),
+                    ("average_allocation", resource.average_allocation),
+                ],
+            ));
+        }
+
+        for entity_set in self.entity_sets.iter() {
+            items.push((
+                "entity_sets",
+                &entity_set.name,
+                vec![
+                    ("size", entity_set.size as f64),
+                    ("average_size", entity_set.average_size),
+                    ("average_time_in_set", entity_set.average_time_in_set),
+                    ("max_time_in_set", entity_set.max_time_in_set),
+                ],
+            ));
+        }
+
+        for matcher in self.matches.iter() {
+            items.push((
+                "matches",
+                &matcher.name,
+                vec![
+                    ("matches", matcher.matches as f64),
+                    ("average_wait_time", matcher.average_wait_time),
+                    ("max_wait_time", matcher.max_wait_time),
+                ],
+            ));
+        }
+
+        for source in self.sources.iter() {
+            items.push(("sources", &source.name, vec![("arrivals", source.arrivals as f64)]));
+        }
+
+        for entity_type in self.entity_types.iter() {
+            items.push((
+                "entity_types",
+                &entity_type.entity_type,
+                vec![
+                    ("created", entity_type.created as f64),
+                    ("disposed", entity_type.disposed as f64),
+                    ("work_in_process", entity_type.work_in_process as f64),
+                    ("average_work_in_process", entity_type.average_work_in_process),
+                    ("average_time_in_system", entity_type.average_time_in_system),
+                    ("max_time_in_system", entity_type.max_time_in_system),
+                    ("histories", entity_type.histories as f64),
+                    ("average_cycle_time", entity_type.average_cycle_time),
+                    ("average_value_added_time", entity_type.average_value_added_time),
+                    ("average_waiting_time", entity_type.average_waiting_time),
+                ],
+            ));
+        }
+
+        for statistic in self.statistics.iter() {
+            items.push((
+                "statistics",
+                &statistic.name,
+                vec![
+                    ("count", statistic.count as f64),
+                    ("mean", statistic.mean),
+                    ("min", statistic.min),
+                    ("max", statistic.max),
+                ],
+            ));
+        }
+
+        items
+    }
+
+    /// Main statistics as `(name, value)`, e.g. at the end of a replication. Entity types are
+    /// sorted, so every replication lists statistics in the same order.
+    pub fn summary(&self) -> Vec<(String, f64)> {
+        let mut summary = vec![];
+
+        for resource in self.resources.iter() {
+            summary.push((format!("{} - Allocation rate", resource.name), resource.allocation_rate));
+            summary.push((format!("{} - Average allocation", resource.name), resource.average_allocation));
+        }
+
+        for entity_set in self.entity_sets.iter() {
+            summary.push((format!("{} - Average size", entity_set.name), entity_set.average_size));
+            summary.push((format!("{} - Average time in set", entity_set.name), entity_set.average_time_in_set));
+            summary.push((format!("{} - Max time in set", entity_set.name), entity_set.max_time_in_set));
+        }
+
+        for matcher in self.matches.iter() {
+            summary.push((format!("{} - Matches", matcher.name), matcher.matches as f64));
+            summary.push((format!("{} - Average wait time", matcher.name), matcher.average_wait_time));
+        }
+
+        for entity_type in self.entity_types.iter() {
+            let name = &entity_type.entity_type;
+            summary.push((format!("{} - Average cycle time", name), entity_type.average_cycle_time));
+            summary.push((format!("{} - Average waiting time", name), entity_type.average_waiting_time));
+            summary.push((format!("{} - Disposed", name), entity_type.disposed as f64));
+            summary.push((format!("{} - Average work in process", name), entity_type.average_work_in_process));
+            summary.push((format!("{} - Average time in system", name), entity_type.average_time_in_system));
+        }
+
+        for statistic in self.statistics.iter() {
+            summary.push((statistic.name.clone(), statistic.mean));
+        }
+
+        summary
+    }
+
+    /// `section.name.metric` and value of every metric, e.g. as the columns of a time series.
+    /// Dots and backslashes in names are escaped with a backslash.
+    fn columns(&self) -> Vec<(String, f64)> {
+        self.items()
+            .into_iter()
+            .flat_map(|(section, name, metrics)| {
+                metrics
+                    .into_iter()
+                    .map(move |(metric, value)| (format!("{}.{}.{}", section, column_name(name), metric), value))
+            })
+            .collect()
+    }
+
+    /// Undefined statistics, e.g. the average time in a set nothing left, are written as `null`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("reports only hold strings and numbers")
+    }
+
+    /// One row per metric: `section,name,metric,value`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("section,name,metric,value\n");
+        for (section, name, metrics) in self.items() {
+            for (metric, value) in metrics {
+                writeln!(csv, "{},{},{},{}", section, csv_field(name), metric, csv_number(value)).unwrap();
+            }
+        }
+
+        csv
+    }
+}
+
+/// Reports taken at a fixed interval during a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TimeSeries {
+    pub reports: Vec<SimulationReport>,
+}
+
+impl TimeSeries {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.reports).expect("reports only hold strings and numbers")
+    }
+
+    /// One row per report, with a `section.name.metric` column per metric. Dots and
+    /// backslashes in names are escaped as `\.` and `\\`. Items created during the run are
+    /// left empty in earlier rows.
+    pub fn to_csv(&self) -> String {
+        let mut header: Vec<String> = vec![];
+        let rows: Vec<Vec<(String, f64)>> = self.reports.iter().map(|report| report.columns()).collect();
+        for row in rows.iter() {
+            for (column, _) in row {
+                if !header.contains(column) {
+                    header.push(column.clone());
+                }
+            }
+        }
+
+        let mut csv = String::from("time,timestamp");
+        for column in header.iter() {
+            write!(csv, ",{}", csv_field(column)).unwrap();
+        }
+        csv.push('\n');
+
+        for (report, row) in self.reports.iter().zip(rows) {
+            write!(csv, "{},{}", csv_number(report.time), csv_field(&report.timestamp)).unwrap();
+            for column in header.iter() {
+                match row.iter().find(|(known, _)| known == column) {
+                    Some((_, value)) => write!(csv, ",{}", csv_number(*value)).unwrap(),
+                    None => csv.push(','),
+                }
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Undefined statistics, e.g. the average time in a set nothing left, are left empty
+fn csv_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        String::new()
+    }
+}
+
+// Keeps the dots of `section.name.metric` columns unambiguous
+fn column_name(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('.', "\\.")
+}
@@ -3,6 +3,9 @@ use std::cell::RefCell;
 
 use anyhow::Result;
 
+use super::history::HistoryKind;
+use super::Entity;
+
 #[derive(Debug, Default)]
 pub struct ResourceInner(pub RefCell<i32>);
 
@@ -19,9 +22,26 @@ pub trait Resource: Downcast + std::fmt::Debug {
 
     fn update_analytics(&self);
 
-    fn allocation_rate(&self) -> f32;
+    fn allocation_rate(&self) -> f64;
+
+    fn average_allocation(&self) -> f64;
+
+    /// Forget collected statistics, keeping current allocations
+    fn reset_statistics(&self);
 
-    fn average_allocation(&self) -> f32;
+    /// Allocate on behalf of an entity, recording it in the entity's history
+    fn allocate_for(&self, quantity: i32, entity: &mut dyn Entity) -> Result<()> {
+        self.allocate(quantity)?;
+        entity.record(HistoryKind::Seized(self.name().to_string(), quantity));
+        Ok(())
+    }
+
+    /// Release on behalf of an entity, recording it in the entity's history
+    fn release_for(&self, quantity: i32, entity: &mut dyn Entity) -> Result<()> {
+        self.release(quantity)?;
+        entity.record(HistoryKind::Released(self.name().to_string(), quantity));
+        Ok(())
+    }
 }
 
 impl_downcast!(Resource);
@@ -33,7 +53,8 @@ macro_rules! ResourceWrapper {
         $vis struct $name {
             name: String,
             id: uuid::Uuid,
-            allocation_time: std::cell::RefCell<f32>,
+            allocation_time: std::cell::RefCell<f64>,
+            statistics_start: std::cell::RefCell<f64>,
             quantity: i32,
             times_allocated: std::cell::RefCell<u32>,
             tokens: sdm_engine::sdm::resource::ResourceInner,
@@ -47,6 +68,7 @@ macro_rules! ResourceWrapper {
                 if quantity <= *self.tokens.0.borrow() {
                     *self.tokens.0.borrow_mut() -= quantity;
                     *self.times_allocated.borrow_mut() += 1;
+                    sdm_engine::sdm::Scheduler::notify(|observer| observer.on_resource_allocated(&self.name, quantity));
                     Ok(())
                 } else {
                     Err(anyhow::anyhow!("Not enough resources to allocate."))
@@ -56,6 +78,7 @@ macro_rules! ResourceWrapper {
             fn release(&self, quantity: i32) -> anyhow::Result<()> {
                 if  *self.tokens.0.borrow() + quantity <= self.quantity  {
                     *self.tokens.0.borrow_mut() += quantity;
+                    sdm_engine::sdm::Scheduler::notify(|observer| observer.on_resource_released(&self.name, quantity));
                     Ok(())
                 } else {
                     Err(anyhow::anyhow!("Releasing too many resources. Have {}", *self.tokens.0.borrow()))
@@ -81,12 +104,30 @@ macro_rules! ResourceWrapper {
                 }
             }
 
-            fn allocation_rate(&self) -> f32 {
-                *self.allocation_time.borrow() / sdm_engine::sdm::Scheduler::time()
+            /// 0 until time passes, e.g. right after a reset
+            fn allocation_rate(&self) -> f64 {
+                let elapsed = sdm_engine::sdm::Scheduler::time() - *self.statistics_start.borrow();
+                if elapsed <= 0.0 {
+                    return 0.0;
+                }
+
+                *self.allocation_time.borrow() / elapsed
+            }
+
+            /// 0 until time passes, e.g. right after a reset
+            fn average_allocation(&self) -> f64 {
+                let elapsed = sdm_engine::sdm::Scheduler::time() - *self.statistics_start.borrow();
+                if elapsed <= 0.0 {
+                    return 0.0;
+                }
+
+                *self.times_allocated.borrow() as f64 / elapsed
             }
 
-            fn average_allocation(&self) -> f32 {
-                *self.times_allocated.borrow() as f32 / sdm_engine::sdm::Scheduler::time()
+            fn reset_statistics(&self) {
+                *self.allocation_time.borrow_mut() = 0.0;
+                *self.times_allocated.borrow_mut() = 0;
+                *self.statistics_start.borrow_mut() = sdm_engine::sdm::Scheduler::time();
             }
         }
 
@@ -95,7 +136,8 @@ macro_rules! ResourceWrapper {
                 Self {
                     name: name.to_string(),
                     id: uuid::Uuid::new_v4(),
-                    allocation_time: std::cell::RefCell::new(0f32),
+                    allocation_time: std::cell::RefCell::new(0f64),
+                    statistics_start: std::cell::RefCell::new(0f64),
                     quantity: quantity,
                     times_allocated: std::cell::RefCell::new(0u32),
                     tokens: sdm_engine::sdm::resource::ResourceInner(std::cell::RefCell::new(quantity)),
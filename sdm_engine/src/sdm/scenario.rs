@@ -0,0 +1,361 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::thread;
+
+use super::experiment::{Experiment, ExperimentResults, Summary};
+use super::Scheduler;
+
+/// Named values of the parameters of a model, e.g. the number of cooks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Parameters {
+    values: Vec<(String, f64)>,
+}
+
+impl Parameters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str, value: f64) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Sets `name`, replacing its previous value if any
+    pub fn set(&mut self, name: &str, value: f64) {
+        match self.values.iter_mut().find(|(known, _)| known == name) {
+            Some((_, known_value)) => *known_value = value,
+            None => self.values.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<f64> {
+        self.values
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| anyhow!("Missing parameter \"{}\"", name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.values.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+impl fmt::Display for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+        write!(f, "{}", values.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Minimize,
+    Maximize,
+}
+
+/// Replications of every scenario, paired by replication number
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    scenarios: Vec<(String, ExperimentResults)>,
+    confidence: f64,
+}
+
+impl Comparison {
+    pub fn results(&self, scenario: &str) -> Option<&ExperimentResults> {
+        self.scenarios
+            .iter()
+            .find(|(name, _)| name == scenario)
+            .map(|(_, results)| results)
+    }
+
+    /// Confidence interval of the mean difference `a - b` of a statistic
+    pub fn paired_t(&self, statistic: &str, a: &str, b: &str) -> Result<Summary> {
+        self.paired_t_at(statistic, a, b, self.confidence)
+    }
+
+    fn paired_t_at(&self, statistic: &str, a: &str, b: &str, confidence: f64) -> Result<Summary> {
+        let values = |scenario: &str| {
+            let results = self
+                .results(scenario)
+                .ok_or_else(|| anyhow!("No scenario named \"{}\"", scenario))?;
+
+            let values = results
+                .values(statistic)
+                .ok_or_else(|| anyhow!("Scenario \"{}\" has no statistic named \"{}\"", scenario, statistic))?;
+
+            // Missing replications are NaN, which would silently poison every difference
+            if values.iter().any(|value| value.is_nan()) {
+                return Err(anyhow!(
+                    "Statistic \"{}\" is missing from some replications of scenario \"{}\"",
+                    statistic,
+                    scenario
+                ));
+            }
+
+            Ok(values)
+        };
+
+        let differences: Vec<f64> = values(a)?.iter().zip(values(b)?).map(|(x, y)| x - y).collect();
+        Ok(Summary::new(&format!("{} - {}", a, b), &differences, confidence))
+    }
+
+    /// Every pair of scenarios, with Bonferroni-corrected intervals that hold simultaneously
+    /// at the overall confidence level
+    pub fn pairwise(&self, statistic: &str) -> Result<Vec<Summary>> {
+        let k = self.scenarios.len();
+        let confidence = 1.0 - (1.0 - self.confidence) / (k * (k - 1) / 2) as f64;
+
+        let mut intervals = vec![];
+        for (i, (a, _)) in self.scenarios.iter().enumerate() {
+            for (b, _) in self.scenarios.iter().skip(i + 1) {
+                intervals.push(self.paired_t_at(statistic, a, b, confidence)?);
+            }
+        }
+
+        Ok(intervals)
+    }
+
+    /// Every other scenario minus `baseline`, with Bonferroni-corrected intervals
+    pub fn against(&self, statistic: &str, baseline: &str) -> Result<Vec<Summary>> {
+        if self.results(baseline).is_none() {
+            return Err(anyhow!("No scenario named \"{}\"", baseline));
+        }
+
+        let confidence = 1.0 - (1.0 - self.confidence) / (self.scenarios.len() - 1) as f64;
+
+        self.scenarios
+            .iter()
+            .filter(|(name, _)| name != baseline)
+            .map(|(name, _)| self.paired_t_at(statistic, name, baseline, confidence))
+            .collect()
+    }
+
+    pub fn print(&self, statistic: &str) -> Result<()> {
+        println!("+++++++++++++++ {} +++++++++++++++", statistic);
+        for (name, results) in self.scenarios.iter() {
+            if let Some(summary) = results.summary(statistic) {
+                println!("- {}: {:.4} ± {:.4}", name, summary.mean, summary.half_width);
+            }
+        }
+
+        println!("Differences (Bonferroni):");
+        for interval in self.pairwise(statistic)? {
+            let verdict = if interval.low() > 0.0 || interval.high() < 0.0 {
+                "significant"
+            } else {
+                "not significant"
+            };
+            println!("- {}, {}", interval, verdict);
+        }
+        println!("+++++++++++++++++++++++++++++++++++++++++++");
+
+        Ok(())
+    }
+}
+
+/// Outcome of the KN ranking-and-selection procedure
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub best: String,
+    pub survivors: Vec<String>, // More than `best` only if `max_replications` was reached
+    pub eliminated: Vec<(String, usize)>, // Scenario and the replications it took to eliminate it
+    pub replications: usize,
+}
+
+/// Variants of a parameterised model, replicated with common random numbers: replication `i`
+/// of every scenario uses the same seed, so differences come from the parameters, not the noise
+pub struct Scenarios<F> {
+    build: F,
+    run_length: f64,
+    seed: u64,
+    confidence: f64,
+    threads: usize,
+    scenarios: Vec<(String, Parameters)>,
+}
+
+impl<F: Fn(&mut Scheduler, &Parameters) -> Result<()> + Sync> Scenarios<F> {
+    /// `build` sets up the model for the given parameters on a fresh scheduler
+    pub fn new(run_length: f64, build: F) -> Self {
+        Self {
+            build,
+            run_length,
+            seed: 0,
+            confidence: 0.95,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            scenarios: vec![],
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Overall confidence level of comparisons and of the selection
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_scenario(mut self, name: &str, parameters: Parameters) -> Self {
+        self.scenarios.push((name.to_string(), parameters));
+        self
+    }
+
+    fn experiment<'a>(
+        &'a self,
+        parameters: &'a Parameters,
+    ) -> Experiment<impl Fn(&mut Scheduler) -> Result<()> + Sync + 'a> {
+        Experiment::new(self.run_length, move |scheduler| (self.build)(scheduler, parameters))
+            .with_seed(self.seed)
+            .with_confidence(self.confidence)
+            .with_threads(self.threads)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.scenarios.len() < 2 {
+            return Err(anyhow!("Comparisons require at least 2 scenarios"));
+        }
+
+        for (i, (name, _)) in self.scenarios.iter().enumerate() {
+            if self.scenarios.iter().skip(i + 1).any(|(other, _)| other == name) {
+                return Err(anyhow!("Scenario \"{}\" is declared twice", name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn run(&self, replications: usize) -> Result<Comparison> {
+        self.check()?;
+
+        let scenarios = self
+            .scenarios
+            .iter()
+            .map(|(name, parameters)| Ok((name.clone(), self.experiment(parameters).run_parallel(replications)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Comparison {
+            scenarios,
+            confidence: self.confidence,
+        })
+    }
+
+    fn observe(&self, parameters: &Parameters, replication: usize, statistic: &str) -> Result<f64> {
+        self.experiment(parameters)
+            .run_replication(replication as u64)?
+            .into_iter()
+            .find(|(name, _)| name == statistic)
+            .map(|(_, value)| value)
+            .ok_or_else(|| anyhow!("The model has no statistic named \"{}\"", statistic))
+    }
+
+    /// Kim & Nelson's fully sequential procedure: after `initial` replications of every scenario,
+    /// keeps adding one replication to the survivors and screening out the ones that are
+    /// clearly worse. With the overall confidence, the best scenario is selected if no other
+    /// is within `indifference` of it.
+    pub fn select_best(
+        &self,
+        statistic: &str,
+        goal: Goal,
+        indifference: f64,
+        initial: usize,
+        max_replications: usize,
+    ) -> Result<Selection> {
+        self.check()?;
+        if indifference <= 0.0 {
+            return Err(anyhow!("Indifference zone must be positive"));
+        }
+        if initial < 2 {
+            return Err(anyhow!("Selection requires at least 2 initial replications"));
+        }
+
+        // Screening is written for larger-is-better
+        let sign = match goal {
+            Goal::Minimize => -1.0,
+            Goal::Maximize => 1.0,
+        };
+
+        let mut observations = vec![];
+        for (_, parameters) in self.scenarios.iter() {
+            let results = self.experiment(parameters).run_parallel(initial)?;
+            match results.values(statistic) {
+                Some(values) if values.iter().all(|value| value.is_finite()) => {
+                    observations.push(values.iter().map(|value| sign * value).collect::<Vec<f64>>())
+                }
+                _ => return Err(anyhow!("Statistic \"{}\" is missing from some replications", statistic)),
+            }
+        }
+
+        let k = self.scenarios.len();
+        let n0 = initial as f64;
+        let alpha = 1.0 - self.confidence;
+        let eta = 0.5 * ((2.0 * alpha / (k - 1) as f64).powf(-2.0 / (n0 - 1.0)) - 1.0);
+        let h2 = 2.0 * eta * (n0 - 1.0);
+
+        // Variances of the pairwise differences over the initial replications
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+        let mut variances = vec![vec![0.0; k]; k];
+        for i in 0..k {
+            for l in 0..i {
+                let difference = mean(&observations[i]) - mean(&observations[l]);
+                let variance = (0..initial)
+                    .map(|j| (observations[i][j] - observations[l][j] - difference).powi(2))
+                    .sum::<f64>()
+                    / (n0 - 1.0);
+                variances[i][l] = variance;
+                variances[l][i] = variance;
+            }
+        }
+
+        let mut alive: Vec<usize> = (0..k).collect();
+        let mut eliminated = vec![];
+        let mut r = initial;
+        loop {
+            // Survivors have exactly `r` observations
+            let means: Vec<f64> = observations.iter().map(|values| mean(values)).collect();
+            let slack = |i: usize, l: usize| {
+                let excess = h2 * variances[i][l] / indifference.powi(2) - r as f64;
+                f64::max(0.0, indifference / (2.0 * r as f64) * excess)
+            };
+
+            let survivors: Vec<usize> = alive
+                .iter()
+                .copied()
+                .filter(|&i| alive.iter().all(|&l| l == i || means[i] >= means[l] - slack(i, l)))
+                .collect();
+            for i in alive.iter().filter(|i| !survivors.contains(i)) {
+                eliminated.push((self.scenarios[*i].0.clone(), r));
+            }
+            alive = survivors;
+
+            if alive.len() == 1 || r >= max_replications {
+                break;
+            }
+
+            for &i in alive.iter() {
+                observations[i].push(sign * self.observe(&self.scenarios[i].1, r, statistic)?);
+            }
+            r += 1;
+        }
+
+        let best = *alive
+            .iter()
+            .max_by(|&&a, &&b| mean(&observations[a]).total_cmp(&mean(&observations[b])))
+            .unwrap();
+
+        Ok(Selection {
+            best: self.scenarios[best].0.clone(),
+            survivors: alive.iter().map(|&i| self.scenarios[i].0.clone()).collect(),
+            eliminated,
+            replications: r,
+        })
+    }
+}
@@ -1,34 +1,59 @@
-use super::{EntitySet, Event, Process, Resource};
+use super::disposal::DisposalStatistics;
+use super::history::HistoryStatistics;
+use super::observer::{ConsoleLogger, Observer};
+use super::report::{
+    EntitySetReport, EntityTypeReport, MatchReport, ResourceReport, SimulationReport, SourceReport,
+    StatisticReport, TimeSeries,
+};
+use super::source::{Source, SourceArrival};
+use super::statistics::Statistic;
+use super::time::{Calendar, DateTime, TimeUnit};
+use super::{Entity, EntitySet, Event, Match, Process, Resource};
 use anyhow::{anyhow, Result};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use std::sync::atomic::{AtomicPtr, Ordering};
 use uuid::Uuid;
 
-pub const ANALYTICS_REFRESH: f32 = 1.0; // Update analytics every second
-pub const ANALYTICS_PRINT_TIME: f32 = 10.0; // Print analytics every 10 seconds
+pub const ANALYTICS_REFRESH: f64 = 1.0; // Update analytics every time unit
+pub const ANALYTICS_PRINT_TIME: f64 = 10.0; // Print analytics every 10 time units
 
-static SCHEDULER_INSTANCE: AtomicPtr<Scheduler> = AtomicPtr::new(std::ptr::null_mut());
+thread_local! {
+    // One scheduler per thread, so independent models can run concurrently
+    static SCHEDULER_INSTANCE: Cell<*mut Scheduler> = const { Cell::new(std::ptr::null_mut()) };
+}
 
 pub struct Scheduler {
-    time: f32,                                                   // Simulation time
-    last_analytics: RefCell<f32>,                                // Time last analytics was run
-    event_queue: RefCell<Vec<(f32, Box<dyn Event>)>>,            // Future events
-    process_queue: RefCell<Vec<(f32, Box<dyn Process>)>>,        // Future processes
-    running_processes: RefCell<HashMap<Uuid, Box<dyn Process>>>, // Process to run every cicle
-    process_finish_events: RefCell<Vec<(f32, Uuid)>>,            // Processes with on_end to run
+    time: f64,                                                   // Simulation time
+    time_unit: TimeUnit,                                         // Unit of simulation time
+    calendar_start: Option<DateTime>,                            // Date and time at time zero
+    quiet: bool,                                                 // Suppress console output
+    last_analytics: RefCell<f64>,                                // Time last analytics was run
+    warm_up: RefCell<Option<f64>>,                               // Pending end of the warm-up period
+    report_interval: Option<f64>,                                // Time between time series reports
+    time_series: RefCell<TimeSeries>,                            // Reports taken during the run
+    event_queue: RefCell<Vec<(f64, Box<dyn Event>)>>,            // Future events
+    process_queue: RefCell<Vec<(f64, Box<dyn Process>)>>,        // Future processes
+    running_processes: RefCell<Vec<Box<dyn Process>>>,           // Processes to run every cycle, in activation order
+    process_finish_events: RefCell<Vec<(f64, Uuid)>>,            // Processes with on_end to run
     entity_sets: RefCell<Vec<Rc<dyn EntitySet>>>,                // Managed EntitySets
     resources: RefCell<Vec<Rc<dyn Resource>>>,                   // Managed Resources
+    matches: RefCell<Vec<Rc<dyn Match>>>,                        // Managed Matches
+    sources: RefCell<Vec<Rc<Source>>>,                           // Managed entity Sources
+    statistics: RefCell<Vec<Rc<dyn Statistic>>>,                 // Managed custom statistics
+    observers: RefCell<Vec<Rc<dyn Observer>>>,                   // Notified of every change
+    console: Rc<ConsoleLogger>,                                  // Notified unless quiet
+    histories: RefCell<HistoryStatistics>,                       // Collected entity histories
+    disposals: RefCell<DisposalStatistics>,                      // Created and disposed entities
 }
 
 impl Drop for Scheduler {
     fn drop(&mut self) {
-        unsafe {
-            std::ptr::drop_in_place(self);
-        }
-
-        SCHEDULER_INSTANCE.store(std::ptr::null_mut(), Ordering::Relaxed);
+        // Only forget the global instance if it is this one
+        SCHEDULER_INSTANCE.with(|instance| {
+            if std::ptr::eq(instance.get(), self) {
+                instance.set(std::ptr::null_mut());
+            }
+        });
     }
 }
 
@@ -36,17 +61,30 @@ impl Scheduler {
     pub fn new() -> Result<&'static mut Self> {
         if !Self::instanciated() {
             let instance = Box::new(Self {
-                time: 0f32,
-                last_analytics: RefCell::new(0f32),
+                time: 0f64,
+                time_unit: TimeUnit::Seconds,
+                calendar_start: None,
+                quiet: false,
+                last_analytics: RefCell::new(0f64),
+                warm_up: RefCell::new(None),
+                report_interval: None,
+                time_series: RefCell::new(TimeSeries::default()),
                 event_queue: RefCell::new(vec![]),
                 process_queue: RefCell::new(vec![]),
-                running_processes: RefCell::new(HashMap::new()),
+                running_processes: RefCell::new(vec![]),
                 process_finish_events: RefCell::new(vec![]),
                 entity_sets: RefCell::new(vec![]),
                 resources: RefCell::new(vec![]),
+                matches: RefCell::new(vec![]),
+                sources: RefCell::new(vec![]),
+                statistics: RefCell::new(vec![]),
+                observers: RefCell::new(vec![]),
+                console: Rc::new(ConsoleLogger),
+                histories: RefCell::new(HistoryStatistics::new()),
+                disposals: RefCell::new(DisposalStatistics::new()),
             });
 
-            SCHEDULER_INSTANCE.store(Box::into_raw(instance), Ordering::Relaxed);
+            SCHEDULER_INSTANCE.with(|current| current.set(Box::into_raw(instance)));
 
             Self::instance()
         } else {
@@ -57,23 +95,93 @@ impl Scheduler {
         }
     }
 
-    pub fn time() -> f32 {
+    /// Drop the scheduler with every event, process and statistic it manages, so that
+    /// a new one can be created, e.g. for the next replication. Crate-private, as any reference
+    /// returned by `new` or `instance` dangles afterwards.
+    pub(crate) fn destroy() {
+        let instance = SCHEDULER_INSTANCE.with(|current| current.replace(std::ptr::null_mut()));
+        if !instance.is_null() {
+            unsafe {
+                drop(Box::from_raw(instance));
+            }
+        }
+    }
+
+    pub fn time() -> f64 {
         unsafe {
             SCHEDULER_INSTANCE
-                .load(Ordering::Relaxed)
+                .with(Cell::get)
                 .as_ref()
                 .expect("No scheduler has been instanciated")
                 .time
         }
     }
 
-    pub fn set_time(&mut self, time: f32) {
+    pub fn set_time(&mut self, time: f64) {
+        let from = self.time;
         self.time = time;
+
+        if time != from {
+            Self::notify(|observer| observer.on_clock_advanced(from, time));
+        }
+    }
+
+    /// Unit of simulation time, seconds unless set otherwise
+    pub fn time_unit() -> TimeUnit {
+        match Self::instance() {
+            Ok(instance) => instance.time_unit,
+            Err(_) => TimeUnit::default(),
+        }
+    }
+
+    pub fn set_time_unit(&mut self, unit: TimeUnit) {
+        self.time_unit = unit;
+    }
+
+    /// Suppress step, match and analytics output, e.g. when running replications
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    /// Take a `report` every `interval` time units, rounded up to the analytics refresh
+    pub fn set_report_interval(&mut self, interval: f64) {
+        self.report_interval = Some(interval.max(ANALYTICS_REFRESH));
+    }
+
+    /// Reports taken so far at the report interval
+    pub fn time_series(&self) -> TimeSeries {
+        self.time_series.borrow().clone()
+    }
+
+    /// Map simulation time zero to a wall-clock date and time
+    pub fn set_calendar(&mut self, start: DateTime) {
+        self.calendar_start = Some(start);
+    }
+
+    /// Calendar of the simulation, starting on 1970-01-01 00:00:00 unless set otherwise
+    pub fn calendar() -> Calendar {
+        match Self::instance() {
+            Ok(instance) => Calendar::new(instance.calendar_start.unwrap_or_default(), instance.time_unit),
+            Err(_) => Calendar::new(DateTime::default(), TimeUnit::default()),
+        }
+    }
+
+    /// Wall-clock timestamp of `time` if a calendar was set, the bare time otherwise
+    pub fn format_time(time: f64) -> String {
+        match Self::instance() {
+            Ok(instance) if instance.calendar_start.is_some() => Self::calendar().date_time(time).to_string(),
+            _ => format!("{:.2}", time),
+        }
+    }
+
+    /// Timestamp of the current simulation time
+    pub fn timestamp() -> String {
+        Self::format_time(Self::time())
     }
 
     pub fn instance() -> Result<&'static mut Self> {
         unsafe {
-            if let Some(instance) = SCHEDULER_INSTANCE.load(Ordering::Relaxed).as_mut() {
+            if let Some(instance) = SCHEDULER_INSTANCE.with(Cell::get).as_mut() {
                 Ok(instance)
             } else {
                 Err(anyhow!("No scheduler has been instanciated"))
@@ -82,7 +190,38 @@ impl Scheduler {
     }
 
     pub fn instanciated() -> bool {
-        !SCHEDULER_INSTANCE.load(Ordering::Relaxed).is_null()
+        !SCHEDULER_INSTANCE.with(Cell::get).is_null()
+    }
+
+    /// Plug in an observer, notified after the ones added before it
+    pub fn add_observer<O: Observer + 'static>(&self, observer: O) -> Rc<O> {
+        let observer = Rc::new(observer);
+        self.observers.borrow_mut().push(observer.clone());
+
+        observer
+    }
+
+    /// Unplug an observer added with `add_observer`, returning whether it was plugged in
+    pub fn remove_observer<O: Observer + 'static>(&self, observer: &Rc<O>) -> bool {
+        let mut observers = self.observers.borrow_mut();
+        let count = observers.len();
+        observers.retain(|known| !std::ptr::eq(Rc::as_ptr(known) as *const u8, Rc::as_ptr(observer) as *const u8));
+
+        observers.len() < count
+    }
+
+    /// Call `hook` on every observer of the current scheduler, if any
+    pub fn notify(hook: impl Fn(&dyn Observer)) {
+        if let Ok(instance) = Self::instance() {
+            // Cloned, so hooks may add observers
+            let observers = instance.observers.borrow().clone();
+            if !instance.quiet {
+                hook(instance.console.as_ref());
+            }
+            for observer in observers.iter() {
+                hook(observer.as_ref());
+            }
+        }
     }
 
     fn sort_event_queue(&self) {
@@ -104,10 +243,12 @@ impl Scheduler {
     }
 
     pub fn schedule_now(&self, event: Box<dyn Event>) {
+        Self::notify(|observer| observer.on_event_scheduled(event.as_ref(), Self::time()));
         self.event_queue.borrow_mut().push((Self::time(), event));
     }
 
-    pub fn schedule_in(&self, event: Box<dyn Event>, time_to_event: f32) {
+    pub fn schedule_in(&self, event: Box<dyn Event>, time_to_event: f64) {
+        Self::notify(|observer| observer.on_event_scheduled(event.as_ref(), Self::time() + time_to_event));
         self.event_queue
             .borrow_mut()
             .push((Self::time() + time_to_event, event));
@@ -115,27 +256,47 @@ impl Scheduler {
         self.sort_event_queue();
     }
 
-    pub fn schedule_at(&self, event: Box<dyn Event>, schedule_time: f32) {
+    pub fn schedule_at(&self, event: Box<dyn Event>, schedule_time: f64) {
+        Self::notify(|observer| observer.on_event_scheduled(event.as_ref(), schedule_time));
         self.event_queue.borrow_mut().push((schedule_time, event));
 
         self.sort_event_queue();
     }
 
+    /// Remove every future event for which `predicate(event, time)` holds from the FEL,
+    /// returning how many there were
+    pub fn cancel_events(&self, predicate: impl Fn(&dyn Event, f64) -> bool) -> usize {
+        let (cancelled, kept) = self
+            .event_queue
+            .take()
+            .into_iter()
+            .partition::<Vec<_>, _>(|(time, event)| predicate(event.as_ref(), *time));
+        *self.event_queue.borrow_mut() = kept;
+
+        for (time, event) in cancelled.iter() {
+            Self::notify(|observer| observer.on_event_cancelled(event.as_ref(), *time));
+        }
+
+        cancelled.len()
+    }
+
     pub fn start_process_now(&self, process: Box<dyn Process>) {
-        self.running_processes
-            .borrow_mut()
-            .insert(process.pid(), process);
+        Self::notify(|observer| observer.on_process_activated(process.as_ref(), Self::time()));
+        self.running_processes.borrow_mut().push(process);
     }
 
-    pub fn start_process_in(&self, process: Box<dyn Process>, time_to_process: f32) {
+    pub fn start_process_in(&self, process: Box<dyn Process>, time_to_process: f64) {
+        let schedule_time = Self::time() + time_to_process;
+        Self::notify(|observer| observer.on_process_scheduled(process.as_ref(), schedule_time));
         self.process_queue
             .borrow_mut()
-            .push((Self::time() + time_to_process, process));
+            .push((schedule_time, process));
 
         self.sort_process_queue()
     }
 
-    pub fn start_process_at(&self, process: Box<dyn Process>, schedule_time: f32) {
+    pub fn start_process_at(&self, process: Box<dyn Process>, schedule_time: f64) {
+        Self::notify(|observer| observer.on_process_scheduled(process.as_ref(), schedule_time));
         self.process_queue
             .borrow_mut()
             .push((schedule_time, process));
@@ -155,23 +316,130 @@ impl Scheduler {
         self.resources.borrow().last().unwrap().clone()
     }
 
-    /// Check for processes that may be scheduled to start and start them
-    fn check_process_queue(&self, future_time: &f32) {
+    pub fn manage_match(&self, matcher: impl Match + 'static) -> Rc<dyn Match> {
+        self.matches.borrow_mut().push(Rc::new(matcher));
+
+        self.matches.borrow().last().unwrap().clone()
+    }
+
+    /// Schedule the first arrival of a `Source`, which keeps scheduling the next ones
+    pub fn manage_source(&self, source: Source) -> Rc<Source> {
+        let source = Rc::new(source);
+        self.sources.borrow_mut().push(source.clone());
+
+        if let Some(first_arrival) = source.first_arrival() {
+            self.schedule_at(
+                Box::new(SourceArrival {
+                    source: source.clone(),
+                }),
+                first_arrival,
+            );
+        }
+
+        source
+    }
+
+    /// Custom statistic reported and reset along with the built-in ones
+    pub fn manage_statistic<S: Statistic + 'static>(&self, statistic: S) -> Rc<S> {
+        let statistic = Rc::new(statistic);
+        self.statistics.borrow_mut().push(statistic.clone());
+
+        statistic
+    }
+
+    /// Statistics are reset once simulation time reaches `length`, keeping the model state
+    pub fn set_warm_up(&self, length: f64) {
+        *self.warm_up.borrow_mut() = Some(length);
+    }
+
+    /// Clear every collected statistic, e.g. at the end of the warm-up period.
+    /// Entities, queues and allocations are left untouched.
+    pub fn reset_statistics(&self) {
+        for entity_set in self.entity_sets.borrow().iter() {
+            entity_set.reset_statistics();
+        }
+
+        for resource in self.resources.borrow().iter() {
+            resource.reset_statistics();
+        }
+
+        for matcher in self.matches.borrow().iter() {
+            matcher.reset_statistics();
+        }
+
+        for statistic in self.statistics.borrow().iter() {
+            statistic.reset();
+        }
+
+        self.histories.borrow_mut().reset();
+        self.disposals.borrow_mut().reset();
+    }
+
+    /// Add an entity's history to the statistics of its type
+    pub fn collect_history(&self, entity: &dyn Entity) {
+        self.histories.borrow_mut().collect(entity, self.time);
+    }
+
+    pub fn history_statistics(&self) -> std::cell::Ref<'_, HistoryStatistics> {
+        self.histories.borrow()
+    }
+
+    /// Count a newly created entity towards the work-in-process of its type. Entities created
+    /// without a scheduler are not counted, and cannot be disposed of.
+    pub fn register_creation(entity_type: &str) {
+        if let Ok(instance) = Self::instance() {
+            instance.disposals.borrow_mut().created(entity_type);
+        }
+    }
+
+    /// Remove an entity from the system, recording its time in system
+    pub fn dispose(&self, entity: Box<dyn Entity>) -> Result<()> {
+        self.disposals.borrow_mut().dispose(entity.as_ref(), self.time)?;
+        self.collect_history(entity.as_ref());
+        Ok(())
+    }
+
+    pub fn disposal_statistics(&self) -> std::cell::Ref<'_, DisposalStatistics> {
+        self.disposals.borrow()
+    }
+
+    /// Release every group of entities that can be matched
+    fn check_matches(&self) {
+        let matches = self.matches.borrow().clone();
+
+        // Every match takes an entity from the first set of its matcher, so entities present now
+        // bound the matches of this step. Entities pushed back by `on_match` wait for the next one.
+        let mut remaining: usize = matches
+            .iter()
+            .filter_map(|matcher| matcher.sets().first().map(|(set, _)| set.size()))
+            .sum();
+
         loop {
-            if let Some((schedule_time, _)) = self.process_queue.borrow().last() {
-                if schedule_time <= future_time {
-                    let proc = self.process_queue.borrow_mut().pop().unwrap().1;
-                    println!("{} - Starting process \"{}\"", schedule_time, proc.name());
-                    self.running_processes.borrow_mut().insert(proc.pid(), proc);
-                } else {
-                    break;
+            let mut matched = false;
+            for matcher in matches.iter() {
+                while remaining > 0 && matcher.try_match() {
+                    Self::notify(|observer| observer.on_entities_matched(matcher.name()));
+                    remaining -= 1;
+                    matched = true;
                 }
-            } else {
+            }
+
+            if !matched || remaining == 0 {
                 break;
             }
         }
     }
 
+    /// Check for processes that may be scheduled to start and start them
+    fn check_process_queue(&self, future_time: &f64) {
+        // The queue is only borrowed to peek, as observers may inspect it
+        while matches!(self.process_queue.borrow().last(), Some((schedule_time, _)) if schedule_time <= future_time) {
+            let (schedule_time, proc) = self.process_queue.borrow_mut().pop().unwrap();
+            Self::notify(|observer| observer.on_process_activated(proc.as_ref(), schedule_time));
+            self.running_processes.borrow_mut().push(proc);
+        }
+    }
+
     fn event_step(&self) {
         // Get first item from FEL
         let event = self.event_queue.borrow_mut().pop();
@@ -187,16 +455,27 @@ impl Scheduler {
 
             // Dispatch event according to listener
             event.1.execute();
+            Self::notify(|observer| observer.on_event_executed(event.1.as_ref()));
 
             // Execute processes and schedule on_end callbacks
-            for (pid, proc) in self.running_processes.borrow_mut().iter_mut() {
+            let mut started = vec![];
+            for proc in self.running_processes.borrow_mut().iter_mut() {
                 let duration = proc.start();
+                started.push((proc.pid(), duration));
                 self.process_finish_events
                     .borrow_mut()
-                    .push((self.time + duration, pid.clone()));
+                    .push((self.time + duration, proc.pid()));
 
                 self.sort_process_finish_event_queue();
             }
+
+            // Observers are notified once the processes are no longer mutably borrowed
+            for (proc_id, duration) in started {
+                let running_processes = self.running_processes.borrow();
+                if let Some(proc) = running_processes.iter().find(|proc| proc.pid() == proc_id) {
+                    Self::notify(|observer| observer.on_process_started(proc.as_ref(), duration));
+                }
+            }
         }
     }
 
@@ -211,8 +490,9 @@ impl Scheduler {
             panic!(
                 "Process '{}' time is in the past ({})! Something has gone terribly wrong!",
                 self.running_processes
-                    .borrow_mut()
-                    .get_mut(&proc_id)
+                    .borrow()
+                    .iter()
+                    .find(|proc| proc.pid() == proc_id)
                     .unwrap()
                     .name(),
                 proc_time
@@ -221,13 +501,115 @@ impl Scheduler {
 
         self.running_processes
             .borrow_mut()
-            .get_mut(&proc_id)
+            .iter_mut()
+            .find(|proc| proc.pid() == proc_id)
             .expect("No process assossiated to PID")
             .end();
+
+        let running_processes = self.running_processes.borrow();
+        if let Some(proc) = running_processes.iter().find(|proc| proc.pid() == proc_id) {
+            Self::notify(|observer| observer.on_process_ended(proc.as_ref()));
+        }
+    }
+
+    /// Every built-in and custom statistic at the current time
+    pub fn report(&self) -> SimulationReport {
+        let histories = self.histories.borrow();
+        let disposals = self.disposals.borrow();
+        let mut entity_types: Vec<&String> = disposals.iter().map(|(entity_type, _)| entity_type).collect();
+        for (entity_type, _) in histories.iter() {
+            if !entity_types.contains(&entity_type) {
+                entity_types.push(entity_type);
+            }
+        }
+        entity_types.sort();
+
+        SimulationReport {
+            time: self.time,
+            timestamp: Self::timestamp(),
+            time_unit: self.time_unit.to_string(),
+            resources: self
+                .resources
+                .borrow()
+                .iter()
+                .map(|resource| ResourceReport {
+                    name: resource.name().to_string(),
+                    quantity: resource.quantity(),
+                    allocated: resource.n_allocated(),
+                    allocation_rate: resource.allocation_rate(),
+                    average_allocation: resource.average_allocation(),
+                })
+                .collect(),
+            entity_sets: self
+                .entity_sets
+                .borrow()
+                .iter()
+                .map(|entity_set| EntitySetReport {
+                    name: entity_set.name().to_string(),
+                    size: entity_set.size(),
+                    average_size: entity_set.average_size(),
+                    average_time_in_set: entity_set.average_time_in_set(),
+                    max_time_in_set: entity_set.max_time_in_set(),
+                })
+                .collect(),
+            matches: self
+                .matches
+                .borrow()
+                .iter()
+                .map(|matcher| MatchReport {
+                    name: matcher.name().to_string(),
+                    matches: matcher.matches(),
+                    average_wait_time: matcher.average_wait_time(),
+                    max_wait_time: matcher.max_wait_time(),
+                })
+                .collect(),
+            sources: self
+                .sources
+                .borrow()
+                .iter()
+                .map(|source| SourceReport {
+                    name: source.name().to_string(),
+                    arrivals: source.arrived(),
+                })
+                .collect(),
+            entity_types: entity_types
+                .into_iter()
+                .map(|entity_type| {
+                    let disposal = disposals.get(entity_type).cloned().unwrap_or_default();
+                    let history = histories.get(entity_type).cloned().unwrap_or_default();
+                    EntityTypeReport {
+                        entity_type: entity_type.clone(),
+                        created: disposal.created,
+                        disposed: disposal.disposed,
+                        work_in_process: disposal.work_in_process(),
+                        average_work_in_process: disposal.average_work_in_process(),
+                        average_time_in_system: disposal.average_system_time(),
+                        max_time_in_system: disposal.max_system_time,
+                        histories: history.count,
+                        average_cycle_time: history.average_cycle_time(),
+                        average_value_added_time: history.average_value_added_time(),
+                        average_waiting_time: history.average_waiting_time(),
+                    }
+                })
+                .collect(),
+            statistics: self
+                .statistics
+                .borrow()
+                .iter()
+                .map(|statistic| StatisticReport {
+                    name: statistic.name().to_string(),
+                    count: statistic.count(),
+                    mean: statistic.mean(),
+                    min: statistic.min(),
+                    max: statistic.max(),
+                })
+                .collect(),
+        }
     }
 
     pub fn print_analytics(&self) {
-        println!("+++++++++++++++ {:3.2} - LOG +++++++++++++++", self.time);
+        println!("+++++++++++++++ {} - LOG +++++++++++++++", Self::timestamp());
+        let unit = self.time_unit;
 
         println!("Resources:");
         for resource in self.resources.borrow().iter() {
@@ -238,37 +620,77 @@ impl Scheduler {
             println!("  - Average allocation: {:.2}", resource.average_allocation());
         }
 
+        println!("Sources:");
+        for source in self.sources.borrow().iter() {
+            println!("- {}:", source.name());
+            println!("  - Arrivals: {}", source.arrived());
+        }
+
         println!("Entity Sets:");
         for entity_set in self.entity_sets.borrow().iter() {
             println!("- {}:", entity_set.name());
             println!("  - Current size: {}", entity_set.size());
             println!("  - Average size: {:.2}", entity_set.average_size());
-            println!("  - Average time in set: {:.2}", entity_set.average_time_in_set());
-            println!("  - Max time in set: {:.2}", entity_set.max_time_in_set());
+            println!("  - Average time in set: {:.2} {}", entity_set.average_time_in_set(), unit);
+            println!("  - Max time in set: {:.2} {}", entity_set.max_time_in_set(), unit);
+        }
+
+        println!("Matches:");
+        for matcher in self.matches.borrow().iter() {
+            println!("- {}:", matcher.name());
+            println!("  - Total matches: {}", matcher.matches());
+            println!("  - Average wait time: {:.2} {}", matcher.average_wait_time(), unit);
+            println!("  - Max wait time: {:.2} {}", matcher.max_wait_time(), unit);
+        }
+
+        println!("Entity histories:");
+        for (entity_type, history) in self.histories.borrow().iter() {
+            println!("- {}:", entity_type);
+            println!("  - Collected: {}", history.count);
+            println!("  - Average cycle time: {:.2} {}", history.average_cycle_time(), unit);
+            println!("  - Average value-added time: {:.2} {}", history.average_value_added_time(), unit);
+            println!("  - Average waiting time: {:.2} {}", history.average_waiting_time(), unit);
+            println!("  - Max cycle time: {:.2} {}", history.max_cycle_time, unit);
+        }
+
+        println!("Disposed entities:");
+        for (entity_type, disposal) in self.disposals.borrow().iter() {
+            println!("- {}:", entity_type);
+            println!("  - Created: {}", disposal.created);
+            println!("  - Disposed: {}", disposal.disposed);
+            println!("  - Work in process: {}", disposal.work_in_process());
+            println!("  - Average work in process: {:.2}", disposal.average_work_in_process());
+            println!("  - Average time in system: {:.2} {}", disposal.average_system_time(), unit);
+            println!("  - Max time in system: {:.2} {}", disposal.max_system_time, unit);
+        }
+
+        println!("Statistics:");
+        for statistic in self.statistics.borrow().iter() {
+            println!("- {}: {:.2}", statistic.name(), statistic.mean());
         }
 
         println!("+++++++++++++++++++++++++++++++++++++++++++");
     }
 
-    /// Simulates one step, returns whether stop condition is met
-    /// A step can be either a process callback or an event from the FEL.
-    /// If both are scheduled to the same time, the process callback takes precedence.
-    pub fn simulate_one_step(&self) -> bool {
-        let proc_time = match self.process_finish_events.borrow().last() {
-            Some(proc_event) => Some(proc_event.0),
-            None => None,
-        };
+    /// Run analytics up to `time`, ending the warm-up period on the way if due
+    fn advance_analytics(&self, time: f64) {
+        let warm_up = *self.warm_up.borrow();
+        if let Some(warm_up) = warm_up {
+            if time >= warm_up {
+                self.run_analytics_until(warm_up);
+                Self::instance().unwrap().set_time(warm_up);
+                self.reset_statistics();
+                *self.warm_up.borrow_mut() = None;
+
+                Self::notify(|observer| observer.on_warm_up_ended());
+            }
+        }
 
-        let event_time = match self.event_queue.borrow().last() {
-            Some(event) => Some(event.0),
-            None => None,
-        };
+        self.run_analytics_until(time);
+    }
 
-        let closest = f32::min(
-            proc_time.unwrap_or(f32::NAN),
-            event_time.unwrap_or(f32::NAN),
-        );
-        while closest >= *self.last_analytics.borrow() + ANALYTICS_REFRESH {
+    fn run_analytics_until(&self, time: f64) {
+        while time >= *self.last_analytics.borrow() + ANALYTICS_REFRESH {
             Self::instance()
                 .unwrap()
                 .set_time(*self.last_analytics.borrow() + ANALYTICS_REFRESH);
@@ -282,13 +704,45 @@ impl Scheduler {
                 resource.update_analytics();
             }
 
+            for statistic in self.statistics.borrow().iter() {
+                statistic.update_analytics();
+            }
+
+            self.disposals.borrow_mut().update_analytics();
+
             *self.last_analytics.borrow_mut() = self.time;
 
-            // Print analytics
-            if self.time % ANALYTICS_PRINT_TIME == 0.0 {
-                Self::instance().unwrap().print_analytics();
+            if let Some(interval) = self.report_interval {
+                let reports = self.time_series.borrow().reports.len();
+                if self.time >= (reports + 1) as f64 * interval {
+                    let report = self.report();
+                    self.time_series.borrow_mut().reports.push(report);
+                }
             }
+
+            Self::notify(|observer| observer.on_analytics_updated());
         }
+    }
+
+    /// Simulates one step, returns whether stop condition is met
+    /// A step can be either a process callback or an event from the FEL.
+    /// If both are scheduled to the same time, the process callback takes precedence.
+    pub fn simulate_one_step(&self) -> bool {
+        let proc_time = match self.process_finish_events.borrow().last() {
+            Some(proc_event) => Some(proc_event.0),
+            None => None,
+        };
+
+        let event_time = match self.event_queue.borrow().last() {
+            Some(event) => Some(event.0),
+            None => None,
+        };
+
+        let closest = f64::min(
+            proc_time.unwrap_or(f64::NAN),
+            event_time.unwrap_or(f64::NAN),
+        );
+        self.advance_analytics(closest);
 
         if let Some(proc_time) = proc_time {
             if let Some(event_time) = event_time {
@@ -308,22 +762,106 @@ impl Scheduler {
             self.event_step();
         }
 
-        self.event_queue.borrow().is_empty() & self.process_finish_events.borrow().is_empty()
+        self.check_matches();
+
+        let events = self.event_queue.borrow().len();
+        let process_callbacks = self.process_finish_events.borrow().len();
+        Self::notify(|observer| observer.on_step_completed(events, process_callbacks));
+
+        events == 0 && process_callbacks == 0
+    }
+
+    /// Time of the next event or process callback, if any
+    pub fn next_time(&self) -> Option<f64> {
+        let proc_time = self.process_finish_events.borrow().last().map(|proc_event| proc_event.0);
+        let event_time = self.event_queue.borrow().last().map(|event| event.0);
+
+        match (proc_time, event_time) {
+            (Some(proc_time), Some(event_time)) => Some(f64::min(proc_time, event_time)),
+            (proc_time, event_time) => proc_time.or(event_time),
+        }
+    }
+
+    /// Future events as `(time, name)`, soonest first
+    pub fn scheduled_events(&self) -> Vec<(f64, String)> {
+        self.event_queue
+            .borrow()
+            .iter()
+            .rev()
+            .map(|(time, event)| (*time, event.name().to_string()))
+            .collect()
+    }
+
+    /// Names of the running processes, sorted
+    pub fn running_processes(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .running_processes
+            .borrow()
+            .iter()
+            .map(|proc| proc.name().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Pending process callbacks as `(time, process name)`, soonest first
+    pub fn scheduled_process_callbacks(&self) -> Vec<(f64, String)> {
+        let running_processes = self.running_processes.borrow();
+        self.process_finish_events
+            .borrow()
+            .iter()
+            .rev()
+            .map(|(time, pid)| {
+                let name = running_processes
+                    .iter()
+                    .find(|proc| proc.pid() == *pid)
+                    .map_or("?", |proc| proc.name());
+                (*time, name.to_string())
+            })
+            .collect()
+    }
+
+    pub fn entity_sets(&self) -> Vec<Rc<dyn EntitySet>> {
+        self.entity_sets.borrow().clone()
+    }
+
+    pub fn resources(&self) -> Vec<Rc<dyn Resource>> {
+        self.resources.borrow().clone()
+    }
+
+    /// Simulates every step up to `end_time` without pausing, then advances the clock to `end_time`.
+    /// Later events stay in the FEL, so the simulation can be resumed.
+    pub fn simulate_until(&self, end_time: f64) {
+        while matches!(self.next_time(), Some(time) if time <= end_time) {
+            self.simulate_one_step();
+        }
+
+        if self.time < end_time {
+            self.advance_analytics(end_time);
+            Self::instance().unwrap().set_time(end_time);
+        }
+    }
+
+    /// Every built-in and custom statistic as `(name, value)`, e.g. at the end of a replication
+    pub fn statistics_summary(&self) -> Vec<(String, f64)> {
+        self.report().summary()
     }
 
     pub fn simulate(&self) {
         loop {
             let stop = self.simulate_one_step();
-
-            println!("--------------------------------------------------------------------------");
-            std::thread::sleep(std::time::Duration::from_secs_f32(0.5));
-            println!(
-                "{:.2} - Step complete. Events in FEL: {}. Scheduled process callbacks: {}",
-                self.time,
-                self.event_queue.borrow().len(),
-                self.process_finish_events.borrow().len()
-            );
-            println!("--------------------------------------------------------------------------");
+            std::thread::sleep(std::time::Duration::from_secs_f64(0.5));
+
+            if !self.quiet {
+                println!("--------------------------------------------------------------------------");
+                println!(
+                    "{} - Step complete. Events in FEL: {}. Scheduled process callbacks: {}",
+                    Self::timestamp(),
+                    self.event_queue.borrow().len(),
+                    self.process_finish_events.borrow().len()
+                );
+                println!("--------------------------------------------------------------------------");
+            }
 
             if stop {
                 break;
@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{
+    DiscreteDistrib, Distrib, Entity, EntitySet, Event, Exponential, Scheduler, Uniform, WeeklySchedule,
+};
+
+/// Arrivals dropped in a row while closed before a `Source` is deemed to never open again
+const MAX_CLOSED_ARRIVALS: u32 = 10_000;
+
+/// Candidates rejected in a row before a `Nonhomogeneous` rate is deemed to have dropped to 0
+const MAX_THINNING_REJECTIONS: u32 = 1_000_000;
+
+/// Time-varying arrival rate, sampled as a Poisson process by thinning
+pub enum ArrivalSchedule {
+    /// `(start time, rate)` pairs sorted by start time, each rate holding until the next start.
+    /// With a period, the schedule repeats itself (e.g. every day).
+    Piecewise {
+        rates: Vec<(f64, f64)>,
+        period: Option<f64>,
+    },
+    /// Non-homogeneous Poisson process. `rate` must never exceed `max_rate`, or arrivals
+    /// happen at `max_rate` instead. Arrivals end once `MAX_THINNING_REJECTIONS` candidates
+    /// are rejected in a row, i.e. when the rate has dropped to 0 for good.
+    Nonhomogeneous { rate: fn(f64) -> f64, max_rate: f64 },
+}
+
+impl ArrivalSchedule {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Piecewise { rates, period } => {
+                if rates.iter().any(|(start, rate)| !start.is_finite() || !rate.is_finite() || *rate < 0.0) {
+                    return Err(anyhow!("Piecewise rates must be finite and non-negative"));
+                }
+                if rates.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                    return Err(anyhow!("Piecewise rates must be sorted by start time"));
+                }
+                if matches!(period, Some(period) if !(period.is_finite() && *period > 0.0)) {
+                    return Err(anyhow!("Piecewise period must be positive"));
+                }
+            }
+            Self::Nonhomogeneous { max_rate, .. } => {
+                if !(max_rate.is_finite() && *max_rate > 0.0) {
+                    return Err(anyhow!("Maximum rate must be positive"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn rate(&self, time: f64) -> f64 {
+        match self {
+            Self::Piecewise { rates, period } => {
+                let time = match period {
+                    Some(period) => time % period,
+                    None => time,
+                };
+
+                rates
+                    .iter()
+                    .take_while(|(start, _)| *start <= time)
+                    .last()
+                    .map(|(_, rate)| *rate)
+                    .unwrap_or(0.0)
+            }
+            Self::Nonhomogeneous { rate, .. } => rate(time),
+        }
+    }
+
+    pub fn max_rate(&self) -> f64 {
+        match self {
+            Self::Piecewise { rates, .. } => rates.iter().map(|(_, rate)| *rate).fold(0.0, f64::max),
+            Self::Nonhomogeneous { max_rate, .. } => *max_rate,
+        }
+    }
+
+    /// Time from which the rate stays at 0 for good, if any
+    fn end(&self) -> Option<f64> {
+        match self {
+            Self::Piecewise { rates, period: None } => rates
+                .iter()
+                .rposition(|(_, rate)| *rate > 0.0)
+                .and_then(|last| rates.get(last + 1))
+                .map(|(start, _)| *start),
+            _ => None,
+        }
+    }
+
+    /// Next arrival after `time` using thinning, if any happens before `stop`
+    fn next_arrival(&self, time: f64, stop: Option<f64>) -> Option<f64> {
+        let max_rate = self.max_rate();
+        if max_rate <= 0.0 {
+            return None;
+        }
+
+        let stop = match (stop, self.end()) {
+            (Some(stop), Some(end)) => Some(f64::min(stop, end)),
+            (stop, end) => stop.or(end),
+        };
+
+        let candidates = Exponential::new(1.0 / max_rate);
+        let mut time = time;
+        for _ in 0..MAX_THINNING_REJECTIONS {
+            time += candidates.gen();
+            if matches!(stop, Some(stop) if time > stop) {
+                return None;
+            }
+
+            if Uniform::gen(0.0, 1.0) <= self.rate(time) / max_rate {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+}
+
+enum Arrivals {
+    Interarrival(Box<dyn Distrib>),
+    Schedule(ArrivalSchedule),
+}
+
+/// Creates entities and pushes them into a target `EntitySet`
+pub struct Source {
+    name: String,
+    arrivals: Arrivals,
+    batch_size: Option<Box<dyn DiscreteDistrib>>,
+    max_arrivals: Option<u32>,
+    start: f64,
+    stop: Option<f64>,
+    opening_hours: Option<WeeklySchedule>,
+    target: Rc<dyn EntitySet>,
+    factory: Box<dyn Fn(f64) -> Box<dyn Entity>>,
+    arrived: RefCell<u32>,
+}
+
+impl Source {
+    pub fn new(
+        name: &str,
+        interarrival: impl Distrib + 'static,
+        target: Rc<dyn EntitySet>,
+        factory: impl Fn(f64) -> Box<dyn Entity> + 'static,
+    ) -> Self {
+        Self::with_arrivals(name, Arrivals::Interarrival(Box::new(interarrival)), target, factory)
+    }
+
+    pub fn new_scheduled(
+        name: &str,
+        schedule: ArrivalSchedule,
+        target: Rc<dyn EntitySet>,
+        factory: impl Fn(f64) -> Box<dyn Entity> + 'static,
+    ) -> Result<Self> {
+        schedule.validate()?;
+        Ok(Self::with_arrivals(name, Arrivals::Schedule(schedule), target, factory))
+    }
+
+    fn with_arrivals(
+        name: &str,
+        arrivals: Arrivals,
+        target: Rc<dyn EntitySet>,
+        factory: impl Fn(f64) -> Box<dyn Entity> + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arrivals,
+            batch_size: None,
+            max_arrivals: None,
+            start: 0.0,
+            stop: None,
+            opening_hours: None,
+            target,
+            factory: Box::new(factory),
+            arrived: RefCell::new(0),
+        }
+    }
+
+    /// Number of entities created on each arrival, none when drawing 0. Fails if sizes may be
+    /// negative; without a known cdf, drawing a negative size panics instead.
+    pub fn with_batch_size(mut self, batch_size: impl DiscreteDistrib + 'static) -> Result<Self> {
+        if matches!(batch_size.cdf(-1), Some(p) if p > 0.0) {
+            return Err(anyhow!("Batch sizes of \"{}\" may be negative", self.name));
+        }
+
+        self.batch_size = Some(Box::new(batch_size));
+        Ok(self)
+    }
+
+    pub fn with_max_arrivals(mut self, max_arrivals: u32) -> Self {
+        self.max_arrivals = Some(max_arrivals);
+        self
+    }
+
+    /// Time of the first arrival, or the time the source is added to the scheduler if later
+    pub fn with_start(mut self, start: f64) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn with_stop(mut self, stop: f64) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Only create arrivals while open, according to the scheduler's calendar
+    pub fn with_opening_hours(mut self, opening_hours: WeeklySchedule) -> Self {
+        self.opening_hours = Some(opening_hours);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of entities created so far
+    pub fn arrived(&self) -> u32 {
+        *self.arrived.borrow()
+    }
+
+    fn exhausted(&self) -> bool {
+        matches!(self.max_arrivals, Some(max) if self.arrived() >= max)
+    }
+
+    fn before_stop(&self, time: f64) -> Option<f64> {
+        match self.stop {
+            Some(stop) if time > stop => None,
+            _ => Some(time),
+        }
+    }
+
+    pub(crate) fn first_arrival(&self) -> Option<f64> {
+        let start = f64::max(self.start, Scheduler::time());
+        let first = match &self.arrivals {
+            Arrivals::Interarrival(_) => self.before_stop(start)?,
+            Arrivals::Schedule(schedule) => schedule.next_arrival(start, self.stop)?,
+        };
+
+        self.when_open(first)
+    }
+
+    fn next_arrival(&self, time: f64) -> Option<f64> {
+        if self.exhausted() {
+            return None;
+        }
+
+        self.when_open(self.arrival_after(time)?)
+    }
+
+    fn arrival_after(&self, time: f64) -> Option<f64> {
+        match &self.arrivals {
+            Arrivals::Interarrival(interarrival) => self.before_stop(time + interarrival.gen()),
+            Arrivals::Schedule(schedule) => schedule.next_arrival(time, self.stop),
+        }
+    }
+
+    /// Arrivals while closed are dropped. Interarrival times restart with an arrival at the
+    /// next opening, while scheduled arrivals are drawn again from the next opening.
+    fn when_open(&self, arrival: f64) -> Option<f64> {
+        let opening_hours = match &self.opening_hours {
+            Some(opening_hours) => opening_hours,
+            None => return Some(arrival),
+        };
+
+        let calendar = Scheduler::calendar();
+        let mut arrival = arrival;
+        for _ in 0..MAX_CLOSED_ARRIVALS {
+            if opening_hours.is_open_at(&calendar, arrival) {
+                return Some(arrival);
+            }
+
+            let opening = opening_hours.next_opening_at(&calendar, arrival);
+            arrival = match &self.arrivals {
+                Arrivals::Interarrival(_) => self.before_stop(opening)?,
+                Arrivals::Schedule(schedule) => schedule.next_arrival(opening, self.stop)?,
+            };
+        }
+
+        None
+    }
+
+    fn arrive(&self) {
+        let batch_size = match &self.batch_size {
+            Some(batch_size) => match batch_size.gen() {
+                size if size >= 0 => size as u32,
+                size => panic!("\"{}\" drew a batch of {} entities", self.name, size),
+            },
+            None => 1,
+        };
+
+        for _ in 0..batch_size {
+            if self.exhausted() {
+                break;
+            }
+
+            self.target.push((self.factory)(Scheduler::time()));
+            *self.arrived.borrow_mut() += 1;
+        }
+    }
+}
+
+/// Event fired on every arrival of a `Source`, scheduling the next one
+pub(crate) struct SourceArrival {
+    pub(crate) source: Rc<Source>,
+}
+
+impl Event for SourceArrival {
+    fn name(&self) -> &str {
+        self.source.name()
+    }
+
+    fn execute(&mut self) {
+        self.source.arrive();
+
+        if let Some(next) = self.source.next_arrival(Scheduler::time()) {
+            Scheduler::instance().unwrap().schedule_at(
+                Box::new(SourceArrival {
+                    source: self.source.clone(),
+                }),
+                next,
+            );
+        }
+    }
+}
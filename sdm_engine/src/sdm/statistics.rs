@@ -0,0 +1,294 @@
+use std::cell::RefCell;
+
+use super::Scheduler;
+
+/// Custom statistic managed by the `Scheduler`, reset with every other statistic after the warm-up
+pub trait Statistic {
+    fn name(&self) -> &str;
+
+    fn mean(&self) -> f64;
+
+    /// Observations, or changes of level for time-persistent statistics, since the last reset
+    fn count(&self) -> u32;
+
+    fn min(&self) -> f64;
+
+    fn max(&self) -> f64;
+
+    fn reset(&self);
+
+    /// Called every `ANALYTICS_REFRESH` time units
+    fn update_analytics(&self) {}
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TallyState {
+    count: u32,
+    sum: f64,
+    sum_squares: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TallyState {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            sum_squares: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+/// Statistic over individual observations, e.g. the waiting time of each client
+#[derive(Debug)]
+pub struct Tally {
+    name: String,
+    state: RefCell<TallyState>,
+    observations: Option<RefCell<Vec<f64>>>,
+}
+
+impl Statistic for Tally {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 0 until a value is recorded
+    fn mean(&self) -> f64 {
+        let state = self.state.borrow();
+        if state.count == 0 {
+            return 0.0;
+        }
+
+        state.sum / state.count as f64
+    }
+
+    fn count(&self) -> u32 {
+        Tally::count(self)
+    }
+
+    fn min(&self) -> f64 {
+        Tally::min(self)
+    }
+
+    fn max(&self) -> f64 {
+        Tally::max(self)
+    }
+
+    fn reset(&self) {
+        *self.state.borrow_mut() = TallyState::default();
+        if let Some(observations) = &self.observations {
+            observations.borrow_mut().clear();
+        }
+    }
+}
+
+impl Tally {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            state: RefCell::new(TallyState::default()),
+            observations: None,
+        }
+    }
+
+    /// Also keep every recorded value, e.g. for batch means
+    pub fn with_observations(mut self) -> Self {
+        self.observations = Some(RefCell::new(vec![]));
+        self
+    }
+
+    pub fn record(&self, value: f64) {
+        let mut state = self.state.borrow_mut();
+        state.count += 1;
+        state.sum += value;
+        state.sum_squares += value * value;
+        state.min = state.min.min(value);
+        state.max = state.max.max(value);
+        if let Some(observations) = &self.observations {
+            observations.borrow_mut().push(value);
+        }
+    }
+
+    /// Every value recorded since the last reset, in order, if built `with_observations`
+    pub fn observations(&self) -> Option<Vec<f64>> {
+        self.observations.as_ref().map(|observations| observations.borrow().clone())
+    }
+
+    pub fn count(&self) -> u32 {
+        self.state.borrow().count
+    }
+
+    /// Sample variance, 0 until two values are recorded
+    pub fn variance(&self) -> f64 {
+        let state = self.state.borrow();
+        if state.count < 2 {
+            return 0.0;
+        }
+
+        let n = state.count as f64;
+        ((state.sum_squares - state.sum * state.sum / n) / (n - 1.0)).max(0.0)
+    }
+
+    /// 0 until a value is recorded
+    pub fn min(&self) -> f64 {
+        let state = self.state.borrow();
+        if state.count == 0 {
+            return 0.0;
+        }
+
+        state.min
+    }
+
+    /// 0 until a value is recorded
+    pub fn max(&self) -> f64 {
+        let state = self.state.borrow();
+        if state.count == 0 {
+            return 0.0;
+        }
+
+        state.max
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimePersistentState {
+    value: f64,
+    last_change: f64,
+    area: f64,
+    start: f64,
+    changes: u32,
+    min: f64,
+    max: f64,
+    tick_area: f64,
+    tick_time: f64,
+}
+
+/// Time-weighted statistic of a level, e.g. the number of busy tables
+#[derive(Debug)]
+pub struct TimePersistent {
+    name: String,
+    state: RefCell<TimePersistentState>,
+    series: RefCell<Vec<f64>>,
+    reset_at: RefCell<usize>, // Length of `series` at the last reset
+}
+
+impl Statistic for TimePersistent {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Time average since the last reset
+    fn mean(&self) -> f64 {
+        let now = Scheduler::time();
+        let state = self.state.borrow();
+        if now <= state.start {
+            return state.value;
+        }
+
+        self.area_until(now) / (now - state.start)
+    }
+
+    fn count(&self) -> u32 {
+        self.state.borrow().changes
+    }
+
+    fn min(&self) -> f64 {
+        self.state.borrow().min
+    }
+
+    fn max(&self) -> f64 {
+        TimePersistent::max(self)
+    }
+
+    fn reset(&self) {
+        let now = Scheduler::time();
+        let mut state = self.state.borrow_mut();
+        state.area = 0.0;
+        state.start = now;
+        state.last_change = now;
+        state.changes = 0;
+        state.min = state.value;
+        state.max = state.value;
+        state.tick_area = 0.0;
+        state.tick_time = now;
+        *self.reset_at.borrow_mut() = self.series.borrow().len();
+    }
+
+    fn update_analytics(&self) {
+        let now = Scheduler::time();
+        let area = self.area_until(now);
+
+        let mut state = self.state.borrow_mut();
+        if now > state.tick_time {
+            self.series.borrow_mut().push((area - state.tick_area) / (now - state.tick_time));
+        }
+        state.tick_area = area;
+        state.tick_time = now;
+    }
+}
+
+impl TimePersistent {
+    pub fn new(name: &str, initial: f64) -> Self {
+        let now = if Scheduler::instanciated() { Scheduler::time() } else { 0.0 };
+
+        Self {
+            name: name.to_string(),
+            state: RefCell::new(TimePersistentState {
+                value: initial,
+                last_change: now,
+                area: 0.0,
+                start: now,
+                changes: 0,
+                min: initial,
+                max: initial,
+                tick_area: 0.0,
+                tick_time: now,
+            }),
+            series: RefCell::new(vec![]),
+            reset_at: RefCell::new(0),
+        }
+    }
+
+    pub fn set(&self, value: f64) {
+        let now = Scheduler::time();
+        let mut state = self.state.borrow_mut();
+        state.area += state.value * (now - state.last_change);
+        state.last_change = now;
+        state.value = value;
+        state.changes += 1;
+        state.min = state.min.min(value);
+        state.max = state.max.max(value);
+    }
+
+    pub fn add(&self, delta: f64) {
+        let value = self.value();
+        self.set(value + delta);
+    }
+
+    pub fn value(&self) -> f64 {
+        self.state.borrow().value
+    }
+
+    pub fn max(&self) -> f64 {
+        self.state.borrow().max
+    }
+
+    /// Time average over each `ANALYTICS_REFRESH` interval since the start of the run,
+    /// including the warm-up, e.g. as one replication of a Welch plot
+    pub fn series(&self) -> Vec<f64> {
+        self.series.borrow().clone()
+    }
+
+    /// Interval averages since the last reset, e.g. for batch means
+    pub fn steady_state_series(&self) -> Vec<f64> {
+        self.series.borrow()[*self.reset_at.borrow()..].to_vec()
+    }
+
+    fn area_until(&self, time: f64) -> f64 {
+        let state = self.state.borrow();
+        state.area + state.value * (time - state.last_change)
+    }
+}
@@ -0,0 +1,364 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::Scheduler;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeUnit {
+    #[default]
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+}
+
+impl TimeUnit {
+    /// Length of one unit in seconds
+    pub fn seconds(&self) -> f64 {
+        match self {
+            Self::Seconds => 1.0,
+            Self::Minutes => 60.0,
+            Self::Hours => 3600.0,
+            Self::Days => SECONDS_PER_DAY,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Seconds => "s",
+            Self::Minutes => "min",
+            Self::Hours => "h",
+            Self::Days => "day",
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// Length of time independent of the scheduler's base unit
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Duration {
+    seconds: f64,
+}
+
+impl Duration {
+    pub fn new(value: f64, unit: TimeUnit) -> Self {
+        Self {
+            seconds: value * unit.seconds(),
+        }
+    }
+
+    pub fn seconds(value: f64) -> Self {
+        Self::new(value, TimeUnit::Seconds)
+    }
+
+    pub fn minutes(value: f64) -> Self {
+        Self::new(value, TimeUnit::Minutes)
+    }
+
+    pub fn hours(value: f64) -> Self {
+        Self::new(value, TimeUnit::Hours)
+    }
+
+    pub fn days(value: f64) -> Self {
+        Self::new(value, TimeUnit::Days)
+    }
+
+    /// Duration of `value` scheduler time units
+    pub fn from_sim(value: f64) -> Self {
+        Self::new(value, Scheduler::time_unit())
+    }
+
+    pub fn as_unit(&self, unit: TimeUnit) -> f64 {
+        self.seconds / unit.seconds()
+    }
+
+    /// Value in the scheduler's base unit, as used by `schedule_in` and distributions
+    pub fn sim(&self) -> f64 {
+        self.as_unit(Scheduler::time_unit())
+    }
+}
+
+impl Add for Duration {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            seconds: self.seconds + other.seconds,
+        }
+    }
+}
+
+impl Sub for Duration {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            seconds: self.seconds - other.seconds,
+        }
+    }
+}
+
+impl Mul<f64> for Duration {
+    type Output = Self;
+
+    fn mul(self, factor: f64) -> Self {
+        Self {
+            seconds: self.seconds * factor,
+        }
+    }
+}
+
+impl Div<f64> for Duration {
+    type Output = Self;
+
+    fn div(self, divisor: f64) -> Self {
+        Self {
+            seconds: self.seconds / divisor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+}
+
+// Civil calendar conversions, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = (if days >= 0 { days } else { days - 146096 }) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = (if month_index < 10 { month_index + 3 } else { month_index - 9 }) as u32;
+
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+/// Wall-clock date and time, without time zones
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct DateTime {
+    seconds: f64, // Since 1970-01-01 00:00:00
+}
+
+impl DateTime {
+    pub fn new(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: f64) -> Result<Self> {
+        if !(1..=12).contains(&month) {
+            return Err(anyhow!("Invalid month {}", month));
+        }
+
+        let days = days_from_civil(year, month, day);
+        if day == 0 || civil_from_days(days) != (year, month, day) {
+            return Err(anyhow!("Invalid day {} for {}-{:02}", day, year, month));
+        }
+
+        if hour >= 24 || minute >= 60 || !(0.0..60.0).contains(&second) {
+            return Err(anyhow!("Invalid time {:02}:{:02}:{:02}", hour, minute, second));
+        }
+
+        Ok(Self {
+            seconds: days as f64 * SECONDS_PER_DAY + (hour * 3600 + minute * 60) as f64 + second,
+        })
+    }
+
+    fn days(&self) -> i64 {
+        (self.seconds / SECONDS_PER_DAY).floor() as i64
+    }
+
+    /// Midnight of the same day
+    pub fn midnight(&self) -> Self {
+        Self {
+            seconds: self.days() as f64 * SECONDS_PER_DAY,
+        }
+    }
+
+    /// `(year, month, day)`
+    pub fn date(&self) -> (i64, u32, u32) {
+        civil_from_days(self.days())
+    }
+
+    pub fn time_of_day(&self) -> Duration {
+        Duration::seconds(self.seconds - self.midnight().seconds)
+    }
+
+    pub fn hour(&self) -> u32 {
+        (self.time_of_day().seconds / 3600.0) as u32
+    }
+
+    pub fn minute(&self) -> u32 {
+        (self.time_of_day().seconds % 3600.0 / 60.0) as u32
+    }
+
+    pub fn second(&self) -> f64 {
+        self.time_of_day().seconds % 60.0
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        // 1970-01-01 was a Thursday
+        Weekday::ALL[(self.days() + 3).rem_euclid(7) as usize]
+    }
+}
+
+impl Add<Duration> for DateTime {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        Self {
+            seconds: self.seconds + duration.seconds,
+        }
+    }
+}
+
+impl Sub for DateTime {
+    type Output = Duration;
+
+    fn sub(self, other: Self) -> Duration {
+        Duration::seconds(self.seconds - other.seconds)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = self.date();
+        write!(
+            f,
+            "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            self.hour(),
+            self.minute(),
+            self.second() as u32
+        )
+    }
+}
+
+/// Maps simulation time to wall-clock date and time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calendar {
+    start: DateTime,
+    unit: TimeUnit,
+}
+
+impl Calendar {
+    /// `start` is the date and time at simulation time zero
+    pub fn new(start: DateTime, unit: TimeUnit) -> Self {
+        Self { start, unit }
+    }
+
+    pub fn start(&self) -> DateTime {
+        self.start
+    }
+
+    pub fn unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    pub fn date_time(&self, time: f64) -> DateTime {
+        self.start + Duration::new(time, self.unit)
+    }
+
+    pub fn time(&self, date_time: DateTime) -> f64 {
+        (date_time - self.start).as_unit(self.unit)
+    }
+}
+
+/// Daily opening hours, e.g. 11:00 to 23:00 except on Mondays
+#[derive(Debug, Clone)]
+pub struct WeeklySchedule {
+    open: Duration,
+    close: Duration,
+    closed_days: Vec<Weekday>,
+}
+
+impl WeeklySchedule {
+    /// `open` and `close` are times of day
+    pub fn new(open: Duration, close: Duration, closed_days: &[Weekday]) -> Result<Self> {
+        if open < Duration::default() || open >= close || close > Duration::days(1.0) {
+            return Err(anyhow!("WeeklySchedule requires 00:00 <= open < close <= 24:00"));
+        }
+
+        if Weekday::ALL.iter().all(|day| closed_days.contains(day)) {
+            return Err(anyhow!("WeeklySchedule must be open at least one day a week"));
+        }
+
+        Ok(Self {
+            open,
+            close,
+            closed_days: closed_days.to_vec(),
+        })
+    }
+
+    pub fn is_open(&self, at: DateTime) -> bool {
+        let time_of_day = at.time_of_day();
+        !self.closed_days.contains(&at.weekday()) && self.open <= time_of_day && time_of_day < self.close
+    }
+
+    /// `at` itself if open, otherwise the next time the schedule opens
+    pub fn next_opening(&self, at: DateTime) -> DateTime {
+        if self.is_open(at) {
+            return at;
+        }
+
+        let today = at.midnight();
+        if !self.closed_days.contains(&at.weekday()) && at.time_of_day() < self.open {
+            return today + self.open;
+        }
+
+        let mut day = today;
+        loop {
+            day = day + Duration::days(1.0);
+            if !self.closed_days.contains(&day.weekday()) {
+                return day + self.open;
+            }
+        }
+    }
+
+    /// Simulation-time variants using `calendar`
+    pub fn is_open_at(&self, calendar: &Calendar, time: f64) -> bool {
+        self.is_open(calendar.date_time(time))
+    }
+
+    pub fn next_opening_at(&self, calendar: &Calendar, time: f64) -> f64 {
+        calendar.time(self.next_opening(calendar.date_time(time)))
+    }
+}
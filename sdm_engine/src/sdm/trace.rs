@@ -0,0 +1,565 @@
+use anyhow::{anyhow, Context, Result};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::observer::Observer;
+use super::{Entity, Event, Process, Scheduler};
+
+const MAGIC: &[u8; 8] = b"SDMTRACE";
+const VERSION: u8 = 2;
+
+/// FEL operation or state change. Entities are numbered in order of appearance, so traces
+/// of runs with the same seed are identical.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceRecord {
+    EventScheduled(String, f64), // Event and the time it is scheduled at
+    EventExecuted(String),
+    EventCancelled(String, f64),
+    ProcessScheduled(String, f64), // Process and the time it is queued to activate at
+    ProcessActivated(String, f64),
+    ProcessStarted(String, f64), // Process and the duration of the cycle
+    ProcessEnded(String),
+    EntityPushed(String, u32), // Entity set and entity
+    EntityPopped(String, u32),
+    EntitiesMatched(String),
+    ResourceAllocated(String, i32),
+    ResourceReleased(String, i32),
+    ClockAdvanced,
+    WarmUpEnded,
+    StepCompleted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub time: f64,
+    pub record: TraceRecord,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} - ", self.time)?;
+        match &self.record {
+            TraceRecord::EventScheduled(event, time) => write!(f, "Scheduled \"{}\" at {:.2}", event, time),
+            TraceRecord::EventExecuted(event) => write!(f, "Executed \"{}\"", event),
+            TraceRecord::EventCancelled(event, time) => write!(f, "Cancelled \"{}\" at {:.2}", event, time),
+            TraceRecord::ProcessScheduled(process, time) => write!(f, "Queued \"{}\" at {:.2}", process, time),
+            TraceRecord::ProcessActivated(process, time) => write!(f, "Activated \"{}\" at {:.2}", process, time),
+            TraceRecord::ProcessStarted(process, duration) => {
+                write!(f, "Started \"{}\" for {:.2}", process, duration)
+            }
+            TraceRecord::ProcessEnded(process) => write!(f, "Ended \"{}\"", process),
+            TraceRecord::EntityPushed(entity_set, entity) => write!(f, "Pushed #{} to \"{}\"", entity, entity_set),
+            TraceRecord::EntityPopped(entity_set, entity) => write!(f, "Popped #{} from \"{}\"", entity, entity_set),
+            TraceRecord::EntitiesMatched(matcher) => write!(f, "Matched on \"{}\"", matcher),
+            TraceRecord::ResourceAllocated(resource, quantity) => write!(f, "Allocated {} {}", quantity, resource),
+            TraceRecord::ResourceReleased(resource, quantity) => write!(f, "Released {} {}", quantity, resource),
+            TraceRecord::ClockAdvanced => write!(f, "Clock advanced"),
+            TraceRecord::WarmUpEnded => write!(f, "Warm-up ended"),
+            TraceRecord::StepCompleted => write!(f, "Step completed"),
+        }
+    }
+}
+
+/// First entry at which two traces differ, `None` on the side that ended first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub left: Option<TraceEntry>,
+    pub right: Option<TraceEntry>,
+}
+
+/// Recorded run, stored in a compact binary format: one tagged entry per record, each name
+/// spelled out where it first appears
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries matching `predicate`, e.g. to assert on them in tests
+    pub fn filter(&self, predicate: impl Fn(&TraceEntry) -> bool) -> Vec<&TraceEntry> {
+        self.entries.iter().filter(|entry| predicate(entry)).collect()
+    }
+
+    /// Where the runs recorded in `self` and `other` stop behaving the same, if anywhere
+    pub fn diff(&self, other: &Trace) -> Option<Divergence> {
+        let index = (0..usize::max(self.len(), other.len()))
+            .find(|&i| self.entries.get(i) != other.entries.get(i))?;
+
+        Some(Divergence {
+            index,
+            left: self.entries.get(index).cloned(),
+            right: other.entries.get(index).cloned(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header();
+        let mut encoder = Encoder::default();
+        for entry in self.entries.iter() {
+            encoder.encode(entry, &mut bytes);
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(anyhow!("Not a simulation trace"));
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(anyhow!("Unsupported trace version {}", version));
+        }
+
+        let mut strings = vec![];
+        let mut entries = vec![];
+        while reader.position < bytes.len() {
+            let tag = reader.take(1)?[0];
+            let time = reader.f64()?;
+            let record = match tag {
+                0 => TraceRecord::EventScheduled(reader.name(&mut strings)?, reader.f64()?),
+                1 => TraceRecord::EventExecuted(reader.name(&mut strings)?),
+                2 => TraceRecord::EventCancelled(reader.name(&mut strings)?, reader.f64()?),
+                3 => TraceRecord::ProcessActivated(reader.name(&mut strings)?, reader.f64()?),
+                4 => TraceRecord::ProcessStarted(reader.name(&mut strings)?, reader.f64()?),
+                5 => TraceRecord::ProcessEnded(reader.name(&mut strings)?),
+                6 => TraceRecord::EntityPushed(reader.name(&mut strings)?, reader.u64()? as u32),
+                7 => TraceRecord::EntityPopped(reader.name(&mut strings)?, reader.u64()? as u32),
+                8 => TraceRecord::EntitiesMatched(reader.name(&mut strings)?),
+                9 => TraceRecord::ResourceAllocated(reader.name(&mut strings)?, reader.u64()? as u32 as i32),
+                10 => TraceRecord::ResourceReleased(reader.name(&mut strings)?, reader.u64()? as u32 as i32),
+                11 => TraceRecord::ClockAdvanced,
+                12 => TraceRecord::WarmUpEnded,
+                13 => TraceRecord::StepCompleted,
+                14 => TraceRecord::ProcessScheduled(reader.name(&mut strings)?, reader.f64()?),
+                _ => return Err(anyhow!("Invalid trace: unknown record {}", tag)),
+            };
+            entries.push(TraceEntry { time, record });
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes()).with_context(|| format!("Could not write \"{}\"", path.display()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("Could not read \"{}\"", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Could not load \"{}\"", path.display()))
+    }
+}
+
+fn header() -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes
+}
+
+// Encodes one tagged entry at a time, so a trace can be written as it is recorded. Names
+// are numbered in order of appearance and spelled out the first time they are used.
+#[derive(Debug, Default)]
+struct Encoder {
+    strings: HashMap<String, u32>,
+}
+
+impl Encoder {
+    fn encode(&mut self, entry: &TraceEntry, bytes: &mut Vec<u8>) {
+        let (tag, name, value) = match &entry.record {
+            TraceRecord::EventScheduled(name, time) => (0, Some(name), Some(time.to_bits())),
+            TraceRecord::EventExecuted(name) => (1, Some(name), None),
+            TraceRecord::EventCancelled(name, time) => (2, Some(name), Some(time.to_bits())),
+            TraceRecord::ProcessActivated(name, time) => (3, Some(name), Some(time.to_bits())),
+            TraceRecord::ProcessStarted(name, duration) => (4, Some(name), Some(duration.to_bits())),
+            TraceRecord::ProcessEnded(name) => (5, Some(name), None),
+            TraceRecord::EntityPushed(name, entity) => (6, Some(name), Some(*entity as u64)),
+            TraceRecord::EntityPopped(name, entity) => (7, Some(name), Some(*entity as u64)),
+            TraceRecord::EntitiesMatched(name) => (8, Some(name), None),
+            TraceRecord::ResourceAllocated(name, quantity) => (9, Some(name), Some(*quantity as u64)),
+            TraceRecord::ResourceReleased(name, quantity) => (10, Some(name), Some(*quantity as u64)),
+            TraceRecord::ClockAdvanced => (11, None, None),
+            TraceRecord::WarmUpEnded => (12, None, None),
+            TraceRecord::StepCompleted => (13, None, None),
+            TraceRecord::ProcessScheduled(name, time) => (14, Some(name), Some(time.to_bits())),
+        };
+
+        bytes.push(tag);
+        bytes.extend(entry.time.to_le_bytes());
+        if let Some(name) = name {
+            match self.strings.get(name) {
+                Some(index) => bytes.extend(index.to_le_bytes()),
+                None => {
+                    let index = self.strings.len() as u32;
+                    self.strings.insert(name.clone(), index);
+                    bytes.extend(index.to_le_bytes());
+                    bytes.extend((name.len() as u32).to_le_bytes());
+                    bytes.extend(name.as_bytes());
+                }
+            }
+        }
+        if let Some(value) = value {
+            bytes.extend(value.to_le_bytes());
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| anyhow!("Invalid trace: truncated at byte {}", self.position))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // A name is spelled out the first time it is used
+    fn name(&mut self, strings: &mut Vec<String>) -> Result<String> {
+        let i = self.u32()? as usize;
+        if i == strings.len() {
+            let length = self.u32()? as usize;
+            let string = String::from_utf8(self.take(length)?.to_vec()).context("Invalid trace: name is not UTF-8")?;
+            strings.push(string);
+        }
+
+        strings
+            .get(i)
+            .cloned()
+            .ok_or_else(|| anyhow!("Invalid trace: unknown name {}", i))
+    }
+}
+
+/// Observer recording every FEL operation and state change of a run, in memory or
+/// streamed to a file
+#[derive(Debug, Default)]
+pub struct TraceRecorder {
+    entries: RefCell<Vec<TraceEntry>>,
+    entities: RefCell<HashMap<Uuid, u32>>,
+    output: Option<RefCell<TraceOutput>>,
+}
+
+#[derive(Debug)]
+struct TraceOutput {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    encoder: Encoder,
+    error: Option<std::io::Error>, // First write that failed, reported by `flush`
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes every entry to `path` as it is recorded instead of keeping it in memory. The
+    /// file is flushed at the end of every step and when the recorder is dropped, so it
+    /// holds every completed step even if the run is interrupted.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut writer =
+            BufWriter::new(File::create(path).with_context(|| format!("Could not create \"{}\"", path.display()))?);
+        writer
+            .write_all(&header())
+            .with_context(|| format!("Could not write \"{}\"", path.display()))?;
+
+        Ok(Self {
+            output: Some(RefCell::new(TraceOutput {
+                path: path.to_path_buf(),
+                writer,
+                encoder: Encoder::default(),
+                error: None,
+            })),
+            ..Self::default()
+        })
+    }
+
+    /// Entries recorded so far, none when streaming to a file: load it with `Trace::load`
+    pub fn trace(&self) -> Trace {
+        Trace {
+            entries: self.entries.borrow().clone(),
+        }
+    }
+
+    /// Writes buffered entries to the file, failing if any write so far failed
+    pub fn flush(&self) -> Result<()> {
+        if let Some(output) = &self.output {
+            let mut output = output.borrow_mut();
+            if output.error.is_none() {
+                output.error = output.writer.flush().err();
+            }
+            if let Some(error) = &output.error {
+                return Err(anyhow!("Could not write \"{}\": {}", output.path.display(), error));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record(&self, record: TraceRecord) {
+        let entry = TraceEntry {
+            time: Scheduler::time(),
+            record,
+        };
+
+        match &self.output {
+            Some(output) => {
+                let mut output = output.borrow_mut();
+                if output.error.is_none() {
+                    let mut bytes = vec![];
+                    output.encoder.encode(&entry, &mut bytes);
+                    output.error = output.writer.write_all(&bytes).err();
+                }
+            }
+            None => self.entries.borrow_mut().push(entry),
+        }
+    }
+
+    fn entity(&self, entity: &dyn Entity) -> u32 {
+        let mut entities = self.entities.borrow_mut();
+        let next = entities.len() as u32;
+        *entities.entry(*entity.id()).or_insert(next)
+    }
+}
+
+impl Observer for TraceRecorder {
+    fn on_event_scheduled(&self, event: &dyn Event, time: f64) {
+        self.record(TraceRecord::EventScheduled(event.name().to_string(), time));
+    }
+
+    fn on_event_executed(&self, event: &dyn Event) {
+        self.record(TraceRecord::EventExecuted(event.name().to_string()));
+    }
+
+    fn on_event_cancelled(&self, event: &dyn Event, time: f64) {
+        self.record(TraceRecord::EventCancelled(event.name().to_string(), time));
+    }
+
+    fn on_process_scheduled(&self, process: &dyn Process, time: f64) {
+        self.record(TraceRecord::ProcessScheduled(process.name().to_string(), time));
+    }
+
+    fn on_process_activated(&self, process: &dyn Process, time: f64) {
+        self.record(TraceRecord::ProcessActivated(process.name().to_string(), time));
+    }
+
+    fn on_process_started(&self, process: &dyn Process, duration: f64) {
+        self.record(TraceRecord::ProcessStarted(process.name().to_string(), duration));
+    }
+
+    fn on_process_ended(&self, process: &dyn Process) {
+        self.record(TraceRecord::ProcessEnded(process.name().to_string()));
+    }
+
+    fn on_entity_pushed(&self, entity_set: &str, entity: &dyn Entity) {
+        self.record(TraceRecord::EntityPushed(entity_set.to_string(), self.entity(entity)));
+    }
+
+    fn on_entity_popped(&self, entity_set: &str, entity: &dyn Entity) {
+        self.record(TraceRecord::EntityPopped(entity_set.to_string(), self.entity(entity)));
+    }
+
+    fn on_entities_matched(&self, matcher: &str) {
+        self.record(TraceRecord::EntitiesMatched(matcher.to_string()));
+    }
+
+    fn on_resource_allocated(&self, resource: &str, quantity: i32) {
+        self.record(TraceRecord::ResourceAllocated(resource.to_string(), quantity));
+    }
+
+    fn on_resource_released(&self, resource: &str, quantity: i32) {
+        self.record(TraceRecord::ResourceReleased(resource.to_string(), quantity));
+    }
+
+    fn on_clock_advanced(&self, _from: f64, _to: f64) {
+        self.record(TraceRecord::ClockAdvanced);
+    }
+
+    fn on_warm_up_ended(&self) {
+        self.record(TraceRecord::WarmUpEnded);
+    }
+
+    fn on_step_completed(&self, _events: usize, _process_callbacks: usize) {
+        self.record(TraceRecord::StepCompleted);
+        // Errors are kept and reported by the next explicit `flush`
+        let _ = self.flush();
+    }
+}
+
+/// Model state rebuilt from a trace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayState {
+    pub time: f64,
+    pub events: Vec<(f64, String)>,             // FEL, soonest first
+    pub process_queue: Vec<(f64, String)>,      // Processes queued to activate, soonest first
+    pub processes: Vec<String>,                 // Activated processes
+    pub process_callbacks: Vec<(f64, String)>,  // Cycles in progress and their end, soonest first
+    pub entity_sets: BTreeMap<String, Vec<u32>>, // Entities in each set
+    pub resources: BTreeMap<String, i32>,       // Units allocated of each resource
+}
+
+impl ReplayState {
+    fn apply(&mut self, entry: &TraceEntry) {
+        self.time = entry.time;
+
+        let remove = |list: &mut Vec<(f64, String)>, time: f64, name: &str| {
+            if let Some(i) = list.iter().position(|(known_time, known)| *known_time == time && known == name) {
+                list.remove(i);
+            }
+        };
+        let insert = |list: &mut Vec<(f64, String)>, time: f64, name: &str| {
+            let i = list.iter().position(|(known_time, _)| *known_time > time).unwrap_or(list.len());
+            list.insert(i, (time, name.to_string()));
+        };
+
+        match &entry.record {
+            TraceRecord::EventScheduled(event, time) => insert(&mut self.events, *time, event),
+            TraceRecord::EventExecuted(event) => remove(&mut self.events, entry.time, event),
+            TraceRecord::EventCancelled(event, time) => remove(&mut self.events, *time, event),
+            TraceRecord::ProcessScheduled(process, time) => insert(&mut self.process_queue, *time, process),
+            TraceRecord::ProcessActivated(process, time) => {
+                remove(&mut self.process_queue, *time, process);
+                self.processes.push(process.clone())
+            }
+            TraceRecord::ProcessStarted(process, duration) => {
+                insert(&mut self.process_callbacks, entry.time + duration, process)
+            }
+            TraceRecord::ProcessEnded(process) => remove(&mut self.process_callbacks, entry.time, process),
+            TraceRecord::EntityPushed(entity_set, entity) => {
+                self.entity_sets.entry(entity_set.clone()).or_default().push(*entity)
+            }
+            TraceRecord::EntityPopped(entity_set, entity) => {
+                if let Some(entities) = self.entity_sets.get_mut(entity_set) {
+                    entities.retain(|known| known != entity);
+                }
+            }
+            TraceRecord::ResourceAllocated(resource, quantity) => {
+                *self.resources.entry(resource.clone()).or_default() += quantity
+            }
+            TraceRecord::ResourceReleased(resource, quantity) => {
+                *self.resources.entry(resource.clone()).or_default() -= quantity
+            }
+            TraceRecord::EntitiesMatched(_)
+            | TraceRecord::ClockAdvanced
+            | TraceRecord::WarmUpEnded
+            | TraceRecord::StepCompleted => {}
+        }
+    }
+}
+
+/// Offline replay of a trace, stepping back and forth one entry or one step at a time
+#[derive(Debug, Clone)]
+pub struct Replay {
+    trace: Trace,
+    position: usize, // Entries applied to the state
+    state: ReplayState,
+}
+
+impl Replay {
+    pub fn new(trace: Trace) -> Self {
+        Self {
+            trace,
+            position: 0,
+            state: ReplayState::default(),
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn state(&self) -> &ReplayState {
+        &self.state
+    }
+
+    /// Last entry applied
+    pub fn current(&self) -> Option<&TraceEntry> {
+        self.position.checked_sub(1).map(|i| &self.trace.entries[i])
+    }
+
+    pub fn forward(&mut self) -> Option<&TraceEntry> {
+        let entry = self.trace.entries.get(self.position)?;
+        self.state.apply(entry);
+        self.position += 1;
+        Some(entry)
+    }
+
+    /// Undoes the last entry, returning the one now current
+    pub fn back(&mut self) -> Option<&TraceEntry> {
+        self.seek(self.position.checked_sub(1)?);
+        self.current()
+    }
+
+    /// State after the first `position` entries, rebuilt from the start when going back
+    pub fn seek(&mut self, position: usize) {
+        let position = usize::min(position, self.trace.len());
+        if position < self.position {
+            self.position = 0;
+            self.state = ReplayState::default();
+        }
+        while self.position < position {
+            self.forward();
+        }
+    }
+
+    /// State once every entry up to `time` is applied
+    pub fn seek_time(&mut self, time: f64) {
+        let position = self.trace.entries.iter().take_while(|entry| entry.time <= time).count();
+        self.seek(position);
+    }
+
+    /// Moves to the end of the next step, or of the trace
+    pub fn next_step(&mut self) {
+        while let Some(entry) = self.forward() {
+            if entry.record == TraceRecord::StepCompleted {
+                break;
+            }
+        }
+    }
+
+    /// Moves to the end of the previous step, or to the start of the trace
+    pub fn previous_step(&mut self) {
+        let position = self.trace.entries[..self.position.saturating_sub(1)]
+            .iter()
+            .rposition(|entry| entry.record == TraceRecord::StepCompleted)
+            .map_or(0, |i| i + 1);
+        self.seek(position);
+    }
+}